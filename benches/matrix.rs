@@ -1,11 +1,11 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use ray_tracer_rs::matrix::Matrix4x4;
+use ray_tracer_rs::{float::Float, matrix::Matrix4x4};
 
-fn matrix_4x4_inverse(data: &[f64; 16]) {
+fn matrix_4x4_inverse(data: &[Float; 16]) {
     Matrix4x4::new(*data).inverse();
 }
 
-fn matrix_4x4_det(data: &[f64; 16]) {
+fn matrix_4x4_det(data: &[Float; 16]) {
     Matrix4x4::new(*data).det();
 }
 
@@ -20,6 +20,16 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
 }
 
+fn affine_inverse(c: &mut Criterion) {
+    c.bench_function("Matrix4x4 affine inverse", |b| {
+        b.iter(|| {
+            matrix_4x4_inverse(black_box(&[
+                2.0, 0.5, 0.0, 4.0, 0.0, 1.0, -1.0, 5.0, 0.3, 0.0, 3.0, 6.0, 0.0, 0.0, 0.0, 1.0,
+            ]))
+        })
+    });
+}
+
 fn matrix_det(c: &mut Criterion) {
     c.bench_function("Matrix4x4 det", |b| {
         b.iter(|| {
@@ -31,5 +41,5 @@ fn matrix_det(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, criterion_benchmark, matrix_det);
+criterion_group!(benches, criterion_benchmark, affine_inverse, matrix_det);
 criterion_main!(benches);
@@ -0,0 +1,189 @@
+use crate::{float::Float, matrix::Matrix4x4, ray::Ray, tuple::Tuple4};
+
+/// Axis-aligned box, used to skip shapes a ray cannot hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    min: Tuple4,
+    max: Tuple4,
+}
+
+impl BoundingBox {
+    pub fn new(min: Tuple4, max: Tuple4) -> Self {
+        BoundingBox { min, max }
+    }
+
+    /// A box containing nothing, which merges into any other box unchanged.
+    pub fn empty() -> Self {
+        BoundingBox {
+            min: Tuple4::point(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+            max: Tuple4::point(
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+            ),
+        }
+    }
+
+    pub fn get_min(&self) -> Tuple4 {
+        self.min
+    }
+
+    pub fn get_max(&self) -> Tuple4 {
+        self.max
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn center(&self) -> Tuple4 {
+        self.min + (self.max - self.min) * 0.5
+    }
+
+    pub fn add_point(&mut self, point: Tuple4) {
+        self.min = Tuple4::point(
+            self.min.x.min(point.x),
+            self.min.y.min(point.y),
+            self.min.z.min(point.z),
+        );
+        self.max = Tuple4::point(
+            self.max.x.max(point.x),
+            self.max.y.max(point.y),
+            self.max.z.max(point.z),
+        );
+    }
+
+    pub fn merge(&self, other: &BoundingBox) -> Self {
+        let mut merged = *self;
+        merged.add_point(other.min);
+        merged.add_point(other.max);
+        merged
+    }
+
+    pub fn contains_point(&self, point: Tuple4) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x)
+            && (self.min.y..=self.max.y).contains(&point.y)
+            && (self.min.z..=self.max.z).contains(&point.z)
+    }
+
+    /// Smallest box around all eight transformed corners. It can be looser
+    /// than the box of the transformed contents.
+    pub fn transform(&self, m: &Matrix4x4) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        let mut transformed = BoundingBox::empty();
+        for x in [self.min.x, self.max.x] {
+            for y in [self.min.y, self.max.y] {
+                for z in [self.min.z, self.max.z] {
+                    transformed.add_point(m * Tuple4::point(x, y, z));
+                }
+            }
+        }
+
+        transformed
+    }
+
+    /// Distances along `ray` where it enters and leaves the box, or `None`
+    /// when it misses. The entry may be negative if the ray starts inside.
+    pub fn intersect(&self, ray: &Ray) -> Option<(Float, Float)> {
+        let axes = [
+            (ray.origin.x, ray.direction.x, self.min.x, self.max.x),
+            (ray.origin.y, ray.direction.y, self.min.y, self.max.y),
+            (ray.origin.z, ray.direction.z, self.min.z, self.max.z),
+        ];
+        let mut t_min = Float::NEG_INFINITY;
+        let mut t_max = Float::INFINITY;
+        for (origin, direction, min, max) in axes {
+            let (t0, t1) = if direction == 0.0 {
+                if origin < min || origin > max {
+                    return None;
+                }
+                (Float::NEG_INFINITY, Float::INFINITY)
+            } else {
+                let t0 = (min - origin) / direction;
+                let t1 = (max - origin) / direction;
+                (t0.min(t1), t0.max(t1))
+            };
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+        }
+
+        if t_min > t_max || t_max < 0.0 {
+            None
+        } else {
+            Some((t_min, t_max))
+        }
+    }
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        BoundingBox::empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float::{
+        consts::{PI, SQRT_2},
+        EPSILON,
+    };
+
+    use super::*;
+
+    fn unit_box() -> BoundingBox {
+        BoundingBox::new(
+            Tuple4::point(-1.0, -1.0, -1.0),
+            Tuple4::point(1.0, 1.0, 1.0),
+        )
+    }
+
+    #[test]
+    fn test_merging_boxes() {
+        let a = BoundingBox::new(Tuple4::point(-5.0, -2.0, 0.0), Tuple4::point(7.0, 4.0, 4.0));
+        let b = BoundingBox::new(
+            Tuple4::point(8.0, -7.0, -2.0),
+            Tuple4::point(14.0, 2.0, 8.0),
+        );
+
+        let merged = a.merge(&b);
+
+        assert_eq!(merged.get_min(), Tuple4::point(-5.0, -7.0, -2.0));
+        assert_eq!(merged.get_max(), Tuple4::point(14.0, 4.0, 8.0));
+        assert_eq!(BoundingBox::empty().merge(&a), a);
+        assert!(BoundingBox::empty().is_empty());
+    }
+
+    #[test]
+    fn test_transforming_a_box() {
+        let m = Matrix4x4::translation(0.0, 0.0, 5.0) * Matrix4x4::rotation_z(PI / 4.0);
+
+        let transformed = unit_box().transform(&m);
+
+        let (min, max) = (transformed.get_min(), transformed.get_max());
+        assert!((min.x + SQRT_2).abs() < EPSILON && (max.x - SQRT_2).abs() < EPSILON);
+        assert!((min.y + SQRT_2).abs() < EPSILON && (max.y - SQRT_2).abs() < EPSILON);
+        assert!((min.z - 4.0).abs() < EPSILON && (max.z - 6.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_intersecting_a_ray_with_a_box() {
+        let hit = Ray::new(Tuple4::point(5.0, 0.5, 0.0), Tuple4::vector(-1.0, 0.0, 0.0));
+        let inside = Ray::new(Tuple4::point(0.0, 0.5, 0.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let miss = Ray::new(Tuple4::point(2.0, 0.0, 2.0), Tuple4::vector(0.0, 0.0, -1.0));
+        let behind = Ray::new(Tuple4::point(0.0, 0.0, 3.0), Tuple4::vector(0.0, 0.0, 1.0));
+
+        let (t0, t1) = unit_box().intersect(&hit).unwrap();
+        assert!((t0 - 4.0).abs() < EPSILON && (t1 - 6.0).abs() < EPSILON);
+        assert_eq!(unit_box().intersect(&inside), Some((-1.0, 1.0)));
+        assert_eq!(unit_box().intersect(&miss), None);
+        assert_eq!(unit_box().intersect(&behind), None);
+    }
+
+    #[test]
+    fn test_box_contains_points() {
+        assert!(unit_box().contains_point(Tuple4::point(1.0, 0.0, -0.5)));
+        assert!(!unit_box().contains_point(Tuple4::point(1.5, 0.0, 0.0)));
+    }
+}
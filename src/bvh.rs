@@ -0,0 +1,512 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read, Write},
+    mem,
+};
+
+use crate::{bounds::BoundingBox, float::Float, ray::Ray, tuple::Tuple4};
+
+const MAX_LEAF_SIZE: usize = 4;
+/// Deepest hierarchy `traverse` can walk with its fixed-size stack. Halving
+/// at every branch stays far below it for any list that fits in memory.
+const MAX_DEPTH: usize = 64;
+const FILE_MAGIC: &[u8; 4] = b"BVH1";
+
+#[derive(Debug)]
+pub enum BvhError {
+    Io(io::Error),
+    Format(String),
+    /// The file was built for different boxes than the ones given.
+    Stale,
+}
+
+impl fmt::Display for BvhError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BvhError::Io(e) => write!(f, "could not read or write hierarchy: {}", e),
+            BvhError::Format(message) => write!(f, "invalid hierarchy file: {}", message),
+            BvhError::Stale => write!(f, "hierarchy file does not match the scene"),
+        }
+    }
+}
+
+impl Error for BvhError {}
+
+impl From<io::Error> for BvhError {
+    fn from(e: io::Error) -> Self {
+        BvhError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum BvhNode {
+    Leaf {
+        bounds: BoundingBox,
+        start: usize,
+        count: usize,
+    },
+    Branch {
+        bounds: BoundingBox,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl BvhNode {
+    fn bounds(&self) -> &BoundingBox {
+        match self {
+            BvhNode::Leaf { bounds, .. } | BvhNode::Branch { bounds, .. } => bounds,
+        }
+    }
+}
+
+/// Bounding volume hierarchy over a list of boxes. Each branch splits its
+/// boxes in half along the longest axis of their centers, so a ray only
+/// visits the shapes whose boxes it passes through.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn new(bounds: &[BoundingBox]) -> Self {
+        let mut bvh = Bvh {
+            nodes: Vec::new(),
+            indices: (0..bounds.len()).collect(),
+        };
+        if !bounds.is_empty() {
+            bvh.build(bounds, 0, bounds.len());
+        }
+
+        bvh
+    }
+
+    pub fn get_bounds(&self) -> BoundingBox {
+        self.nodes
+            .first()
+            .map_or(BoundingBox::empty(), |node| *node.bounds())
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Calls `visit` with the index of every box in a leaf the ray passes
+    /// through. Boxes entirely behind the ray's origin are skipped. The
+    /// nodes still to visit are kept on the call stack, so no ray allocates.
+    pub fn traverse<F: FnMut(usize)>(&self, ray: &Ray, mut visit: F) {
+        self.any(ray, |index| {
+            visit(index);
+            false
+        });
+    }
+
+    /// Same walk as `traverse`, stopping at the first box `visit` returns
+    /// true for. Returns whether there was one.
+    pub fn any<F: FnMut(usize) -> bool>(&self, ray: &Ray, mut visit: F) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let mut stack = [0; MAX_DEPTH + 1];
+        let mut len = 1;
+        while len > 0 {
+            len -= 1;
+            let node = &self.nodes[stack[len]];
+            if node.bounds().intersect(ray).is_none() {
+                continue;
+            }
+            match *node {
+                BvhNode::Leaf { start, count, .. } => {
+                    if self.indices[start..start + count]
+                        .iter()
+                        .any(|&index| visit(index))
+                    {
+                        return true;
+                    }
+                }
+                BvhNode::Branch { left, right, .. } => {
+                    stack[len] = right;
+                    stack[len + 1] = left;
+                    len += 2;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Saves the hierarchy along with a hash of the `bounds` it was built
+    /// from, so `read` can tell whether it still fits.
+    pub fn write<W: Write>(&self, writer: &mut W, bounds: &[BoundingBox]) -> io::Result<()> {
+        let mut bytes = FILE_MAGIC.to_vec();
+        bytes.push(mem::size_of::<Float>() as u8);
+        bytes.extend_from_slice(&content_hash(bounds).to_le_bytes());
+        for n in [self.nodes.len(), self.indices.len()] {
+            bytes.extend_from_slice(&(n as u64).to_le_bytes());
+        }
+        for &i in &self.indices {
+            bytes.extend_from_slice(&(i as u64).to_le_bytes());
+        }
+        for node in &self.nodes {
+            let (tag, a, b) = match *node {
+                BvhNode::Leaf { start, count, .. } => (0, start, count),
+                BvhNode::Branch { left, right, .. } => (1, left, right),
+            };
+            bytes.push(tag);
+            push_box(&mut bytes, node.bounds());
+            bytes.extend_from_slice(&(a as u64).to_le_bytes());
+            bytes.extend_from_slice(&(b as u64).to_le_bytes());
+        }
+
+        writer.write_all(&bytes)
+    }
+
+    /// Loads a hierarchy saved by `write` for exactly these `bounds`,
+    /// skipping the build.
+    pub fn read<R: Read>(reader: &mut R, bounds: &[BoundingBox]) -> Result<Self, BvhError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut input = Input { bytes: &bytes };
+
+        if input.take(4)? != FILE_MAGIC {
+            return Err(BvhError::Format("not a hierarchy file".to_string()));
+        }
+        if input.take(1)?[0] as usize != mem::size_of::<Float>() {
+            return Err(BvhError::Format(
+                "saved with another float width".to_string(),
+            ));
+        }
+        if input.u64()? != content_hash(bounds) {
+            return Err(BvhError::Stale);
+        }
+        let (node_count, index_count) = (input.usize()?, input.usize()?);
+        if index_count != bounds.len() {
+            return Err(BvhError::Stale);
+        }
+
+        let mut seen = vec![false; index_count];
+        let mut indices = Vec::with_capacity(index_count);
+        for _ in 0..index_count {
+            let i = input.usize()?;
+            if i >= index_count || seen[i] {
+                return Err(BvhError::Format(
+                    "indices are not a permutation".to_string(),
+                ));
+            }
+            seen[i] = true;
+            indices.push(i);
+        }
+        let mut nodes = Vec::with_capacity(node_count.min(2 * index_count));
+        for k in 0..node_count {
+            let tag = input.take(1)?[0];
+            let node_bounds = input.bounding_box()?;
+            let (a, b) = (input.usize()?, input.usize()?);
+            nodes.push(match tag {
+                0 if a <= index_count && b <= index_count - a => BvhNode::Leaf {
+                    bounds: node_bounds,
+                    start: a,
+                    count: b,
+                },
+                1 if k < a.min(b) && a.max(b) < node_count => BvhNode::Branch {
+                    bounds: node_bounds,
+                    left: a,
+                    right: b,
+                },
+                _ => return Err(BvhError::Format("node out of range".to_string())),
+            });
+        }
+        if !input.bytes.is_empty() {
+            return Err(BvhError::Format("trailing data".to_string()));
+        }
+        let bvh = Bvh { nodes, indices };
+        if bvh.depth() > MAX_DEPTH {
+            return Err(BvhError::Format("hierarchy is too deep".to_string()));
+        }
+
+        Ok(bvh)
+    }
+
+    /// Box of every node with the number of branches above it, root first.
+    pub fn node_bounds(&self) -> Vec<(usize, BoundingBox)> {
+        self.nodes
+            .iter()
+            .zip(self.node_depths())
+            .map(|(node, depth)| (depth, *node.bounds()))
+            .collect()
+    }
+
+    /// Branches on the longest path from the root to a leaf.
+    fn depth(&self) -> usize {
+        self.node_depths().into_iter().max().unwrap_or(0)
+    }
+
+    /// Children always come after their parent, in built and loaded trees.
+    fn node_depths(&self) -> Vec<usize> {
+        let mut depths = vec![0; self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            if let BvhNode::Branch { left, right, .. } = *node {
+                depths[left] = depths[left].max(depths[i] + 1);
+                depths[right] = depths[right].max(depths[i] + 1);
+            }
+        }
+
+        depths
+    }
+
+    fn build(&mut self, bounds: &[BoundingBox], start: usize, end: usize) -> usize {
+        let slice = &mut self.indices[start..end];
+        let node_bounds = slice
+            .iter()
+            .fold(BoundingBox::empty(), |b, &i| b.merge(&bounds[i]));
+        let node = self.nodes.len();
+        if end - start <= MAX_LEAF_SIZE {
+            self.nodes.push(BvhNode::Leaf {
+                bounds: node_bounds,
+                start,
+                count: end - start,
+            });
+            return node;
+        }
+
+        let mut centers = BoundingBox::empty();
+        for &i in slice.iter() {
+            centers.add_point(bounds[i].center());
+        }
+        let extent = centers.get_max() - centers.get_min();
+        let axis = |i: usize| {
+            let c = bounds[i].center();
+            if extent.x >= extent.y && extent.x >= extent.z {
+                c.x
+            } else if extent.y >= extent.z {
+                c.y
+            } else {
+                c.z
+            }
+        };
+        slice.sort_by(|&a, &b| axis(a).total_cmp(&axis(b)));
+
+        self.nodes.push(BvhNode::Branch {
+            bounds: node_bounds,
+            left: 0,
+            right: 0,
+        });
+        let mid = start + (end - start) / 2;
+        let left = self.build(bounds, start, mid);
+        let right = self.build(bounds, mid, end);
+        if let BvhNode::Branch {
+            left: l, right: r, ..
+        } = &mut self.nodes[node]
+        {
+            *l = left;
+            *r = right;
+        }
+
+        node
+    }
+}
+
+/// FNV-1a over the bytes of every box, stable between runs and builds.
+fn content_hash(bounds: &[BoundingBox]) -> u64 {
+    let mut bytes = Vec::with_capacity(bounds.len() * 6 * mem::size_of::<Float>());
+    for b in bounds {
+        push_box(&mut bytes, b);
+    }
+
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn push_box(bytes: &mut Vec<u8>, b: &BoundingBox) {
+    let (min, max) = (b.get_min(), b.get_max());
+    for n in [min.x, min.y, min.z, max.x, max.y, max.z] {
+        bytes.extend_from_slice(&n.to_le_bytes());
+    }
+}
+
+/// Reads the fields of a hierarchy file from the front of `bytes`.
+struct Input<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Input<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], BvhError> {
+        if self.bytes.len() < n {
+            return Err(BvhError::Format("file is truncated".to_string()));
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+
+        Ok(head)
+    }
+
+    fn u64(&mut self) -> Result<u64, BvhError> {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(self.take(8)?);
+
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn usize(&mut self) -> Result<usize, BvhError> {
+        usize::try_from(self.u64()?).map_err(|_| BvhError::Format("count too large".to_string()))
+    }
+
+    fn float(&mut self) -> Result<Float, BvhError> {
+        let mut buf = [0; mem::size_of::<Float>()];
+        buf.copy_from_slice(self.take(mem::size_of::<Float>())?);
+
+        Ok(Float::from_le_bytes(buf))
+    }
+
+    fn bounding_box(&mut self) -> Result<BoundingBox, BvhError> {
+        let mut n = [0.0; 6];
+        for value in n.iter_mut() {
+            *value = self.float()?;
+        }
+
+        Ok(BoundingBox::new(
+            Tuple4::point(n[0], n[1], n[2]),
+            Tuple4::point(n[3], n[4], n[5]),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{float::Float, tuple::Tuple4};
+
+    use super::*;
+
+    fn cube_at(x: Float) -> BoundingBox {
+        BoundingBox::new(
+            Tuple4::point(x - 0.5, -0.5, -0.5),
+            Tuple4::point(x + 0.5, 0.5, 0.5),
+        )
+    }
+
+    #[test]
+    fn test_an_empty_hierarchy_visits_nothing() {
+        let bvh = Bvh::new(&[]);
+        let ray = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let mut visited = Vec::new();
+
+        bvh.traverse(&ray, |i| visited.push(i));
+
+        assert!(bvh.is_empty());
+        assert!(visited.is_empty());
+        assert!(bvh.get_bounds().is_empty());
+    }
+
+    #[test]
+    fn test_traversal_only_visits_boxes_along_the_ray() {
+        let boxes: Vec<_> = (0..100).map(|i| cube_at(i as Float * 2.0)).collect();
+        let bvh = Bvh::new(&boxes);
+        let ray = Ray::new(
+            Tuple4::point(20.0, 0.0, -5.0),
+            Tuple4::vector(0.0, 0.0, 1.0),
+        );
+        let mut visited = Vec::new();
+
+        bvh.traverse(&ray, |i| visited.push(i));
+
+        assert_eq!(bvh.len(), 100);
+        assert!(visited.contains(&10));
+        assert!(visited.len() <= MAX_LEAF_SIZE);
+        assert_eq!(bvh.get_bounds().get_max().x, 198.5);
+    }
+
+    #[test]
+    fn test_traversal_finds_every_box_a_ray_crosses() {
+        let boxes: Vec<_> = (0..37).map(|i| cube_at(i as Float)).collect();
+        let bvh = Bvh::new(&boxes);
+        let ray = Ray::new(Tuple4::point(-5.0, 0.0, 0.0), Tuple4::vector(1.0, 0.0, 0.0));
+        let mut visited = Vec::new();
+
+        bvh.traverse(&ray, |i| visited.push(i));
+
+        visited.sort();
+        assert_eq!(visited, (0..37).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_any_stops_at_the_first_match() {
+        let boxes: Vec<_> = (0..37).map(|i| cube_at(i as Float)).collect();
+        let bvh = Bvh::new(&boxes);
+        let ray = Ray::new(Tuple4::point(-5.0, 0.0, 0.0), Tuple4::vector(1.0, 0.0, 0.0));
+        let mut visited = 0;
+
+        let found = bvh.any(&ray, |_| {
+            visited += 1;
+            true
+        });
+
+        assert!(found);
+        assert_eq!(visited, 1);
+        assert!(!bvh.any(&ray, |_| false));
+    }
+
+    #[test]
+    fn test_node_bounds_list_every_node_with_its_depth() {
+        let boxes: Vec<_> = (0..16).map(|i| cube_at(i as Float)).collect();
+        let bvh = Bvh::new(&boxes);
+
+        let nodes = bvh.node_bounds();
+
+        assert_eq!(nodes.len(), 7);
+        assert_eq!(nodes[0], (0, bvh.get_bounds()));
+        assert_eq!(nodes.iter().filter(|(depth, _)| *depth == 2).count(), 4);
+        assert_eq!(bvh.depth(), 2);
+    }
+
+    #[test]
+    fn test_a_saved_hierarchy_loads_only_for_the_same_boxes() {
+        let boxes: Vec<_> = (0..23).map(|i| cube_at(i as Float * 1.5)).collect();
+        let bvh = Bvh::new(&boxes);
+        let mut file = Vec::new();
+        bvh.write(&mut file, &boxes).unwrap();
+
+        let loaded = Bvh::read(&mut file.as_slice(), &boxes).unwrap();
+        let mut moved = boxes.clone();
+        moved[3] = cube_at(100.0);
+        let stale = Bvh::read(&mut file.as_slice(), &moved);
+        let truncated = Bvh::read(&mut &file[..file.len() - 1], &boxes);
+
+        assert_eq!(loaded, bvh);
+        assert!(matches!(stale, Err(BvhError::Stale)));
+        assert!(matches!(truncated, Err(BvhError::Format(_))));
+    }
+
+    #[test]
+    fn test_a_saved_hierarchy_too_deep_to_traverse_is_rejected() {
+        let boxes = [cube_at(0.0)];
+        let mut nodes: Vec<_> = (1..=MAX_DEPTH + 1)
+            .map(|child| BvhNode::Branch {
+                bounds: boxes[0],
+                left: child,
+                right: child,
+            })
+            .collect();
+        nodes.push(BvhNode::Leaf {
+            bounds: boxes[0],
+            start: 0,
+            count: 1,
+        });
+        let deep = Bvh {
+            nodes,
+            indices: vec![0],
+        };
+        let mut file = Vec::new();
+        deep.write(&mut file, &boxes).unwrap();
+
+        let loaded = Bvh::read(&mut file.as_slice(), &boxes);
+
+        assert!(matches!(loaded, Err(BvhError::Format(_))));
+        assert!(Bvh::new(&vec![cube_at(0.0); 1000]).depth() <= 10);
+    }
+}
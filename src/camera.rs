@@ -0,0 +1,2767 @@
+use std::{
+    error::Error,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    bounds::BoundingBox,
+    canvas::Canvas,
+    capabilities::parallel_map,
+    color::Color,
+    film::{Film, Filter},
+    float::{consts::PI, Float},
+    matrix::Matrix4x4,
+    palette::Palette,
+    photons::PhotonGather,
+    ray::Ray,
+    rng::{RandomSource, Rng},
+    sampling::{uniform_disk, uniform_polygon},
+    settings::{Integrator, RenderSettings},
+    transform::Transform,
+    tuple::Tuple4,
+    world::World,
+};
+
+const DEFAULT_TILE_SIZE: usize = 32;
+/// Nearest depth in front of a perspective camera `Camera::screen_rect`
+/// still projects; boxes reaching closer cover the whole image.
+const EPSILON_DEPTH: Float = 1e-4;
+/// Samples of a `RayTable` gathered together when mapping photons.
+const PHOTON_RUN: usize = 256;
+
+/// Pixel rectangle covered by one render tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// A tile whose rendering panicked, with the panic message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileFailure {
+    pub rect: TileRect,
+    pub message: String,
+}
+
+/// A tiled render in which some tiles panicked. The canvas holds every tile
+/// that finished; failed tiles are left black.
+#[derive(Debug)]
+pub struct PartialRender {
+    pub canvas: Canvas,
+    pub failures: Vec<TileFailure>,
+}
+
+impl fmt::Display for PartialRender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} tile(s) failed to render", self.failures.len())?;
+        if let Some(first) = self.failures.first() {
+            write!(
+                f,
+                ", first at ({}, {}): {}",
+                first.rect.x, first.rect.y, first.message
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for PartialRender {}
+
+/// How far a render has got, as reported by `Camera::render_with_progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderProgress {
+    pub tiles_done: usize,
+    pub tiles_total: usize,
+    pub pixels_done: usize,
+    pub pixels_total: usize,
+    pub elapsed: Duration,
+}
+
+impl RenderProgress {
+    pub fn fraction(&self) -> Float {
+        if self.pixels_total == 0 {
+            1.0
+        } else {
+            self.pixels_done as Float / self.pixels_total as Float
+        }
+    }
+
+    /// Time left if the remaining pixels render at the average rate so far.
+    /// `None` until the first pixel is done.
+    pub fn estimated_remaining(&self) -> Option<Duration> {
+        if self.pixels_done == 0 {
+            return None;
+        }
+        let left = (self.pixels_total - self.pixels_done) as f64 / self.pixels_done as f64;
+
+        Some(self.elapsed.mul_f64(left))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective(Float),
+    /// Perspective with separate horizontal and vertical fields of view,
+    /// which fix the framing whatever the image's aspect ratio.
+    Frustum {
+        horizontal: Float,
+        vertical: Float,
+    },
+    Orthographic(Float),
+    Fisheye(Float),
+    Equirectangular,
+}
+
+/// Where film samples are placed inside each pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Antialiasing {
+    /// One sample at the pixel center.
+    #[default]
+    None,
+    /// An `n` by `n` grid of evenly spaced samples.
+    Grid(usize),
+    /// `n` samples at random positions.
+    Random(usize),
+    /// A 2 by 2 grid, followed by random samples in batches of four while
+    /// the luminance variance of the pixel's samples exceeds `threshold`,
+    /// up to `max_samples` in total. Only `pixel_samples` and the renders
+    /// built on it refine; `samples_for_pixel` returns the initial grid.
+    Adaptive {
+        threshold: Float,
+        max_samples: usize,
+    },
+}
+
+/// How the rays of a pixel are spread over the aperture, which sets the
+/// shape and texture of out-of-focus highlights.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum LensPattern {
+    /// Independent points on a round aperture.
+    #[default]
+    UniformDisk,
+    /// Points along a golden-angle spiral at a random rotation per pixel,
+    /// covering the disk evenly with few samples.
+    GoldenSpiral,
+    /// Points on the disk kept apart from one another by best-candidate
+    /// sampling, avoiding the clumps of `UniformDisk` without its pattern.
+    Poisson,
+    /// Points on a regular polygon, as formed by the blades of a real
+    /// aperture, with a corner at `rotation` radians.
+    Polygon { blades: usize, rotation: Float },
+    /// Points spread over the square around the aperture as densely as the
+    /// mask is bright, so highlights take the mask's shape.
+    Mask(Arc<ApertureMask>),
+}
+
+/// Grayscale image of an aperture, e.g. a star or a heart cut into the lens
+/// cap. The image is stretched over the square that holds the round
+/// aperture, with its top towards the camera's up vector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    /// Running total of pixel luminance, scaled to end at one.
+    cdf: Vec<Float>,
+}
+
+impl ApertureMask {
+    /// `None` if the image has no bright pixel to sample.
+    pub fn new(image: &Canvas) -> Option<Self> {
+        let (width, height) = (image.get_width(), image.get_height());
+        let mut total = 0.0;
+        let mut cdf = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                total += image.get_pixel((x, y)).luminance().max(0.0);
+                cdf.push(total);
+            }
+        }
+        if total <= 0.0 {
+            return None;
+        }
+        cdf.iter_mut().for_each(|c| *c /= total);
+
+        Some(ApertureMask { width, height, cdf })
+    }
+
+    fn sample<R: RandomSource>(&self, radius: Float, rng: &mut R) -> (Float, Float) {
+        let u = rng.next_float();
+        let i = self
+            .cdf
+            .partition_point(|&c| c <= u)
+            .min(self.cdf.len() - 1);
+        let x = ((i % self.width) as Float + rng.next_float()) / self.width as Float;
+        let y = ((i / self.width) as Float + rng.next_float()) / self.height as Float;
+
+        (radius * (2.0 * x - 1.0), radius * (1.0 - 2.0 * y))
+    }
+}
+
+/// When the sensor collects light, in the time units of the camera's
+/// motion. Every row is exposed for `close - open`; with a rolling shutter
+/// the rows are read out one after another, the last starting `readout`
+/// after the first, so a camera that moves during the readout skews the
+/// image.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Shutter {
+    pub open: Float,
+    pub close: Float,
+    pub readout: Float,
+}
+
+impl Shutter {
+    /// Time of a sample taken at image row `y` of `vsize`, `u` of the way
+    /// through that row's exposure.
+    pub fn time_at(&self, y: Float, vsize: usize, u: Float) -> Float {
+        self.open + self.readout * (y / vsize as Float) + u * (self.close - self.open)
+    }
+}
+
+/// Candidates drawn for each `LensPattern::Poisson` point.
+const POISSON_CANDIDATES: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera {
+    hsize: usize,
+    vsize: usize,
+    projection: Projection,
+    transform: Matrix4x4,
+    inverse: Matrix4x4,
+    half_width: Float,
+    half_height: Float,
+    pixel_size: Float,
+    pixel_height: Float,
+    pixel_aspect: Float,
+    aspect_ratio: Option<Float>,
+    aperture: Float,
+    focal_distance: Float,
+    lens_samples: usize,
+    lens_pattern: LensPattern,
+    motion: Option<Transform>,
+    shutter: Shutter,
+    filter: Filter,
+    antialiasing: Antialiasing,
+    seed: u64,
+    tile_size: usize,
+}
+
+impl Camera {
+    pub fn new(hsize: usize, vsize: usize, projection: Projection) -> Self {
+        let mut camera = Camera {
+            hsize,
+            vsize,
+            projection,
+            transform: Matrix4x4::identity(),
+            inverse: Matrix4x4::identity(),
+            half_width: 0.0,
+            half_height: 0.0,
+            pixel_size: 0.0,
+            pixel_height: 0.0,
+            pixel_aspect: 1.0,
+            aspect_ratio: None,
+            aperture: 0.0,
+            focal_distance: 1.0,
+            lens_samples: 1,
+            lens_pattern: LensPattern::default(),
+            motion: None,
+            shutter: Shutter::default(),
+            filter: Filter::default(),
+            antialiasing: Antialiasing::default(),
+            seed: 0,
+            tile_size: DEFAULT_TILE_SIZE,
+        };
+        camera.set_projection(projection);
+
+        camera
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        let half_view = match projection {
+            Projection::Perspective(field_of_view) => (field_of_view / 2.0).tan(),
+            Projection::Frustum { horizontal, .. } => (horizontal / 2.0).tan(),
+            Projection::Orthographic(width) => width / 2.0,
+            Projection::Fisheye(field_of_view) => field_of_view / 2.0,
+            Projection::Equirectangular => PI,
+        };
+        let aspect = self.aspect();
+        let (half_width, half_height) = match projection {
+            Projection::Frustum { vertical, .. } => (half_view, (vertical / 2.0).tan()),
+            _ if aspect >= 1.0 => (half_view, half_view / aspect),
+            _ => (half_view * aspect, half_view),
+        };
+
+        self.projection = projection;
+        self.half_width = half_width;
+        self.half_height = half_height;
+        self.pixel_size = half_width * 2.0 / self.hsize as Float;
+        self.pixel_height = half_height * 2.0 / self.vsize as Float;
+    }
+
+    /// Width over height of one pixel on the target display, e.g. 2.0 for
+    /// 2x anamorphic footage. Pixels are stretched so the image still frames
+    /// the same view once it is shown.
+    pub fn set_pixel_aspect(&mut self, pixel_aspect: Float) {
+        self.pixel_aspect = pixel_aspect;
+        self.set_projection(self.projection);
+    }
+
+    pub fn get_pixel_aspect(&self) -> Float {
+        self.pixel_aspect
+    }
+
+    /// Frames the view for this display aspect ratio instead of the one
+    /// implied by the image size and pixel aspect. `None` goes back to the
+    /// implied one.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: Option<Float>) {
+        self.aspect_ratio = aspect_ratio;
+        self.set_projection(self.projection);
+    }
+
+    pub fn get_aspect_ratio(&self) -> Float {
+        self.aspect()
+    }
+
+    pub fn get_hsize(&self) -> usize {
+        self.hsize
+    }
+
+    pub fn get_vsize(&self) -> usize {
+        self.vsize
+    }
+
+    pub fn get_projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn frame(&mut self, world: &World, direction: Tuple4, margin: Float) {
+        let (min, max) = match world.summary().extent {
+            Some(extent) => extent,
+            None => return,
+        };
+        let center = min + (max - min) * 0.5;
+        let radius = (max - min).magnitude() / 2.0 * margin;
+
+        let distance = match self.projection {
+            Projection::Perspective(_) | Projection::Frustum { .. } => {
+                let half_angle = self.half_width.min(self.half_height).atan();
+                radius / half_angle.sin()
+            }
+            Projection::Orthographic(_) => {
+                // The shorter side must still span the whole scene.
+                let aspect = self.aspect();
+                let width = 2.0 * radius * aspect.max(1.0 / aspect);
+                self.set_projection(Projection::Orthographic(width));
+                radius * 2.0
+            }
+            Projection::Fisheye(field_of_view) => {
+                radius / (field_of_view / 2.0).min(PI / 2.0).sin()
+            }
+            Projection::Equirectangular => radius * 2.0,
+        };
+
+        let direction = direction.normalize();
+        let from = center - direction * distance;
+        self.set_transform(Matrix4x4::view_transform(from, center, up_for(direction)));
+        self.focal_distance = distance;
+    }
+
+    pub fn get_pixel_size(&self) -> Float {
+        self.pixel_size
+    }
+
+    pub fn set_transform(&mut self, m: Matrix4x4) {
+        self.inverse = m.inverse().expect("Can't inverse singular matrix");
+        self.transform = m;
+    }
+
+    pub fn get_transform(&self) -> &Matrix4x4 {
+        &self.transform
+    }
+
+    pub fn set_aperture(&mut self, aperture: Float) {
+        self.aperture = aperture;
+    }
+
+    pub fn get_aperture(&self) -> Float {
+        self.aperture
+    }
+
+    pub fn set_focal_distance(&mut self, focal_distance: Float) {
+        self.focal_distance = focal_distance;
+    }
+
+    pub fn get_focal_distance(&self) -> Float {
+        self.focal_distance
+    }
+
+    pub fn autofocus(&mut self, world: &World, pixel: (usize, usize)) -> Option<Float> {
+        let (px, py) = pixel;
+        let ray = self.ray_for_pixel(px, py);
+        let hit = world.hit(&ray)?;
+        let point = self.transform * ray.position(hit.t);
+
+        self.focal_distance = match self.projection {
+            Projection::Perspective(_)
+            | Projection::Frustum { .. }
+            | Projection::Orthographic(_) => -point.z,
+            Projection::Fisheye(_) | Projection::Equirectangular => {
+                (point - Tuple4::point(0.0, 0.0, 0.0)).magnitude()
+            }
+        };
+
+        Some(self.focal_distance)
+    }
+
+    pub fn set_lens_samples(&mut self, lens_samples: usize) {
+        self.lens_samples = lens_samples.max(1);
+    }
+
+    pub fn get_lens_samples(&self) -> usize {
+        self.lens_samples
+    }
+
+    pub fn set_lens_pattern(&mut self, lens_pattern: LensPattern) {
+        self.lens_pattern = lens_pattern;
+    }
+
+    pub fn get_lens_pattern(&self) -> &LensPattern {
+        &self.lens_pattern
+    }
+
+    /// Moves the whole camera over time: each sample's ray is carried by
+    /// the motion sampled at the sample's shutter time, on top of the view
+    /// transform. `None` keeps the camera still.
+    pub fn set_motion(&mut self, motion: Option<Transform>) {
+        self.motion = motion;
+    }
+
+    pub fn get_motion(&self) -> Option<&Transform> {
+        self.motion.as_ref()
+    }
+
+    /// Only matters with a `motion`.
+    pub fn set_shutter(&mut self, shutter: Shutter) {
+        self.shutter = shutter;
+    }
+
+    pub fn get_shutter(&self) -> Shutter {
+        self.shutter
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    pub fn get_filter(&self) -> Filter {
+        self.filter
+    }
+
+    pub fn set_antialiasing(&mut self, antialiasing: Antialiasing) {
+        self.antialiasing = antialiasing;
+    }
+
+    pub fn get_antialiasing(&self) -> Antialiasing {
+        self.antialiasing
+    }
+
+    /// Mixed into the seed of every pixel's samples. The same seed always
+    /// gives the same image.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Edge length of the square tiles `render_parallel` hands out.
+    pub fn set_tile_size(&mut self, tile_size: usize) {
+        self.tile_size = tile_size.max(1);
+    }
+
+    pub fn get_tile_size(&self) -> usize {
+        self.tile_size
+    }
+
+    pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
+        self.ray_at(px as Float + 0.5, py as Float + 0.5)
+    }
+
+    pub fn ray_at(&self, x: Float, y: Float) -> Ray {
+        let (origin, direction) = self.view_ray(x, y);
+        let origin = self.inverse * origin;
+        let direction = self.inverse * direction;
+
+        Ray::new(origin, direction.normalize())
+    }
+
+    /// Returns the image position and ray of every sample taken for a pixel.
+    /// Antialiasing picks the film positions; with a lens, each of them gets
+    /// `lens_samples` rays through the aperture. Without antialiasing, lens
+    /// samples are jittered across the pixel area instead.
+    pub fn samples_for_pixel(&self, px: usize, py: usize) -> Vec<(Float, Float, Ray)> {
+        let samples = self.samples_at_rest(px, py);
+        if self.motion.is_none() {
+            return samples;
+        }
+
+        let mut rng = self.shutter_rng(px, py);
+        samples
+            .into_iter()
+            .map(|(x, y, ray)| (x, y, self.moved(ray, y, &mut rng)))
+            .collect()
+    }
+
+    fn samples_at_rest(&self, px: usize, py: usize) -> Vec<(Float, Float, Ray)> {
+        let mut rng = Rng::for_stream(self.seed, (py * self.hsize + px) as u64);
+        let (x0, y0) = (px as Float, py as Float);
+        let lens = self.aperture > 0.0 && self.lens_samples > 1;
+        let positions: Vec<(Float, Float)> = match self.antialiasing {
+            Antialiasing::None if lens => {
+                let mut sampler = self.lens_sampler(self.lens_samples);
+                return (0..self.lens_samples)
+                    .map(|_| {
+                        let x = x0 + rng.next_float();
+                        let y = y0 + rng.next_float();
+                        let (lens_x, lens_y) = sampler.next(&mut rng);
+                        (x, y, self.lens_ray_at(x, y, lens_x, lens_y))
+                    })
+                    .collect();
+            }
+            Antialiasing::None => vec![(x0 + 0.5, y0 + 0.5)],
+            Antialiasing::Grid(n) => grid_positions(x0, y0, n),
+            Antialiasing::Adaptive { .. } => grid_positions(x0, y0, 2),
+            Antialiasing::Random(n) => (0..n.max(1))
+                .map(|_| (x0 + rng.next_float(), y0 + rng.next_float()))
+                .collect(),
+        };
+
+        if !lens {
+            return positions
+                .into_iter()
+                .map(|(x, y)| (x, y, self.ray_at(x, y)))
+                .collect();
+        }
+        positions
+            .into_iter()
+            .flat_map(|(x, y)| {
+                let mut sampler = self.lens_sampler(self.lens_samples);
+                (0..self.lens_samples)
+                    .map(|_| {
+                        let (lens_x, lens_y) = sampler.next(&mut rng);
+                        (x, y, self.lens_ray_at(x, y, lens_x, lens_y))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    pub fn render(&self, world: &World) -> Canvas {
+        if let Integrator::PhotonMapping { .. } = world.get_settings().integrator {
+            return self.render_photons(world, |_| {});
+        }
+        self.render_with(|ray| world.color_at(ray))
+    }
+
+    /// Same image as `render`, with tiles spread over every core.
+    pub fn render_parallel(&self, world: &World) -> Canvas {
+        if let Integrator::PhotonMapping { .. } = world.get_settings().integrator {
+            return self.render_photons(world, |_| {});
+        }
+        self.render_tiled(self.tile_size, |ray| world.color_at(ray))
+    }
+
+    /// `render_parallel`, calling `on_progress` after every finished tile.
+    pub fn render_with_progress<P>(&self, world: &World, on_progress: P) -> Canvas
+    where
+        P: Fn(RenderProgress) + Sync,
+    {
+        self.try_render_with_progress(world, on_progress)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// `render_with_progress` with the panic recovery of `try_render_tiled`.
+    pub fn try_render_with_progress<P>(
+        &self,
+        world: &World,
+        on_progress: P,
+    ) -> Result<Canvas, PartialRender>
+    where
+        P: Fn(RenderProgress) + Sync,
+    {
+        let start = Instant::now();
+        if let Integrator::PhotonMapping { passes, .. } = world.get_settings().integrator {
+            let pixels_total = self.hsize * self.vsize;
+            let passes = passes.max(1);
+            return self.try_render_photons(world, |done| {
+                on_progress(RenderProgress {
+                    tiles_done: done,
+                    tiles_total: passes,
+                    pixels_done: pixels_total * done / passes,
+                    pixels_total,
+                    elapsed: start.elapsed(),
+                })
+            });
+        }
+
+        let tiles_total = self.hsize.div_ceil(self.tile_size) * self.vsize.div_ceil(self.tile_size);
+        let done = Mutex::new((0, 0));
+
+        self.try_render_progressive(
+            self.tile_size,
+            |ray| world.color_at(ray),
+            |rect, _| {
+                let mut done = done.lock().unwrap();
+                done.0 += 1;
+                done.1 += rect.width * rect.height;
+                on_progress(RenderProgress {
+                    tiles_done: done.0,
+                    tiles_total,
+                    pixels_done: done.1,
+                    pixels_total: self.hsize * self.vsize,
+                    elapsed: start.elapsed(),
+                });
+            },
+        )
+    }
+
+    /// Renders under `Integrator::PhotonMapping` one pass at a time: each
+    /// pass traces a photon map, refines every pixel's estimate with it and
+    /// drops it, so only one pass's photons are ever held. Pass `i` traces
+    /// the `i`th of a pixel's samples, cycling through them, and the pixels
+    /// are not filtered. `on_pass` is called with the number of passes done.
+    /// Worlds using another integrator are rendered as usual.
+    pub fn render_photons<P: Fn(usize)>(&self, world: &World, on_pass: P) -> Canvas {
+        self.try_render_photons(world, on_pass)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// `render_photons` with the panic recovery of `try_render_tiled`. Rows
+    /// stand in for tiles: a row that panics in any pass is left black.
+    pub fn try_render_photons<P: Fn(usize)>(
+        &self,
+        world: &World,
+        on_pass: P,
+    ) -> Result<Canvas, PartialRender> {
+        self.photon_canvas(world, |done| {
+            on_pass(done);
+            true
+        })
+        .unwrap_or_else(|| self.try_render_tiled(self.tile_size, |ray| world.color_at(ray)))
+    }
+
+    /// The photon passes of `try_render_photons`, stopping early once
+    /// `keep_going` returns false. None when `world` does not map photons.
+    fn photon_canvas<P: FnMut(usize) -> bool>(
+        &self,
+        world: &World,
+        keep_going: P,
+    ) -> Option<Result<Canvas, PartialRender>> {
+        let (gathers, failed_rows) = photon_passes(
+            world,
+            self.hsize * self.vsize,
+            self.hsize,
+            |i, pass| {
+                let samples = self.samples_for_pixel(i % self.hsize, i / self.hsize);
+                samples[pass % samples.len()].2
+            },
+            keep_going,
+        )?;
+
+        let mut canvas = Canvas::new(self.hsize, self.vsize);
+        for (i, gather) in gathers.iter().enumerate() {
+            canvas.put_pixel(gather.estimate(), (i % self.hsize, i / self.hsize));
+        }
+        if failed_rows.is_empty() {
+            return Some(Ok(canvas));
+        }
+
+        let mut failures = Vec::new();
+        for (y, message) in failed_rows {
+            for x in 0..self.hsize {
+                canvas.put_pixel(Color::new(0.0, 0.0, 0.0), (x, y));
+            }
+            failures.push(TileFailure {
+                rect: TileRect {
+                    x: 0,
+                    y,
+                    width: self.hsize,
+                    height: 1,
+                },
+                message,
+            });
+        }
+
+        Some(Err(PartialRender { canvas, failures }))
+    }
+
+    /// Grayscale alpha matte to go with `render`, see `World::alpha_at`.
+    pub fn render_matte(&self, world: &World) -> Canvas {
+        self.render_with(|ray| {
+            let alpha = world.alpha_at(ray);
+            Color::new(alpha, alpha, alpha)
+        })
+    }
+
+    /// Distance to the first hit along the ray through each pixel center,
+    /// nearest in the first color of `palette` and misses in
+    /// `Palette::missing`. Depths are not averaged over samples, which
+    /// would mix misses into the silhouette pixels.
+    pub fn render_depth(&self, world: &World, palette: Palette) -> Canvas {
+        let mut depth = Canvas::new(self.hsize, self.vsize);
+
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                let t = match world.hit(&self.ray_for_pixel(x, y)) {
+                    Some(hit) => hit.t,
+                    None => Float::INFINITY,
+                };
+                depth.put_pixel(Color::new(t, t, t), (x, y));
+            }
+        }
+
+        depth.false_color(palette)
+    }
+
+    /// `render_parallel` of `World::with_bvh_bounds` under
+    /// `RenderSettings::preview`, to check how the object hierarchy splits
+    /// the scene.
+    pub fn render_bvh(&self, world: &World, thickness: Float, palette: Palette) -> Canvas {
+        let mut outlined = world.with_bvh_bounds(thickness, palette);
+        outlined.set_settings(RenderSettings::preview());
+
+        self.render_parallel(&outlined)
+    }
+
+    pub fn render_with<F: Fn(&Ray) -> Color>(&self, shade: F) -> Canvas {
+        let mut film = Film::new(self.hsize, self.vsize, self.filter);
+
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                for (sx, sy, color) in self.pixel_samples(x, y, &shade) {
+                    film.add_sample(sx, sy, color);
+                }
+            }
+        }
+
+        film.to_canvas()
+    }
+
+    /// Renders the image in square tiles spread across threads. Threads take
+    /// the next unrendered tile whenever they finish one, so expensive
+    /// regions do not hold the others up. Each tile splats into a film padded
+    /// by the filter footprint, and the tiles are merged in a fixed order
+    /// once every thread has finished.
+    pub fn render_tiled<F: Fn(&Ray) -> Color + Sync>(&self, tile_size: usize, shade: F) -> Canvas {
+        self.render_progressive(tile_size, shade, |_, _| {})
+    }
+
+    /// `render_tiled`, calling `on_tile` with each tile's pixels, row by row,
+    /// as soon as it finishes. Calls come from the render threads in no
+    /// particular order. Pixels near a tile edge only see that tile's
+    /// samples, so with filters wider than a pixel they can differ slightly
+    /// from the returned canvas.
+    pub fn render_progressive<F, P>(&self, tile_size: usize, shade: F, on_tile: P) -> Canvas
+    where
+        F: Fn(&Ray) -> Color + Sync,
+        P: Fn(TileRect, &[Color]) + Sync,
+    {
+        self.try_render_progressive(tile_size, shade, on_tile)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// `render_tiled`, but a tile that panics is recorded and the remaining
+    /// tiles still render, so one bad sample does not lose the whole image.
+    pub fn try_render_tiled<F>(&self, tile_size: usize, shade: F) -> Result<Canvas, PartialRender>
+    where
+        F: Fn(&Ray) -> Color + Sync,
+    {
+        self.try_render_progressive(tile_size, shade, |_, _| {})
+    }
+
+    /// `render_progressive` with the panic recovery of `try_render_tiled`.
+    /// `on_tile` is only called for tiles that finish.
+    pub fn try_render_progressive<F, P>(
+        &self,
+        tile_size: usize,
+        shade: F,
+        on_tile: P,
+    ) -> Result<Canvas, PartialRender>
+    where
+        F: Fn(&Ray) -> Color + Sync,
+        P: Fn(TileRect, &[Color]) + Sync,
+    {
+        let tile_size = tile_size.max(1);
+        let tiles = self.tile_origins(tile_size);
+        let rect = |x: usize, y: usize| TileRect {
+            x,
+            y,
+            width: (x + tile_size).min(self.hsize) - x,
+            height: (y + tile_size).min(self.vsize) - y,
+        };
+        let results = self.render_tiles(&tiles, &|x, y| {
+            catch_tile(|| {
+                let film = self.render_tile(x, y, tile_size, &shade);
+                let rect = rect(x, y);
+                on_tile(rect, &film.resolve(x, y, rect.width, rect.height));
+                film
+            })
+        });
+
+        let mut image = Film::new(self.hsize, self.vsize, self.filter);
+        let mut failures = Vec::new();
+        for (&(x, y), result) in tiles.iter().zip(results) {
+            match result {
+                Ok(film) => image.merge(&film),
+                Err(message) => failures.push(TileFailure {
+                    rect: rect(x, y),
+                    message,
+                }),
+            }
+        }
+
+        let canvas = image.to_canvas();
+        if failures.is_empty() {
+            Ok(canvas)
+        } else {
+            Err(PartialRender { canvas, failures })
+        }
+    }
+
+    /// `render_parallel` with a deadline, see `render_tiled_within`. Late
+    /// tiles shade with `RenderSettings::draft`, keeping the world's seed,
+    /// from a copy of `world` made when the first of them starts. Photon
+    /// mapped worlds stop taking passes once the budget has run out.
+    pub fn render_within(&self, world: &World, budget: Duration) -> Canvas {
+        let deadline = Instant::now() + budget;
+        if let Some(photons) = self.photon_canvas(world, |_| Instant::now() < deadline) {
+            return photons.unwrap_or_else(|e| panic!("{}", e));
+        }
+
+        let draft = OnceLock::new();
+        let draft = || {
+            draft.get_or_init(|| {
+                let mut draft = world.clone();
+                draft.set_settings(RenderSettings {
+                    seed: world.get_settings().seed,
+                    ..RenderSettings::draft()
+                });
+                draft
+            })
+        };
+
+        self.render_tiled_within(
+            self.tile_size,
+            budget,
+            |ray| world.color_at(ray),
+            |ray| draft().color_at(ray),
+        )
+    }
+
+    /// `render_tiled` that stops refining once `budget` has run out. Tiles
+    /// started after the deadline shade with `late_shade`, usually a cheaper
+    /// version of `shade`, and take a single ray through each pixel center,
+    /// skipping antialiasing and lens samples. The image is done soon after
+    /// the deadline at the cost of quality in the late tiles.
+    pub fn render_tiled_within<F, G>(
+        &self,
+        tile_size: usize,
+        budget: Duration,
+        shade: F,
+        late_shade: G,
+    ) -> Canvas
+    where
+        F: Fn(&Ray) -> Color + Sync,
+        G: Fn(&Ray) -> Color + Sync,
+    {
+        let deadline = Instant::now() + budget;
+        let mut draft = self.clone();
+        draft.antialiasing = Antialiasing::None;
+        draft.lens_samples = 1;
+
+        let tile_size = tile_size.max(1);
+        let tiles = self.tile_origins(tile_size);
+        let films = self.render_tiles(&tiles, &|x, y| {
+            if Instant::now() < deadline {
+                self.render_tile(x, y, tile_size, &shade)
+            } else {
+                draft.render_tile(x, y, tile_size, &late_shade)
+            }
+        });
+
+        self.merge_tiles(&films).to_canvas()
+    }
+
+    /// `render_dirty_with` shading with `world`. Photons an edit moves can
+    /// land anywhere, so photon mapped worlds are rendered again in full.
+    pub fn render_dirty(
+        &self,
+        world: &World,
+        previous: &Canvas,
+        changed: &[BoundingBox],
+    ) -> Canvas {
+        if let Integrator::PhotonMapping { .. } = world.get_settings().integrator {
+            return self.render_photons(world, |_| {});
+        }
+        self.render_dirty_with(previous, changed, |ray| world.color_at(ray))
+    }
+
+    /// Updates `previous`, this camera's `render_parallel` of a scene, after
+    /// edits confined to the `changed` boxes, e.g. the old and new bounds of
+    /// a moved object. Only tiles the boxes can reach on screen are shaded
+    /// again, so small edits re-render quickly. Shadows and reflections the
+    /// edit causes outside those tiles are not updated. A `previous` of
+    /// another size is replaced by a full render.
+    pub fn render_dirty_with<F>(
+        &self,
+        previous: &Canvas,
+        changed: &[BoundingBox],
+        shade: F,
+    ) -> Canvas
+    where
+        F: Fn(&Ray) -> Color + Sync,
+    {
+        if (previous.get_width(), previous.get_height()) != (self.hsize, self.vsize) {
+            return self.render_tiled(self.tile_size, shade);
+        }
+
+        let tile_size = self.tile_size;
+        let padding = (self.filter.radius() - 0.5).ceil().max(0.0) as usize;
+        let (columns, rows) = (
+            self.hsize.div_ceil(tile_size),
+            self.vsize.div_ceil(tile_size),
+        );
+        let mut dirty = vec![false; columns * rows];
+        for rect in changed.iter().filter_map(|b| self.screen_rect(b)) {
+            let x0 = rect.x.saturating_sub(padding) / tile_size;
+            let y0 = rect.y.saturating_sub(padding) / tile_size;
+            let x1 = ((rect.x + rect.width + padding).min(self.hsize) - 1) / tile_size;
+            let y1 = ((rect.y + rect.height + padding).min(self.vsize) - 1) / tile_size;
+            for ty in y0..=y1 {
+                for tx in x0..=x1 {
+                    dirty[ty * columns + tx] = true;
+                }
+            }
+        }
+
+        // Pixels near a tile's edge also take samples from its neighbours.
+        let reach = padding.div_ceil(tile_size);
+        let needed: Vec<(usize, usize)> = (0..rows)
+            .flat_map(|ty| (0..columns).map(move |tx| (tx, ty)))
+            .filter(|&(tx, ty)| {
+                (ty.saturating_sub(reach)..=(ty + reach).min(rows - 1)).any(|ny| {
+                    (tx.saturating_sub(reach)..=(tx + reach).min(columns - 1))
+                        .any(|nx| dirty[ny * columns + nx])
+                })
+            })
+            .map(|(tx, ty)| (tx * tile_size, ty * tile_size))
+            .collect();
+        let films = self.render_tiles(&needed, &|x, y| self.render_tile(x, y, tile_size, &shade));
+        let image = self.merge_tiles(&films);
+
+        let mut canvas = previous.clone();
+        for &(x, y) in &needed {
+            if !dirty[(y / tile_size) * columns + x / tile_size] {
+                continue;
+            }
+            let width = (x + tile_size).min(self.hsize) - x;
+            let height = (y + tile_size).min(self.vsize) - y;
+            for (i, color) in image.resolve(x, y, width, height).into_iter().enumerate() {
+                canvas.put_pixel(color, (x + i % width, y + i / width));
+            }
+        }
+
+        canvas
+    }
+
+    /// Pixels a box may cover, including the blur of the lens. `None` when
+    /// it is off screen; the whole image when it cannot be bounded, as with
+    /// wide-angle projections, camera motion or a box reaching behind the
+    /// camera.
+    fn screen_rect(&self, bounds: &BoundingBox) -> Option<TileRect> {
+        let whole = TileRect {
+            x: 0,
+            y: 0,
+            width: self.hsize,
+            height: self.vsize,
+        };
+        if bounds.is_empty() {
+            return None;
+        }
+        let perspective = match self.projection {
+            Projection::Perspective(_) | Projection::Frustum { .. } => true,
+            Projection::Orthographic(_) => false,
+            Projection::Fisheye(_) | Projection::Equirectangular => return Some(whole),
+        };
+        if self.motion.is_some() {
+            return Some(whole);
+        }
+
+        let (min, max) = (bounds.get_min(), bounds.get_max());
+        let aperture = if self.lens_samples > 1 {
+            self.aperture
+        } else {
+            0.0
+        };
+        let (mut x0, mut y0) = (Float::INFINITY, Float::INFINITY);
+        let (mut x1, mut y1) = (Float::NEG_INFINITY, Float::NEG_INFINITY);
+        for i in 0..8 {
+            let corner = Tuple4::point(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            );
+            let p = self.transform * corner;
+            let depth = -p.z;
+            let (vx, vy, blur) = if perspective {
+                if depth <= EPSILON_DEPTH {
+                    return Some(whole);
+                }
+                let blur = aperture * (1.0 / self.focal_distance - 1.0 / depth).abs();
+                (p.x / depth, p.y / depth, blur)
+            } else {
+                let blur = aperture * (1.0 - depth / self.focal_distance).abs();
+                (p.x, p.y, blur)
+            };
+            let px = (self.half_width - vx) / self.pixel_size;
+            let py = (self.half_height - vy) / self.pixel_height;
+            let (bx, by) = (blur / self.pixel_size, blur / self.pixel_height);
+            x0 = x0.min(px - bx);
+            x1 = x1.max(px + bx);
+            y0 = y0.min(py - by);
+            y1 = y1.max(py + by);
+        }
+
+        if x1 < 0.0 || y1 < 0.0 || x0 >= self.hsize as Float || y0 >= self.vsize as Float {
+            return None;
+        }
+        let x = x0.floor().max(0.0) as usize;
+        let y = y0.floor().max(0.0) as usize;
+        let width = (x1.ceil().max(0.0) as usize + 1).min(self.hsize) - x;
+        let height = (y1.ceil().max(0.0) as usize + 1).min(self.vsize) - y;
+
+        Some(TileRect {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    /// Top-left corners of the tiles covering the image, row by row.
+    fn tile_origins(&self, tile_size: usize) -> Vec<(usize, usize)> {
+        (0..self.vsize)
+            .step_by(tile_size)
+            .flat_map(|y| (0..self.hsize).step_by(tile_size).map(move |x| (x, y)))
+            .collect()
+    }
+
+    /// Tile films merged, in order, into one covering the whole image.
+    fn merge_tiles(&self, films: &[Film]) -> Film {
+        let mut image = Film::new(self.hsize, self.vsize, self.filter);
+        for film in films {
+            image.merge(film);
+        }
+
+        image
+    }
+
+    fn render_tiles<T: Send, R: Fn(usize, usize) -> T + Sync>(
+        &self,
+        tiles: &[(usize, usize)],
+        render: &R,
+    ) -> Vec<T> {
+        parallel_map(tiles.len(), |i| render(tiles[i].0, tiles[i].1))
+    }
+
+    pub fn render_tile<F: Fn(&Ray) -> Color>(
+        &self,
+        x: usize,
+        y: usize,
+        tile_size: usize,
+        shade: &F,
+    ) -> Film {
+        let x1 = (x + tile_size).min(self.hsize);
+        let y1 = (y + tile_size).min(self.vsize);
+        let padding = (self.filter.radius() - 0.5).ceil().max(0.0) as usize;
+        let (fx, fy) = (x.saturating_sub(padding), y.saturating_sub(padding));
+        let mut film = Film::window(
+            fx,
+            fy,
+            (x1 + padding).min(self.hsize) - fx,
+            (y1 + padding).min(self.vsize) - fy,
+            self.filter,
+        );
+
+        for py in y..y1 {
+            for px in x..x1 {
+                for (sx, sy, color) in self.pixel_samples(px, py, shade) {
+                    film.add_sample(sx, sy, color);
+                }
+            }
+        }
+
+        film
+    }
+
+    /// Precomputes every sample ray so frames rendered from an unchanging
+    /// camera can skip ray generation.
+    pub fn ray_table(&self) -> RayTable {
+        let mut samples = Vec::with_capacity(self.hsize * self.vsize);
+        let mut offsets = Vec::with_capacity(self.hsize * self.vsize + 1);
+        offsets.push(0);
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                samples.extend(self.samples_for_pixel(x, y));
+                offsets.push(samples.len());
+            }
+        }
+
+        RayTable {
+            hsize: self.hsize,
+            vsize: self.vsize,
+            filter: self.filter,
+            samples,
+            offsets,
+        }
+    }
+
+    /// Shades every sample of a single pixel. Any sampling randomness is
+    /// seeded from the pixel coordinates alone, so the result does not depend
+    /// on the order in which pixels are rendered or on which thread renders them.
+    pub fn pixel_samples<F: Fn(&Ray) -> Color>(
+        &self,
+        px: usize,
+        py: usize,
+        shade: &F,
+    ) -> Vec<(Float, Float, Color)> {
+        let mut samples: Vec<_> = self
+            .samples_for_pixel(px, py)
+            .into_iter()
+            .map(|(x, y, ray)| (x, y, shade(&ray)))
+            .collect();
+
+        if let Antialiasing::Adaptive {
+            threshold,
+            max_samples,
+        } = self.antialiasing
+        {
+            let mut rng = Rng::for_stream(self.seed, !((py * self.hsize + px) as u64));
+            let mut shutter_rng = self.shutter_rng(px, py);
+            while samples.len() < max_samples && luminance_variance(&samples) > threshold {
+                for _ in 0..4.min(max_samples - samples.len()) {
+                    let x = px as Float + rng.next_float();
+                    let y = py as Float + rng.next_float();
+                    let mut ray = if self.aperture > 0.0 {
+                        let (lens_x, lens_y) = self.lens_sampler(1).next(&mut rng);
+                        self.lens_ray_at(x, y, lens_x, lens_y)
+                    } else {
+                        self.ray_at(x, y)
+                    };
+                    if self.motion.is_some() {
+                        ray = self.moved(ray, y, &mut shutter_rng);
+                    }
+                    samples.push((x, y, shade(&ray)));
+                }
+            }
+        }
+
+        samples
+    }
+
+    /// Separate from the pixel's other random numbers, so adding motion
+    /// leaves the film and lens positions as they were.
+    fn shutter_rng(&self, px: usize, py: usize) -> Rng {
+        let pixels = self.hsize * self.vsize;
+        Rng::for_stream(self.seed, (pixels + py * self.hsize + px) as u64)
+    }
+
+    fn moved(&self, ray: Ray, y: Float, rng: &mut Rng) -> Ray {
+        let motion = match &self.motion {
+            Some(motion) => motion,
+            None => return ray,
+        };
+        let time = self.shutter.time_at(y, self.vsize, rng.next_float());
+        let moved = ray.transform(&motion.sample(time));
+
+        Ray::new(moved.origin, moved.direction.normalize())
+    }
+
+    fn lens_sampler(&self, count: usize) -> LensSampler<'_> {
+        LensSampler {
+            pattern: &self.lens_pattern,
+            aperture: self.aperture,
+            count: count.max(1),
+            index: 0,
+            rotation: None,
+            points: Vec::new(),
+        }
+    }
+
+    fn lens_ray_at(&self, x: Float, y: Float, lens_x: Float, lens_y: Float) -> Ray {
+        let (origin, direction) = self.view_ray(x, y);
+        let focal_point = match self.projection {
+            Projection::Perspective(_)
+            | Projection::Frustum { .. }
+            | Projection::Orthographic(_) => {
+                origin + direction * (self.focal_distance / -direction.z)
+            }
+            Projection::Fisheye(_) | Projection::Equirectangular => {
+                origin + direction.normalize() * self.focal_distance
+            }
+        };
+        let lens = origin + Tuple4::vector(lens_x, lens_y, 0.0);
+        let origin = self.inverse * lens;
+        let target = self.inverse * focal_point;
+
+        Ray::new(origin, (target - origin).normalize())
+    }
+
+    fn view_ray(&self, x: Float, y: Float) -> (Tuple4, Tuple4) {
+        let (world_x, world_y) = self.image_to_view(x, y);
+
+        match self.projection {
+            Projection::Perspective(_) | Projection::Frustum { .. } => (
+                Tuple4::point(0.0, 0.0, 0.0),
+                Tuple4::vector(world_x, world_y, -1.0),
+            ),
+            Projection::Orthographic(_) => (
+                Tuple4::point(world_x, world_y, 0.0),
+                Tuple4::vector(0.0, 0.0, -1.0),
+            ),
+            Projection::Fisheye(_) => {
+                let theta = world_x.hypot(world_y);
+                let direction = if theta == 0.0 {
+                    Tuple4::vector(0.0, 0.0, -1.0)
+                } else {
+                    let s = theta.sin() / theta;
+                    Tuple4::vector(world_x * s, world_y * s, -theta.cos())
+                };
+                (Tuple4::point(0.0, 0.0, 0.0), direction)
+            }
+            Projection::Equirectangular => {
+                let u = x / self.hsize as Float;
+                let v = y / self.vsize as Float;
+                let longitude = (0.5 - u) * 2.0 * PI;
+                let latitude = (0.5 - v) * PI;
+                let direction = Tuple4::vector(
+                    latitude.cos() * longitude.sin(),
+                    latitude.sin(),
+                    -latitude.cos() * longitude.cos(),
+                );
+                (Tuple4::point(0.0, 0.0, 0.0), direction)
+            }
+        }
+    }
+
+    fn aspect(&self) -> Float {
+        self.aspect_ratio
+            .unwrap_or(self.hsize as Float * self.pixel_aspect / self.vsize as Float)
+    }
+
+    fn image_to_view(&self, x: Float, y: Float) -> (Float, Float) {
+        let xoffset = x * self.pixel_size;
+        let yoffset = y * self.pixel_height;
+
+        (self.half_width - xoffset, self.half_height - yoffset)
+    }
+}
+
+/// A snapshot of a camera's sample rays. Changing the camera afterwards does
+/// not affect the table; build a new one instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RayTable {
+    hsize: usize,
+    vsize: usize,
+    filter: Filter,
+    samples: Vec<(Float, Float, Ray)>,
+    offsets: Vec<usize>,
+}
+
+impl RayTable {
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn samples_for_pixel(&self, px: usize, py: usize) -> &[(Float, Float, Ray)] {
+        let i = py * self.hsize + px;
+
+        &self.samples[self.offsets[i]..self.offsets[i + 1]]
+    }
+
+    /// Photon mapped worlds refine one gather per sample, as
+    /// `Camera::render_photons` does per pixel.
+    pub fn render(&self, world: &World) -> Canvas {
+        let photons = photon_passes(
+            world,
+            self.samples.len(),
+            PHOTON_RUN,
+            |i, _| self.samples[i].2,
+            |_| true,
+        );
+        let Some((gathers, failures)) = photons else {
+            return self.render_with(|ray| world.color_at(ray));
+        };
+        if let Some((_, message)) = failures.first() {
+            panic!("{}", message);
+        }
+
+        let mut film = Film::new(self.hsize, self.vsize, self.filter);
+        for ((x, y, _), gather) in self.samples.iter().zip(&gathers) {
+            film.add_sample(*x, *y, gather.estimate());
+        }
+
+        film.to_canvas()
+    }
+
+    pub fn render_with<F: Fn(&Ray) -> Color>(&self, shade: F) -> Canvas {
+        let mut film = Film::new(self.hsize, self.vsize, self.filter);
+
+        for (x, y, ray) in &self.samples {
+            film.add_sample(*x, *y, shade(ray));
+        }
+
+        film.to_canvas()
+    }
+}
+
+pub fn turntable(target: Tuple4, radius: Float, elevation: Float, frames: usize) -> Vec<Matrix4x4> {
+    (0..frames)
+        .map(|i| {
+            let angle = 2.0 * PI * i as Float / frames as Float;
+            let offset = Tuple4::vector(
+                radius * elevation.cos() * angle.sin(),
+                radius * elevation.sin(),
+                -radius * elevation.cos() * angle.cos(),
+            );
+            let from = target + offset;
+            Matrix4x4::view_transform(from, target, up_for(target - from))
+        })
+        .collect()
+}
+
+fn up_for(direction: Tuple4) -> Tuple4 {
+    if direction
+        .normalize()
+        .cross(Tuple4::vector(0.0, 1.0, 0.0))
+        .magnitude()
+        < 1e-6
+    {
+        Tuple4::vector(0.0, 0.0, 1.0)
+    } else {
+        Tuple4::vector(0.0, 1.0, 0.0)
+    }
+}
+
+/// Hands out the lens positions of one set of `count` rays in turn, so
+/// patterns can place each point with the earlier ones in mind.
+struct LensSampler<'a> {
+    pattern: &'a LensPattern,
+    aperture: Float,
+    count: usize,
+    index: usize,
+    rotation: Option<Float>,
+    points: Vec<(Float, Float)>,
+}
+
+impl LensSampler<'_> {
+    fn next<R: RandomSource>(&mut self, rng: &mut R) -> (Float, Float) {
+        let i = self.index;
+        self.index += 1;
+
+        match *self.pattern {
+            LensPattern::UniformDisk => {
+                uniform_disk(self.aperture, rng.next_float(), rng.next_float())
+            }
+            LensPattern::GoldenSpiral => {
+                let rotation = *self
+                    .rotation
+                    .get_or_insert_with(|| 2.0 * PI * rng.next_float());
+                let golden_angle = PI * (3.0 - Float::sqrt(5.0));
+                let r =
+                    self.aperture * ((i as Float + rng.next_float()) / self.count as Float).sqrt();
+                let theta = rotation + i as Float * golden_angle;
+
+                (r * theta.cos(), r * theta.sin())
+            }
+            LensPattern::Poisson => {
+                let distance = |p: (Float, Float)| {
+                    self.points
+                        .iter()
+                        .map(|q| (p.0 - q.0).hypot(p.1 - q.1))
+                        .fold(Float::INFINITY, Float::min)
+                };
+                let mut best = uniform_disk(self.aperture, rng.next_float(), rng.next_float());
+                if !self.points.is_empty() {
+                    let mut best_distance = distance(best);
+                    for _ in 1..POISSON_CANDIDATES {
+                        let candidate =
+                            uniform_disk(self.aperture, rng.next_float(), rng.next_float());
+                        let d = distance(candidate);
+                        if d > best_distance {
+                            best = candidate;
+                            best_distance = d;
+                        }
+                    }
+                }
+                self.points.push(best);
+
+                best
+            }
+            LensPattern::Polygon { blades, rotation } => uniform_polygon(
+                blades,
+                rotation,
+                self.aperture,
+                rng.next_float(),
+                rng.next_float(),
+            ),
+            LensPattern::Mask(ref mask) => mask.sample(self.aperture, rng),
+        }
+    }
+}
+
+fn grid_positions(x0: Float, y0: Float, n: usize) -> Vec<(Float, Float)> {
+    let n = n.max(1);
+    let step = 1.0 / n as Float;
+
+    (0..n * n)
+        .map(|i| {
+            let (sx, sy) = (i % n, i / n);
+            (
+                x0 + (sx as Float + 0.5) * step,
+                y0 + (sy as Float + 0.5) * step,
+            )
+        })
+        .collect()
+}
+
+fn luminance_variance(samples: &[(Float, Float, Color)]) -> Float {
+    let n = samples.len() as Float;
+    let mean = samples.iter().map(|(_, _, c)| c.luminance()).sum::<Float>() / n;
+
+    samples
+        .iter()
+        .map(|(_, _, c)| (c.luminance() - mean).powi(2))
+        .sum::<Float>()
+        / n
+}
+
+/// Index of a run of photon gathers that panicked, with its message.
+type RunFailure = (usize, String);
+
+/// Runs the passes of `Integrator::PhotonMapping` for `count` camera rays,
+/// with one gather each. Pass `pass` follows `ray(i, pass)` for gather `i`.
+/// Gathers are refined `run` at a time across threads; a run that panics
+/// keeps its gathers from the last pass and is returned with its message.
+/// `keep_going` is called after each pass with the number done. None when
+/// `world` uses another integrator.
+fn photon_passes<R, P>(
+    world: &World,
+    count: usize,
+    run: usize,
+    ray: R,
+    mut keep_going: P,
+) -> Option<(Vec<PhotonGather>, Vec<RunFailure>)>
+where
+    R: Fn(usize, usize) -> Ray + Sync,
+    P: FnMut(usize) -> bool,
+{
+    let (passes, photons, radius) = match world.get_settings().integrator {
+        Integrator::PhotonMapping {
+            passes,
+            photons,
+            radius,
+        } => (passes.max(1), photons, radius),
+        _ => return None,
+    };
+    let run = run.max(1);
+    let mut gathers = vec![PhotonGather::new(radius); count];
+    let mut failed: Vec<Option<String>> = vec![None; count.div_ceil(run)];
+
+    for pass in 0..passes {
+        let map = world.trace_photons(pass, photons, radius);
+        let runs = parallel_map(failed.len(), |r| {
+            catch_tile(|| {
+                (r * run..((r + 1) * run).min(count))
+                    .map(|i| {
+                        let stream = (pass * count + i) as u64;
+                        let mut rng = Rng::for_stream(world.get_settings().seed, stream);
+                        let mut gather = gathers[i];
+                        world.gather_photons(&ray(i, pass), &map, &mut gather, &mut rng);
+                        gather
+                    })
+                    .collect::<Vec<_>>()
+            })
+        });
+        for (r, result) in runs.into_iter().enumerate() {
+            match result {
+                Ok(done) => gathers[r * run..r * run + done.len()].copy_from_slice(&done),
+                Err(message) => {
+                    failed[r].get_or_insert(message);
+                }
+            }
+        }
+        if !keep_going(pass + 1) {
+            break;
+        }
+    }
+
+    let failures = failed
+        .into_iter()
+        .enumerate()
+        .filter_map(|(r, message)| message.map(|message| (r, message)))
+        .collect();
+
+    Some((gathers, failures))
+}
+
+/// Runs one tile, turning a panic into its message.
+pub(crate) fn catch_tile<T>(render: impl FnOnce() -> T) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(render)).map_err(|payload| {
+        payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "tile panicked".to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    };
+
+    use crate::{
+        float::{consts::FRAC_1_SQRT_2, Float},
+        lights::PointLight,
+        materials::Material,
+        sphere::Sphere,
+        transform::{Keyframe, Quaternion},
+        world::Background,
+    };
+
+    use super::*;
+
+    const EPSILON: Float = 1e-6;
+
+    fn equal(a: Float, b: Float) -> bool {
+        (a - b).abs() < EPSILON
+    }
+
+    fn tuples_equal(a: &Tuple4, b: &Tuple4) -> bool {
+        equal(a.x, b.x) && equal(a.y, b.y) && equal(a.z, b.z) && a.w == b.w
+    }
+
+    #[test]
+    fn test_constructing_a_camera() {
+        let c = Camera::new(160, 120, Projection::Perspective(PI / 2.0));
+
+        assert_eq!(c.get_hsize(), 160);
+        assert_eq!(c.get_vsize(), 120);
+        assert_eq!(c.get_projection(), Projection::Perspective(PI / 2.0));
+        assert_eq!(*c.get_transform(), Matrix4x4::identity());
+    }
+
+    #[test]
+    fn test_pixel_size_for_a_horizontal_canvas() {
+        let c = Camera::new(200, 125, Projection::Perspective(PI / 2.0));
+
+        assert!(equal(c.get_pixel_size(), 0.01));
+    }
+
+    #[test]
+    fn test_pixel_size_for_a_vertical_canvas() {
+        let c = Camera::new(125, 200, Projection::Perspective(PI / 2.0));
+
+        assert!(equal(c.get_pixel_size(), 0.01));
+    }
+
+    #[test]
+    fn test_a_frustum_has_independent_fields_of_view() {
+        let c = Camera::new(
+            2,
+            2,
+            Projection::Frustum {
+                horizontal: PI / 2.0,
+                vertical: PI / 3.0,
+            },
+        );
+
+        let r = c.ray_for_pixel(0, 0);
+
+        let expected = Tuple4::vector(0.5, (PI / 6.0).tan() / 2.0, -1.0).normalize();
+        assert!(tuples_equal(&r.direction, &expected));
+    }
+
+    #[test]
+    fn test_anamorphic_pixels_stretch_the_view_horizontally() {
+        let mut anamorphic = Camera::new(100, 100, Projection::Perspective(PI / 2.0));
+        anamorphic.set_pixel_aspect(2.0);
+        let mut widescreen = Camera::new(100, 100, Projection::Perspective(PI / 2.0));
+        widescreen.set_aspect_ratio(Some(2.0));
+
+        let r = anamorphic.ray_for_pixel(0, 0);
+
+        let expected = Tuple4::vector(0.99, 0.495, -1.0).normalize();
+        assert!(tuples_equal(&r.direction, &expected));
+        assert!(equal(anamorphic.get_aspect_ratio(), 2.0));
+        assert!(tuples_equal(
+            &widescreen.ray_for_pixel(0, 0).direction,
+            &expected
+        ));
+        widescreen.set_aspect_ratio(None);
+        assert!(equal(widescreen.get_aspect_ratio(), 1.0));
+    }
+
+    #[test]
+    fn test_constructing_a_ray_through_the_center_of_the_canvas() {
+        let c = Camera::new(201, 101, Projection::Perspective(PI / 2.0));
+
+        let r = c.ray_for_pixel(100, 50);
+
+        assert!(tuples_equal(&r.origin, &Tuple4::point(0.0, 0.0, 0.0)));
+        assert!(tuples_equal(&r.direction, &Tuple4::vector(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn test_constructing_a_ray_through_a_corner_of_the_canvas() {
+        let c = Camera::new(201, 101, Projection::Perspective(PI / 2.0));
+
+        let r = c.ray_for_pixel(0, 0);
+
+        assert!(tuples_equal(&r.origin, &Tuple4::point(0.0, 0.0, 0.0)));
+        assert!((r.direction - Tuple4::vector(0.66519, 0.33259, -0.66851)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn test_constructing_a_ray_when_the_camera_is_transformed() {
+        let mut c = Camera::new(201, 101, Projection::Perspective(PI / 2.0));
+        c.set_transform(Matrix4x4::rotation_y(PI / 4.0) * Matrix4x4::translation(0.0, -2.0, 5.0));
+
+        let r = c.ray_for_pixel(100, 50);
+
+        assert!(tuples_equal(&r.origin, &Tuple4::point(0.0, 2.0, -5.0)));
+        assert!(tuples_equal(
+            &r.direction,
+            &Tuple4::vector(FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2)
+        ));
+    }
+
+    #[test]
+    fn test_orthographic_pixel_size_covers_the_view_width() {
+        let c = Camera::new(200, 100, Projection::Orthographic(4.0));
+
+        assert!(equal(c.get_pixel_size(), 0.02));
+    }
+
+    #[test]
+    fn test_orthographic_rays_are_parallel() {
+        let c = Camera::new(201, 101, Projection::Orthographic(4.0));
+
+        let r1 = c.ray_for_pixel(0, 0);
+        let r2 = c.ray_for_pixel(200, 100);
+
+        assert!(tuples_equal(&r1.direction, &Tuple4::vector(0.0, 0.0, -1.0)));
+        assert!(tuples_equal(&r2.direction, &Tuple4::vector(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn test_orthographic_ray_origins_lie_on_the_view_plane() {
+        let c = Camera::new(201, 101, Projection::Orthographic(4.0));
+
+        let center = c.ray_for_pixel(100, 50);
+        let corner = c.ray_for_pixel(0, 0);
+
+        assert!(tuples_equal(&center.origin, &Tuple4::point(0.0, 0.0, 0.0)));
+        let half_pixel = c.get_pixel_size() / 2.0;
+        let half_height = 2.0 * 101.0 / 201.0;
+        assert!(tuples_equal(
+            &corner.origin,
+            &Tuple4::point(2.0 - half_pixel, half_height - half_pixel, 0.0)
+        ));
+    }
+
+    #[test]
+    fn test_orthographic_ray_when_the_camera_is_transformed() {
+        let mut c = Camera::new(201, 101, Projection::Orthographic(4.0));
+        c.set_transform(Matrix4x4::view_transform(
+            Tuple4::point(0.0, 0.0, -5.0),
+            Tuple4::point(0.0, 0.0, 0.0),
+            Tuple4::vector(0.0, 1.0, 0.0),
+        ));
+
+        let r = c.ray_for_pixel(100, 50);
+
+        assert!(tuples_equal(&r.origin, &Tuple4::point(0.0, 0.0, -5.0)));
+        assert!(tuples_equal(&r.direction, &Tuple4::vector(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn test_pinhole_camera_produces_a_single_ray_per_pixel() {
+        let mut c = Camera::new(11, 11, Projection::Perspective(PI / 2.0));
+        c.set_lens_samples(8);
+
+        let samples = c.samples_for_pixel(3, 4);
+
+        assert_eq!(samples.len(), 1);
+        assert_eq!((samples[0].0, samples[0].1), (3.5, 4.5));
+        assert_eq!(samples[0].2.direction, c.ray_for_pixel(3, 4).direction);
+    }
+
+    #[test]
+    fn test_grid_antialiasing_spreads_samples_evenly() {
+        let mut c = Camera::new(11, 11, Projection::Perspective(PI / 2.0));
+        c.set_antialiasing(Antialiasing::Grid(2));
+
+        let positions: Vec<(Float, Float)> = c
+            .samples_for_pixel(3, 4)
+            .into_iter()
+            .map(|(x, y, _)| (x, y))
+            .collect();
+
+        assert_eq!(
+            positions,
+            [(3.25, 4.25), (3.75, 4.25), (3.25, 4.75), (3.75, 4.75)]
+        );
+    }
+
+    #[test]
+    fn test_random_antialiasing_stays_inside_the_pixel() {
+        let mut c = Camera::new(11, 11, Projection::Perspective(PI / 2.0));
+        c.set_antialiasing(Antialiasing::Random(6));
+        c.set_aperture(0.2);
+        c.set_lens_samples(2);
+
+        let samples = c.samples_for_pixel(5, 1);
+
+        assert_eq!(samples.len(), 12);
+        for (x, y, _) in &samples {
+            assert!((5.0..6.0).contains(x) && (1.0..2.0).contains(y));
+        }
+        assert_eq!(samples, c.samples_for_pixel(5, 1));
+    }
+
+    #[test]
+    fn test_antialiasing_softens_silhouettes() {
+        let mut world = World::new();
+        let mut s = Sphere::new();
+        s.set_material(Material {
+            ambient: 1.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            ..Default::default()
+        });
+        world.add_object(s);
+        world.add_light(PointLight::new(
+            Tuple4::point(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let mut c = Camera::new(16, 16, Projection::Orthographic(2.5));
+        c.set_transform(Matrix4x4::view_transform(
+            Tuple4::point(0.0, 0.0, -5.0),
+            Tuple4::point(0.0, 0.0, 0.0),
+            Tuple4::vector(0.0, 1.0, 0.0),
+        ));
+        let partial = |canvas: &Canvas| {
+            (0..16)
+                .flat_map(|y| (0..16).map(move |x| (x, y)))
+                .filter(|&at| {
+                    let r = canvas.get_pixel(at).r;
+                    r > 0.0 && r < 1.0
+                })
+                .count()
+        };
+
+        let aliased = c.render(&world);
+        c.set_antialiasing(Antialiasing::Grid(4));
+        let smooth = c.render(&world);
+
+        assert_eq!(partial(&aliased), 0);
+        assert!(partial(&smooth) > 0);
+    }
+
+    #[test]
+    fn test_photon_passes_refine_every_pixel() {
+        let mut world = World::new();
+        world.add_light(PointLight::new(
+            Tuple4::point(0.0, 5.0, -5.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let mut s = Sphere::new();
+        s.set_material(Material {
+            ambient: 0.0,
+            ..Default::default()
+        });
+        world.add_object(s);
+        let mut c = Camera::new(5, 5, Projection::Orthographic(2.5));
+        c.set_transform(Matrix4x4::view_transform(
+            Tuple4::point(0.0, 0.0, -5.0),
+            Tuple4::point(0.0, 0.0, 0.0),
+            Tuple4::vector(0.0, 1.0, 0.0),
+        ));
+        let whitted = c.render(&world);
+        world.set_settings(RenderSettings {
+            integrator: Integrator::PhotonMapping {
+                passes: 3,
+                photons: 100,
+                radius: 0.1,
+            },
+            ..Default::default()
+        });
+        let passes = Mutex::new(Vec::new());
+
+        let image = c.render_photons(&world, |done| passes.lock().unwrap().push(done));
+
+        // A lone sphere cannot light itself and photon mapping has no
+        // ambient term, so only direct light is left.
+        assert_eq!(*passes.lock().unwrap(), vec![1, 2, 3]);
+        for y in 0..5 {
+            for x in 0..5 {
+                let (a, b) = (image.get_pixel((x, y)), whitted.get_pixel((x, y)));
+                assert!((a.r - b.r).abs() < EPSILON, "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_world_render_maps_photons() {
+        let mut world = World::new();
+        world.add_light(PointLight::new(
+            Tuple4::point(0.0, 5.0, -5.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        world.add_object(Sphere::new());
+        world.set_settings(RenderSettings {
+            integrator: Integrator::PhotonMapping {
+                passes: 2,
+                photons: 100,
+                radius: 0.1,
+            },
+            ..Default::default()
+        });
+        let mut c = Camera::new(6, 4, Projection::Perspective(PI / 3.0));
+        c.set_transform(Matrix4x4::view_transform(
+            Tuple4::point(0.0, 0.0, -5.0),
+            Tuple4::point(0.0, 0.0, 0.0),
+            Tuple4::vector(0.0, 1.0, 0.0),
+        ));
+        let expected = c.render_photons(&world, |_| {});
+        let previous = Canvas::new(6, 4);
+
+        let within = c.render_within(&world, Duration::from_secs(60));
+        let dirty = c.render_dirty(&world, &previous, &[Sphere::new().bounds()]);
+        let table = c.ray_table().render(&world);
+
+        for image in [within, dirty, table] {
+            assert!(image.diff(&expected).unwrap().max < EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_photon_renders_report_panicking_rows() {
+        let mut world = World::new();
+        world.set_background(Background::shader(|ray: &Ray| {
+            if ray.direction.y > 0.0 {
+                panic!("bad sky");
+            }
+            Color::new(0.0, 0.0, 0.0)
+        }));
+        world.set_settings(RenderSettings {
+            integrator: Integrator::PhotonMapping {
+                passes: 2,
+                photons: 10,
+                radius: 0.1,
+            },
+            ..Default::default()
+        });
+        let camera = Camera::new(8, 4, Projection::Perspective(PI / 2.0));
+
+        let partial = quietly(|| camera.try_render_photons(&world, |_| {})).unwrap_err();
+
+        let rows: Vec<_> = partial.failures.iter().map(|f| f.rect.y).collect();
+        assert_eq!(rows, vec![0, 1]);
+        assert_eq!(partial.failures[0].message, "bad sky");
+        assert_eq!(*partial.canvas.get_pixel((0, 0)), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_depth_render_colors_the_nearest_hit_first() {
+        let mut world = World::new();
+        world.add_object(Sphere::new());
+        let mut c = Camera::new(9, 9, Projection::Orthographic(2.5));
+        c.set_transform(Matrix4x4::view_transform(
+            Tuple4::point(0.0, 0.0, -5.0),
+            Tuple4::point(0.0, 0.0, 0.0),
+            Tuple4::vector(0.0, 1.0, 0.0),
+        ));
+
+        let depth = c.render_depth(&world, Palette::Magma);
+
+        assert_eq!(*depth.get_pixel((4, 4)), Palette::Magma.color(0.0));
+        assert_eq!(*depth.get_pixel((0, 0)), Palette::Magma.missing());
+        let edge = *depth.get_pixel((4, 1));
+        assert!(edge.luminance() > Palette::Magma.color(0.0).luminance());
+        for (x, y) in [(4, 0), (0, 4), (2, 1), (1, 2)] {
+            let pixel = *depth.get_pixel((x, y));
+            assert!(
+                pixel == Palette::Magma.missing() || pixel.luminance() > edge.luminance() * 0.5,
+                "({}, {}): {:?}",
+                x,
+                y,
+                pixel
+            );
+        }
+    }
+
+    #[test]
+    fn test_bvh_render_outlines_the_node_bounds() {
+        let mut world = World::new();
+        world.add_object(Sphere::new());
+        world.add_light(PointLight::new(
+            Tuple4::point(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let mut c = Camera::new(9, 9, Projection::Orthographic(2.5));
+        c.set_transform(Matrix4x4::view_transform(
+            Tuple4::point(0.0, 0.0, -5.0),
+            Tuple4::point(0.0, 0.0, 0.0),
+            Tuple4::vector(0.0, 1.0, 0.0),
+        ));
+
+        let plain = c.render_parallel(&world);
+        let outlined = c.render_bvh(&world, 0.3, Palette::Viridis);
+
+        let edge = Palette::Viridis.color(0.0);
+        for (x, y) in [(0, 4), (8, 4), (4, 0), (4, 8)] {
+            assert_eq!(*plain.get_pixel((x, y)), Color::new(0.0, 0.0, 0.0));
+            let pixel = *outlined.get_pixel((x, y));
+            assert!(
+                (pixel.g - edge.g).abs() < EPSILON,
+                "({}, {}): {:?}",
+                x,
+                y,
+                pixel
+            );
+        }
+        assert_eq!(outlined.get_pixel((4, 4)), plain.get_pixel((4, 4)));
+    }
+
+    #[test]
+    fn test_adaptive_sampling_refines_only_edges() {
+        let mut c = Camera::new(16, 16, Projection::Orthographic(2.5));
+        c.set_transform(Matrix4x4::view_transform(
+            Tuple4::point(0.0, 0.0, -5.0),
+            Tuple4::point(0.0, 0.0, 0.0),
+            Tuple4::vector(0.0, 1.0, 0.0),
+        ));
+        c.set_antialiasing(Antialiasing::Adaptive {
+            threshold: 0.01,
+            max_samples: 16,
+        });
+        let disc = |ray: &Ray| {
+            let inside = ray.origin.x.hypot(ray.origin.y) < 1.0;
+            Color::new(inside as u8 as Float, 0.0, 0.0)
+        };
+
+        let counts: Vec<usize> = (0..16)
+            .flat_map(|y| (0..16).map(move |x| (x, y)))
+            .map(|(x, y)| c.pixel_samples(x, y, &disc).len())
+            .collect();
+
+        assert_eq!(counts[0], 4);
+        assert_eq!(counts[8 * 16 + 8], 4);
+        assert!(counts.contains(&16));
+        assert!(counts.iter().all(|&n| n == 4 || n % 4 == 0 && n <= 16));
+    }
+
+    #[test]
+    fn test_lens_rays_converge_on_the_focal_plane() {
+        let mut c = Camera::new(11, 11, Projection::Perspective(PI / 2.0));
+        c.set_aperture(0.5);
+        c.set_focal_distance(4.0);
+        c.set_lens_samples(16);
+
+        let samples = c.samples_for_pixel(2, 7);
+
+        assert_eq!(samples.len(), 16);
+        for (x, y, r) in samples {
+            let pinhole = c.ray_at(x, y);
+            let focus = pinhole.position(4.0 / -pinhole.direction.z);
+            assert!(equal(r.origin.z, 0.0));
+            assert!(r.origin.x.hypot(r.origin.y) <= 0.5);
+            let t = (focus.z - r.origin.z) / r.direction.z;
+            assert!(tuples_equal(&r.position(t), &focus));
+        }
+    }
+
+    #[test]
+    fn test_lens_samples_are_jittered_within_the_pixel() {
+        let mut c = Camera::new(11, 11, Projection::Perspective(PI / 2.0));
+        c.set_aperture(0.5);
+        c.set_lens_samples(16);
+
+        let samples = c.samples_for_pixel(2, 7);
+
+        assert!(samples
+            .iter()
+            .all(|&(x, y, _)| (2.0..3.0).contains(&x) && (7.0..8.0).contains(&y)));
+        assert!(samples.iter().any(|&(x, _, _)| x != samples[0].0));
+    }
+
+    #[test]
+    fn test_lens_patterns_stay_on_the_aperture() {
+        let mut c = Camera::new(1, 1, Projection::Perspective(PI / 2.0));
+        c.set_aperture(0.5);
+        let closest = |points: &[(Float, Float)]| {
+            let mut closest = Float::INFINITY;
+            for (i, p) in points.iter().enumerate() {
+                for q in &points[i + 1..] {
+                    closest = closest.min((p.0 - q.0).hypot(p.1 - q.1));
+                }
+            }
+            closest
+        };
+        let mut spacing = Vec::new();
+
+        for pattern in [
+            LensPattern::UniformDisk,
+            LensPattern::GoldenSpiral,
+            LensPattern::Poisson,
+            LensPattern::Polygon {
+                blades: 5,
+                rotation: 0.3,
+            },
+        ] {
+            c.set_lens_pattern(pattern);
+            let mut rng = Rng::new(11);
+            let mut sampler = c.lens_sampler(32);
+            let points: Vec<_> = (0..32).map(|_| sampler.next(&mut rng)).collect();
+
+            assert!(points.iter().all(|p| p.0.hypot(p.1) <= 0.5 + EPSILON));
+            spacing.push(closest(&points));
+        }
+
+        assert!(
+            spacing[1] > spacing[0] && spacing[2] > spacing[0],
+            "{:?}",
+            spacing
+        );
+    }
+
+    #[test]
+    fn test_an_aperture_mask_shapes_the_lens_samples() {
+        let mut image = Canvas::new(4, 4);
+        image.put_pixel(Color::new(1.0, 1.0, 1.0), (0, 0));
+        image.put_pixel(Color::new(0.5, 0.5, 0.5), (1, 1));
+        let mut c = Camera::new(1, 1, Projection::Perspective(PI / 2.0));
+        c.set_aperture(0.5);
+        c.set_lens_pattern(LensPattern::Mask(Arc::new(
+            ApertureMask::new(&image).unwrap(),
+        )));
+        let mut rng = Rng::new(2);
+        let mut sampler = c.lens_sampler(64);
+
+        let points: Vec<_> = (0..64).map(|_| sampler.next(&mut rng)).collect();
+
+        assert!(points.iter().all(|&(x, y)| x < 0.0 && y > 0.0));
+        let corner = points
+            .iter()
+            .filter(|&&(x, y)| x < -0.25 && y > 0.25)
+            .count();
+        assert!(corner > 32, "{}", corner);
+        assert_eq!(ApertureMask::new(&Canvas::new(4, 4)), None);
+    }
+
+    #[test]
+    fn test_a_rolling_shutter_skews_a_moving_camera() {
+        let mut c = Camera::new(10, 10, Projection::Orthographic(2.0));
+        c.set_motion(Some(Transform::identity().keyframe(Keyframe {
+            time: 1.0,
+            translation: Tuple4::vector(1.0, 0.0, 0.0),
+            rotation: Quaternion::identity(),
+            scale: Tuple4::vector(1.0, 1.0, 1.0),
+        })));
+        let shift = |c: &Camera, py: usize| {
+            let (_, _, ray) = c.samples_for_pixel(5, py)[0];
+            ray.origin.x - c.ray_for_pixel(5, py).origin.x
+        };
+
+        c.set_shutter(Shutter {
+            open: 0.0,
+            close: 0.0,
+            readout: 1.0,
+        });
+        let (top, bottom) = (shift(&c, 0), shift(&c, 9));
+        c.set_shutter(Shutter {
+            open: 0.5,
+            close: 0.5,
+            readout: 0.0,
+        });
+        let (global_top, global_bottom) = (shift(&c, 0), shift(&c, 9));
+
+        assert!(
+            equal(top, 0.05) && equal(bottom, 0.95),
+            "{} {}",
+            top,
+            bottom
+        );
+        assert!(equal(global_top, 0.5) && equal(global_bottom, 0.5));
+    }
+
+    #[test]
+    fn test_lens_samples_are_deterministic_per_pixel() {
+        let mut c = Camera::new(11, 11, Projection::Perspective(PI / 2.0));
+        c.set_aperture(0.5);
+        c.set_lens_samples(4);
+
+        let a = c.samples_for_pixel(5, 5);
+        let b = c.samples_for_pixel(5, 5);
+
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert_eq!((a.0, a.1), (b.0, b.1));
+            assert_eq!(a.2.origin, b.2.origin);
+            assert_eq!(a.2.direction, b.2.direction);
+        }
+    }
+
+    #[test]
+    fn test_the_seed_changes_lens_samples() {
+        let mut c = Camera::new(11, 11, Projection::Perspective(PI / 2.0));
+        c.set_aperture(0.5);
+        c.set_lens_samples(4);
+        let origins = |c: &Camera| {
+            c.samples_for_pixel(5, 5)
+                .iter()
+                .map(|(_, _, ray)| ray.origin)
+                .collect::<Vec<_>>()
+        };
+
+        let first = origins(&c);
+        c.set_seed(3);
+
+        assert_eq!(c.get_seed(), 3);
+        assert_ne!(first, origins(&c));
+    }
+
+    #[test]
+    fn test_render_with_averages_lens_samples() {
+        let mut c = Camera::new(3, 2, Projection::Perspective(PI / 2.0));
+        c.set_aperture(0.1);
+        c.set_lens_samples(4);
+
+        let image = c.render_with(|_| Color::new(0.5, 0.25, 1.0));
+
+        assert_eq!(image.get_width(), 3);
+        assert_eq!(image.get_height(), 2);
+        assert_eq!(*image.get_pixel((2, 1)), Color::new(0.5, 0.25, 1.0));
+    }
+
+    #[test]
+    fn test_default_filter_is_a_pixel_sized_box() {
+        let c = Camera::new(3, 2, Projection::Perspective(PI / 2.0));
+
+        assert_eq!(c.get_filter(), Filter::Box(0.5));
+    }
+
+    #[test]
+    fn test_wide_filter_blurs_across_pixel_boundaries() {
+        let mut c = Camera::new(4, 1, Projection::Orthographic(4.0));
+        c.set_filter(Filter::Tent(1.5));
+
+        let image = c.render_with(|ray| {
+            if ray.origin.x > 0.0 {
+                Color::new(1.0, 1.0, 1.0)
+            } else {
+                Color::new(0.0, 0.0, 0.0)
+            }
+        });
+
+        let left = image.get_pixel((1, 0)).r;
+        let right = image.get_pixel((2, 0)).r;
+        assert!(left > 0.0 && left < 1.0);
+        assert!(right > 0.0 && right < 1.0);
+    }
+
+    #[test]
+    fn test_tiled_render_matches_the_serial_render() {
+        let mut c = Camera::new(13, 9, Projection::Perspective(PI / 2.0));
+        c.set_aperture(0.2);
+        c.set_lens_samples(4);
+        c.set_filter(Filter::Mitchell {
+            radius: 2.0,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        });
+        let shade = |ray: &Ray| {
+            let d = ray.direction;
+            Color::new(d.x.abs(), d.y.abs(), (d.x * 7.0).sin().abs())
+        };
+
+        let serial = c.render_with(shade);
+        let tiled = c.render_tiled(4, shade);
+
+        for (a, b) in serial.into_iter().zip(tiled) {
+            assert!((a.r - b.r).abs() < 1e-14 && (a.g - b.g).abs() < 1e-14);
+            assert!((a.b - b.b).abs() < 1e-14);
+        }
+    }
+
+    #[test]
+    fn test_dirty_rerender_matches_a_full_render_after_a_move() {
+        let scene = || {
+            let mut world = World::new();
+            world.add_light(PointLight::new(
+                Tuple4::point(0.0, 10.0, -10.0),
+                Color::new(1.0, 1.0, 1.0),
+            ));
+            world.add_object(Sphere::new());
+            let mut small = Sphere::new();
+            small.set_transform(
+                Matrix4x4::translation(4.0, 0.0, 0.0) * Matrix4x4::scaling(0.5, 0.5, 0.5),
+            );
+            world.add_object(small);
+            world
+        };
+        let mut c = Camera::new(96, 48, Projection::Perspective(PI / 2.0));
+        c.set_transform(Matrix4x4::view_transform(
+            Tuple4::point(0.0, 0.0, -6.0),
+            Tuple4::point(0.0, 0.0, 0.0),
+            Tuple4::vector(0.0, 1.0, 0.0),
+        ));
+        c.set_tile_size(8);
+
+        for filter in [
+            Filter::default(),
+            Filter::Mitchell {
+                radius: 2.0,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            },
+        ] {
+            c.set_filter(filter);
+            let mut world = scene();
+            let before = c.render_parallel(&world);
+            let mut moved = world.get_objects()[1].clone();
+            moved.set_transform(
+                Matrix4x4::translation(4.0, 1.0, 0.0) * Matrix4x4::scaling(0.5, 0.5, 0.5),
+            );
+            let old = world.replace_object(1, moved);
+            let changed = [old.bounds(), world.get_objects()[1].bounds()];
+            let calls = AtomicUsize::new(0);
+
+            let updated = c.render_dirty_with(&before, &changed, |ray| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                world.color_at(ray)
+            });
+
+            let full = c.render_parallel(&world);
+            assert!(updated.diff(&full).unwrap().max < 1e-12, "{:?}", filter);
+            assert!(before.diff(&full).unwrap().max > 0.1);
+            assert!(calls.load(Ordering::Relaxed) < 96 * 48 / 2, "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn test_tile_film_is_padded_by_the_filter_footprint() {
+        let mut c = Camera::new(16, 16, Projection::Perspective(PI / 2.0));
+        c.set_filter(Filter::Tent(1.5));
+
+        let film = c.render_tile(4, 8, 4, &|_: &Ray| Color::new(1.0, 1.0, 1.0));
+
+        assert_eq!(film.get_offset(), (3, 7));
+        assert_eq!(film.get_width(), 6);
+        assert_eq!(film.get_height(), 6);
+    }
+
+    #[test]
+    fn test_ray_table_holds_every_sample() {
+        let mut c = Camera::new(5, 3, Projection::Perspective(PI / 2.0));
+        c.set_aperture(0.2);
+        c.set_lens_samples(3);
+
+        let table = c.ray_table();
+
+        assert_eq!(table.len(), 45);
+        assert_eq!(
+            table.samples_for_pixel(4, 1),
+            c.samples_for_pixel(4, 1).as_slice()
+        );
+    }
+
+    #[test]
+    fn test_rendering_from_a_ray_table_matches_the_camera() {
+        let mut c = Camera::new(9, 7, Projection::Perspective(PI / 2.0));
+        c.set_aperture(0.2);
+        c.set_lens_samples(4);
+        c.set_filter(Filter::Tent(1.5));
+        let shade = |ray: &Ray| Color::new(ray.direction.x.abs(), ray.direction.y.abs(), 0.5);
+
+        let direct = c.render_with(shade);
+        let cached = c.ray_table().render_with(shade);
+
+        for (a, b) in direct.into_iter().zip(cached) {
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn test_fisheye_center_ray_looks_down_the_view_axis() {
+        let c = Camera::new(101, 101, Projection::Fisheye(PI));
+
+        let r = c.ray_for_pixel(50, 50);
+
+        assert!(tuples_equal(&r.direction, &Tuple4::vector(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn test_fisheye_edge_ray_is_at_half_the_field_of_view() {
+        let c = Camera::new(100, 100, Projection::Fisheye(PI));
+
+        let r = c.ray_for_pixel(0, 49);
+
+        let angle = r.direction.dot(&Tuple4::vector(0.0, 0.0, -1.0)).acos();
+        let expected = PI / 2.0 - c.get_pixel_size() / 2.0;
+        assert!((angle - expected).abs() < c.get_pixel_size());
+        assert!(r.direction.x > 0.0);
+    }
+
+    #[test]
+    fn test_equirectangular_covers_the_full_sphere() {
+        let c = Camera::new(400, 200, Projection::Equirectangular);
+
+        let front = c.ray_for_pixel(200, 100);
+        let left = c.ray_for_pixel(100, 100);
+        let back = c.ray_for_pixel(0, 100);
+        let up = c.ray_for_pixel(200, 0);
+
+        assert!(front.direction.z < -0.999);
+        assert!(left.direction.x > 0.999);
+        assert!(back.direction.z > 0.999);
+        assert!(up.direction.y > 0.999);
+    }
+
+    #[test]
+    fn test_equirectangular_rays_follow_the_camera_transform() {
+        let mut c = Camera::new(400, 200, Projection::Equirectangular);
+        c.set_transform(Matrix4x4::translation(0.0, 0.0, 5.0));
+
+        let r = c.ray_for_pixel(200, 100);
+
+        assert!(tuples_equal(&r.origin, &Tuple4::point(0.0, 0.0, -5.0)));
+    }
+
+    #[test]
+    fn test_rendering_a_world_with_a_camera() {
+        let mut world = World::new();
+        world.add_light(PointLight::new(
+            Tuple4::point(-10.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let mut s1 = Sphere::new();
+        s1.set_material(Material {
+            color: Color::new(0.8, 1.0, 0.6),
+            diffuse: 0.7,
+            specular: 0.2,
+            ..Default::default()
+        });
+        let mut s2 = Sphere::new();
+        s2.set_transform(Matrix4x4::scaling(0.5, 0.5, 0.5));
+        world.add_object(s1);
+        world.add_object(s2);
+        let mut c = Camera::new(11, 11, Projection::Perspective(PI / 2.0));
+        c.set_transform(Matrix4x4::view_transform(
+            Tuple4::point(0.0, 0.0, -5.0),
+            Tuple4::point(0.0, 0.0, 0.0),
+            Tuple4::vector(0.0, 1.0, 0.0),
+        ));
+
+        let image = c.render(&world);
+
+        let pixel = image.get_pixel((5, 5));
+        assert!((pixel.r - 0.38066).abs() < 1e-5);
+        assert!((pixel.g - 0.47583).abs() < 1e-5);
+        assert!((pixel.b - 0.2855).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_parallel_render_matches_the_serial_render() {
+        let (world, mut camera) = crate::presets::three_spheres(24, 12);
+        camera.set_tile_size(5);
+
+        let serial = camera.render(&world);
+        let parallel = camera.render_parallel(&world);
+
+        for (a, b) in serial.into_iter().zip(parallel) {
+            assert!(equal(a.r, b.r) && equal(a.g, b.g) && equal(a.b, b.b));
+        }
+    }
+
+    #[test]
+    fn test_tiles_past_the_deadline_take_one_sample_per_pixel() {
+        let mut c = Camera::new(6, 4, Projection::Perspective(PI / 2.0));
+        c.set_antialiasing(Antialiasing::Grid(2));
+        let shaded = AtomicUsize::new(0);
+        let shade = |_: &Ray| {
+            shaded.fetch_add(1, Ordering::Relaxed);
+            Color::new(1.0, 0.5, 0.25)
+        };
+
+        let late_shaded = AtomicUsize::new(0);
+        let late_shade = |ray: &Ray| {
+            late_shaded.fetch_add(1, Ordering::Relaxed);
+            shade(ray)
+        };
+
+        let late = c.render_tiled_within(2, Duration::ZERO, shade, late_shade);
+        assert_eq!(late_shaded.swap(0, Ordering::Relaxed), 24);
+        assert_eq!(shaded.swap(0, Ordering::Relaxed), 24);
+        let on_time = c.render_tiled_within(2, Duration::from_secs(3600), shade, late_shade);
+        assert_eq!(shaded.load(Ordering::Relaxed), 96);
+        assert_eq!(late_shaded.load(Ordering::Relaxed), 0);
+
+        for (a, b) in late.into_iter().zip(on_time) {
+            assert!(equal(a.r, b.r) && equal(a.g, b.g) && equal(a.b, b.b));
+        }
+    }
+
+    #[test]
+    fn test_late_tiles_shade_with_draft_settings() {
+        let (mut world, mut c) = crate::presets::three_spheres(12, 6);
+        world.set_settings(RenderSettings {
+            shadow_samples: 16,
+            integrator: Integrator::PathTracer {
+                samples: 4,
+                max_bounces: 2,
+            },
+            ..Default::default()
+        });
+        c.set_antialiasing(Antialiasing::None);
+
+        let late = c.render_within(&world, Duration::ZERO);
+
+        world.set_settings(RenderSettings::draft());
+        let draft = c.render(&world);
+        for (a, b) in late.into_iter().zip(draft) {
+            assert!(equal(a.r, b.r) && equal(a.g, b.g) && equal(a.b, b.b));
+        }
+    }
+
+    #[test]
+    fn test_progressive_render_reports_every_tile_once() {
+        let c = Camera::new(10, 7, Projection::Perspective(PI / 2.0));
+        let shade = |ray: &Ray| Color::new(ray.direction.x.abs(), ray.direction.y.abs(), 0.0);
+        let tiles = Mutex::new(Vec::new());
+
+        let canvas = c.render_progressive(4, shade, |rect, colors| {
+            assert_eq!(colors.len(), rect.width * rect.height);
+            tiles.lock().unwrap().push((rect, colors.to_vec()));
+        });
+
+        let tiles = tiles.into_inner().unwrap();
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles.iter().map(|(r, _)| r.width * r.height).sum::<usize>(),
+            70
+        );
+        for (rect, colors) in &tiles {
+            for (i, color) in colors.iter().enumerate() {
+                let (x, y) = (rect.x + i % rect.width, rect.y + i / rect.width);
+                assert_eq!(canvas.get_pixel((x, y)), color);
+            }
+        }
+    }
+
+    /// Runs `f` with the panic hook silenced, so tiles that panic on purpose
+    /// do not print their messages over the test output.
+    fn quietly<T>(f: impl FnOnce() -> T) -> T {
+        static HOOK: Mutex<()> = Mutex::new(());
+        let _guard = HOOK.lock().unwrap_or_else(|e| e.into_inner());
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+        let result = panic::catch_unwind(AssertUnwindSafe(f));
+        panic::set_hook(hook);
+
+        result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+    }
+
+    #[test]
+    fn test_a_panicking_tile_does_not_lose_the_others() {
+        let c = Camera::new(8, 4, Projection::Perspective(PI / 2.0));
+        let shade = |ray: &Ray| {
+            if ray.direction.x < 0.0 {
+                panic!("bad sample");
+            }
+            Color::new(1.0, 1.0, 1.0)
+        };
+
+        let partial = quietly(|| c.try_render_tiled(4, shade)).unwrap_err();
+
+        assert_eq!(
+            partial.failures,
+            [TileFailure {
+                rect: TileRect {
+                    x: 4,
+                    y: 0,
+                    width: 4,
+                    height: 4
+                },
+                message: "bad sample".to_string(),
+            }]
+        );
+        assert_eq!(*partial.canvas.get_pixel((1, 1)), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(*partial.canvas.get_pixel((6, 1)), Color::new(0.0, 0.0, 0.0));
+        assert!(c.try_render_tiled(4, |_| Color::new(0.0, 0.0, 0.0)).is_ok());
+    }
+
+    #[test]
+    fn test_progress_is_reported_for_every_tile() {
+        let (world, mut camera) = crate::presets::three_spheres(10, 6);
+        camera.set_tile_size(4);
+        let reports = Mutex::new(Vec::new());
+
+        camera.render_with_progress(&world, |progress| reports.lock().unwrap().push(progress));
+
+        let reports = reports.into_inner().unwrap();
+        let last = reports.last().unwrap();
+        assert_eq!(reports.len(), 6);
+        assert_eq!((last.tiles_done, last.tiles_total), (6, 6));
+        assert_eq!((last.pixels_done, last.pixels_total), (60, 60));
+        assert_eq!(last.fraction(), 1.0);
+        assert_eq!(last.estimated_remaining(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_progress_renders_report_panicking_tiles() {
+        let mut world = World::new();
+        world.set_background(Background::shader(|ray: &Ray| {
+            if ray.direction.x < 0.0 {
+                panic!("bad sky");
+            }
+            Color::new(0.0, 0.0, 0.0)
+        }));
+        let mut camera = Camera::new(8, 4, Projection::Perspective(PI / 2.0));
+        camera.set_tile_size(4);
+        let reports = Mutex::new(0);
+
+        let partial =
+            quietly(|| camera.try_render_with_progress(&world, |_| *reports.lock().unwrap() += 1))
+                .unwrap_err();
+
+        assert_eq!(partial.failures.len(), 1);
+        assert_eq!(partial.failures[0].rect.x, 4);
+        assert_eq!(partial.failures[0].message, "bad sky");
+        assert_eq!(*reports.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_estimating_the_remaining_time() {
+        let mut progress = RenderProgress {
+            tiles_done: 0,
+            tiles_total: 4,
+            pixels_done: 0,
+            pixels_total: 400,
+            elapsed: Duration::from_secs(1),
+        };
+        assert_eq!(progress.estimated_remaining(), None);
+
+        progress.pixels_done = 100;
+
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(progress.estimated_remaining(), Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn test_tile_size_defaults_to_32_and_is_at_least_one() {
+        let mut c = Camera::new(8, 8, Projection::Perspective(PI / 2.0));
+        assert_eq!(c.get_tile_size(), 32);
+
+        c.set_tile_size(0);
+
+        assert_eq!(c.get_tile_size(), 1);
+    }
+
+    fn framing_world() -> World {
+        let mut world = World::new();
+        let mut s1 = Sphere::new();
+        s1.set_transform(Matrix4x4::translation(10.0, 0.0, 0.0));
+        let mut s2 = Sphere::new();
+        s2.set_transform(Matrix4x4::translation(14.0, 2.0, 0.0));
+        world.add_object(s1);
+        world.add_object(s2);
+
+        world
+    }
+
+    fn sees_every_object(c: &Camera, world: &World) -> bool {
+        world.get_objects().iter().all(|object| {
+            (0..c.get_vsize()).any(|y| {
+                (0..c.get_hsize()).any(|x| object.intersect(&c.ray_for_pixel(x, y)).hit().is_some())
+            })
+        })
+    }
+
+    #[test]
+    fn test_framing_an_empty_world_leaves_the_camera_alone() {
+        let mut c = Camera::new(20, 10, Projection::Perspective(PI / 3.0));
+
+        c.frame(&World::new(), Tuple4::vector(0.0, 0.0, 1.0), 1.1);
+
+        assert_eq!(*c.get_transform(), Matrix4x4::identity());
+    }
+
+    #[test]
+    fn test_framing_points_the_camera_at_the_scene_center() {
+        let world = framing_world();
+        let mut c = Camera::new(21, 11, Projection::Perspective(PI / 3.0));
+
+        c.frame(&world, Tuple4::vector(0.0, 0.0, 1.0), 1.1);
+
+        let r = c.ray_for_pixel(10, 5);
+        assert!(equal(r.direction.x, 0.0));
+        assert!(equal(r.direction.y, 0.0));
+        assert!(equal(r.direction.z, 1.0));
+        assert!(equal(r.origin.x, 12.0));
+        assert!(equal(r.origin.y, 1.0));
+        assert!(sees_every_object(&c, &world));
+    }
+
+    #[test]
+    fn test_framing_keeps_the_whole_scene_in_view() {
+        let world = framing_world();
+        let mut c = Camera::new(40, 20, Projection::Perspective(PI / 3.0));
+
+        c.frame(&world, Tuple4::vector(1.0, -1.0, 1.0), 1.0);
+
+        let corner = c.ray_for_pixel(0, 0);
+        assert!(world.intersect(&corner).hit().is_none());
+        assert!(sees_every_object(&c, &world));
+    }
+
+    #[test]
+    fn test_framing_straight_down_uses_a_fallback_up_vector() {
+        let world = framing_world();
+        let mut c = Camera::new(20, 20, Projection::Orthographic(1.0));
+
+        c.frame(&world, Tuple4::vector(0.0, -1.0, 0.0), 1.0);
+
+        let r = c.ray_for_pixel(10, 10);
+        assert!(equal(r.direction.y, -1.0));
+        assert!(sees_every_object(&c, &world));
+    }
+
+    #[test]
+    fn test_framing_a_portrait_orthographic_view_fits_the_width() {
+        let world = framing_world();
+        let (min, max) = world.summary().extent.unwrap();
+        let radius = (max - min).magnitude() / 2.0;
+        let mut c = Camera::new(10, 30, Projection::Orthographic(1.0));
+
+        c.frame(&world, Tuple4::vector(0.0, 0.0, 1.0), 1.0);
+
+        assert!(c.half_width.min(c.half_height) >= radius - EPSILON);
+        assert!(equal(c.half_height, 3.0 * c.half_width));
+        assert!(sees_every_object(&c, &world));
+    }
+
+    #[test]
+    fn test_turntable_produces_the_requested_number_of_frames() {
+        let frames = turntable(Tuple4::point(0.0, 0.0, 0.0), 5.0, 0.0, 12);
+
+        assert_eq!(frames.len(), 12);
+    }
+
+    #[test]
+    fn test_turntable_starts_in_front_of_the_target() {
+        let target = Tuple4::point(1.0, 2.0, 3.0);
+
+        let frames = turntable(target, 5.0, 0.0, 4);
+
+        let expected = Matrix4x4::view_transform(
+            Tuple4::point(1.0, 2.0, -2.0),
+            target,
+            Tuple4::vector(0.0, 1.0, 0.0),
+        );
+        assert_eq!(frames[0], expected);
+    }
+
+    #[test]
+    fn test_turntable_orbits_at_constant_distance_and_elevation() {
+        let target = Tuple4::point(1.0, 2.0, 3.0);
+
+        let frames = turntable(target, 5.0, PI / 6.0, 8);
+
+        for m in frames {
+            let eye = m.inverse().unwrap() * Tuple4::point(0.0, 0.0, 0.0);
+            let forward = m.inverse().unwrap() * Tuple4::vector(0.0, 0.0, -1.0);
+            assert!(equal((eye - target).magnitude(), 5.0));
+            assert!(equal(eye.y - target.y, 2.5));
+            assert!(tuples_equal(&forward, &(target - eye).normalize()));
+        }
+    }
+
+    #[test]
+    fn test_turntable_quarter_turn_looks_from_the_side() {
+        let target = Tuple4::point(0.0, 0.0, 0.0);
+
+        let frames = turntable(target, 5.0, 0.0, 4);
+
+        let eye = frames[1].inverse().unwrap() * Tuple4::point(0.0, 0.0, 0.0);
+        assert!(tuples_equal(&eye, &Tuple4::point(5.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_autofocus_on_the_object_under_a_pixel() {
+        let mut world = World::new();
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4x4::translation(0.0, 0.0, 3.0));
+        world.add_object(s);
+        let mut c = Camera::new(11, 11, Projection::Perspective(PI / 2.0));
+        c.set_transform(Matrix4x4::view_transform(
+            Tuple4::point(0.0, 0.0, -5.0),
+            Tuple4::point(0.0, 0.0, 0.0),
+            Tuple4::vector(0.0, 1.0, 0.0),
+        ));
+
+        let distance = c.autofocus(&world, (5, 5));
+
+        assert_eq!(distance, Some(7.0));
+        assert_eq!(c.get_focal_distance(), 7.0);
+    }
+
+    #[test]
+    fn test_autofocus_measures_along_the_view_axis() {
+        let mut world = World::new();
+        let mut s = Sphere::new();
+        s.set_transform(
+            Matrix4x4::translation(0.0, 0.0, -1.0) * Matrix4x4::scaling(10.0, 10.0, 0.01),
+        );
+        world.add_object(s);
+        let mut c = Camera::new(11, 11, Projection::Perspective(PI / 2.0));
+
+        let distance = c.autofocus(&world, (0, 0)).unwrap();
+
+        assert!((distance - 0.99).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_autofocus_on_empty_space_keeps_the_focal_distance() {
+        let mut c = Camera::new(11, 11, Projection::Perspective(PI / 2.0));
+        c.set_focal_distance(3.0);
+
+        let distance = c.autofocus(&World::new(), (5, 5));
+
+        assert_eq!(distance, None);
+        assert_eq!(c.get_focal_distance(), 3.0);
+    }
+}
@@ -1,11 +1,58 @@
-use crate::{color::Color, ppm::PPM};
+#[cfg(feature = "image")]
+use crate::ppm::RGB;
+use crate::{
+    color::{Color, WhitePoint},
+    float::Float,
+    palette::Palette,
+    ppm::PPM,
+};
 
+/// 3x5 bitmaps of the digits 0-9 used to label contact sheets, one row
+/// per entry with the leftmost pixel in the highest bit.
+const DIGITS: [[u8; 5]; 10] = [
+    [7, 5, 5, 5, 7],
+    [2, 6, 2, 2, 7],
+    [7, 1, 7, 4, 7],
+    [7, 1, 3, 1, 7],
+    [5, 5, 7, 1, 1],
+    [7, 4, 7, 1, 7],
+    [7, 4, 7, 5, 7],
+    [7, 1, 1, 1, 1],
+    [7, 5, 7, 5, 7],
+    [7, 5, 7, 1, 7],
+];
+const SHEET_GAP: usize = 2;
+const LABEL_HEIGHT: usize = 7;
+/// 4x4 Bayer matrix, the order in which ordered dithering rounds pixels up.
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// How `Canvas::dither` hides the steps between neighbouring 8-bit values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dither {
+    /// Rounds every pixel to the nearest value.
+    #[default]
+    None,
+    /// Offsets the rounding by a repeating Bayer pattern. Cheap and stable
+    /// between frames, but the pattern can show in flat areas.
+    Ordered,
+    /// Floyd-Steinberg error diffusion: each pixel's rounding error is
+    /// pushed onto the pixels right of and below it.
+    FloydSteinberg,
+}
+
+#[derive(Debug, Clone)]
 pub struct Canvas {
     width: usize,
     height: usize,
     pixels: Vec<Color>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageDiff {
+    pub max: Float,
+    pub mean: Float,
+}
+
 impl Canvas {
     pub fn new(width: usize, height: usize) -> Canvas {
         let pixels = vec![Color::new(0.0, 0.0, 0.0); width * height];
@@ -40,6 +87,235 @@ impl Canvas {
         let i = self.to_index(at);
         &self.pixels[i]
     }
+
+    /// Like `put_pixel`, but positions off the canvas are silently ignored,
+    /// so plotting code does not need its own bounds checks.
+    pub fn put_pixel_clipped(&mut self, pixel: Color, at: (i64, i64)) {
+        if let Some(at) = self.clip(at) {
+            self.put_pixel(pixel, at);
+        }
+    }
+
+    /// Like `get_pixel`, returning `None` for positions off the canvas.
+    pub fn get_pixel_clipped(&self, at: (i64, i64)) -> Option<&Color> {
+        self.clip(at).map(|at| self.get_pixel(at))
+    }
+
+    fn clip(&self, (x, y): (i64, i64)) -> Option<(usize, usize)> {
+        let x = usize::try_from(x).ok().filter(|&x| x < self.width)?;
+        let y = usize::try_from(y).ok().filter(|&y| y < self.height)?;
+
+        Some((x, y))
+    }
+
+    /// Per-channel absolute difference between two images of the same size.
+    pub fn diff(&self, other: &Canvas) -> Option<ImageDiff> {
+        if self.width != other.width || self.height != other.height {
+            return None;
+        }
+
+        let mut max: Float = 0.0;
+        let mut sum = 0.0;
+        for (a, b) in self.pixels.iter().zip(other.pixels.iter()) {
+            for d in [a.r - b.r, a.g - b.g, a.b - b.b] {
+                max = max.max(d.abs());
+                sum += d.abs();
+            }
+        }
+        let channels = (self.pixels.len() * 3).max(1) as Float;
+
+        Some(ImageDiff {
+            max,
+            mean: sum / channels,
+        })
+    }
+
+    /// Treats the red channel as a measurement, e.g. a distance, and maps it
+    /// through `palette` from the smallest to the largest finite value.
+    /// Pixels without a finite value come out in `Palette::missing`.
+    pub fn false_color(&self, palette: Palette) -> Canvas {
+        let finite = self.pixels.iter().map(|c| c.r).filter(|v| v.is_finite());
+        let (min, max) = finite.fold((Float::INFINITY, Float::NEG_INFINITY), |(lo, hi), v| {
+            (lo.min(v), hi.max(v))
+        });
+        let range = if max > min { max - min } else { 1.0 };
+
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels: self
+                .pixels
+                .iter()
+                .map(|c| {
+                    if c.r.is_finite() {
+                        palette.color((c.r - min) / range)
+                    } else {
+                        palette.missing()
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Copy with every channel rounded to one of the 256 levels an 8-bit
+    /// encoder writes, spreading the rounding error as `dither` says so
+    /// smooth gradients do not band. Encoding the copy stores its values
+    /// as they are.
+    pub fn dither(&self, dither: Dither) -> Canvas {
+        let quantize = |n: Float| (n * 255.0).clamp(0.0, 255.0).round() / 255.0;
+        let mut pixels = self.pixels.clone();
+
+        match dither {
+            Dither::None => {
+                for c in pixels.iter_mut() {
+                    *c = Color::new(quantize(c.r), quantize(c.g), quantize(c.b));
+                }
+            }
+            Dither::Ordered => {
+                for (i, c) in pixels.iter_mut().enumerate() {
+                    let (x, y) = (i % self.width, i / self.width);
+                    let offset = ((BAYER[y % 4][x % 4] as Float + 0.5) / 16.0 - 0.5) / 255.0;
+                    *c = Color::new(
+                        quantize(c.r + offset),
+                        quantize(c.g + offset),
+                        quantize(c.b + offset),
+                    );
+                }
+            }
+            Dither::FloydSteinberg => {
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        let i = y * self.width + x;
+                        let old = pixels[i];
+                        let new = Color::new(quantize(old.r), quantize(old.g), quantize(old.b));
+                        pixels[i] = new;
+                        let error = old - new;
+                        let mut spread = |dx: i64, dy: usize, weight: Float| {
+                            let nx = x as i64 + dx;
+                            if nx >= 0 && (nx as usize) < self.width && y + dy < self.height {
+                                let j = (y + dy) * self.width + nx as usize;
+                                pixels[j] = pixels[j] + error * weight;
+                            }
+                        };
+                        spread(1, 0, 7.0 / 16.0);
+                        spread(-1, 1, 3.0 / 16.0);
+                        spread(0, 1, 5.0 / 16.0);
+                        spread(1, 1, 1.0 / 16.0);
+                    }
+                }
+            }
+        }
+
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+
+    /// Box-filtered copy at most `width` pixels wide with the same aspect
+    /// ratio, for quick previews. Narrower canvases are copied as they are.
+    pub fn downscale(&self, width: usize) -> Canvas {
+        let width = width.clamp(1, self.width.max(1));
+        let height = (self.height * width / self.width.max(1)).max(1);
+        let span = |i: usize, from: usize, to: usize| {
+            let start = i * from / to;
+            start..((i + 1) * from / to).max(start + 1)
+        };
+
+        let mut canvas = Canvas::new(width, height);
+        for y in 0..height {
+            let rows = span(y, self.height, height);
+            for x in 0..width {
+                let cols = span(x, self.width, width);
+                let count = (rows.len() * cols.len()) as Float;
+                let sum: Color = rows
+                    .clone()
+                    .flat_map(|sy| cols.clone().map(move |sx| (sx, sy)))
+                    .map(|at| *self.get_pixel(at))
+                    .sum();
+                canvas.put_pixel(sum * (1.0 / count), (x, y));
+            }
+        }
+
+        canvas
+    }
+
+    /// Copy with every pixel encoded by `Color::to_srgb`, ready to be
+    /// written out by an encoder that stores values as they are.
+    pub fn to_srgb(&self) -> Canvas {
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(Color::to_srgb).collect(),
+        }
+    }
+
+    /// 8-bit image of the canvas, encoded by `Color::to_srgb` first when
+    /// `srgb` is set. `image::RgbImage::from` stores values as they are.
+    #[cfg(feature = "image")]
+    pub fn to_rgb_image(&self, srgb: bool) -> image::RgbImage {
+        if srgb {
+            image::RgbImage::from(&self.to_srgb())
+        } else {
+            image::RgbImage::from(self)
+        }
+    }
+
+    /// Copy with every pixel adapted by `Color::adapt` from the scene's
+    /// white point to the one the output is viewed under.
+    pub fn adapt(&self, from: WhitePoint, to: WhitePoint) -> Canvas {
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(|c| c.adapt(from, to)).collect(),
+        }
+    }
+
+    /// Lays `canvases` out left to right in rows of `columns`, each in a
+    /// cell as large as the largest of them and labelled underneath with
+    /// its index, so variations of a render can be compared side by side.
+    pub fn contact_sheet(canvases: &[Canvas], columns: usize) -> Canvas {
+        let columns = columns.clamp(1, canvases.len().max(1));
+        let rows = canvases.len().div_ceil(columns);
+        let cell_width = canvases.iter().map(Canvas::get_width).max().unwrap_or(0);
+        let cell_height = canvases.iter().map(Canvas::get_height).max().unwrap_or(0);
+        let (step_x, step_y) = (
+            cell_width + SHEET_GAP,
+            cell_height + LABEL_HEIGHT + SHEET_GAP,
+        );
+
+        let mut sheet = Canvas::new(columns * step_x + SHEET_GAP, rows * step_y + SHEET_GAP);
+        for (i, canvas) in canvases.iter().enumerate() {
+            let x0 = SHEET_GAP + (i % columns) * step_x;
+            let y0 = SHEET_GAP + (i / columns) * step_y;
+            for y in 0..canvas.height {
+                for x in 0..canvas.width {
+                    sheet.put_pixel(*canvas.get_pixel((x, y)), (x0 + x, y0 + y));
+                }
+            }
+            sheet.draw_number(i, (x0, y0 + cell_height + 1));
+        }
+
+        sheet
+    }
+
+    /// Writes `n` in white digits with their top left corner at `at`,
+    /// clipped to the canvas.
+    fn draw_number(&mut self, n: usize, at: (usize, usize)) {
+        let white = Color::new(1.0, 1.0, 1.0);
+        for (i, digit) in n.to_string().bytes().enumerate() {
+            let glyph = DIGITS[(digit - b'0') as usize];
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (4 >> col) != 0 {
+                        let x = at.0 + i * 4 + col;
+                        self.put_pixel_clipped(white, (x as i64, (at.1 + row) as i64));
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl IntoIterator for Canvas {
@@ -65,10 +341,61 @@ impl PPM<Color> for Canvas {
     }
 }
 
+/// Clamps and rounds every channel to 8 bits, as the PPM encoder does.
+/// Use `Canvas::to_rgb_image` to encode the pixels as sRGB on the way.
+#[cfg(feature = "image")]
+impl From<&Canvas> for image::RgbImage {
+    fn from(canvas: &Canvas) -> Self {
+        image::RgbImage::from_fn(canvas.width as u32, canvas.height as u32, |x, y| {
+            let color = canvas.get_pixel((x as usize, y as usize));
+            image::Rgb([color.r(), color.g(), color.b()])
+        })
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<&image::RgbImage> for Canvas {
+    fn from(image: &image::RgbImage) -> Self {
+        let mut canvas = Canvas::new(image.width() as usize, image.height() as usize);
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let [r, g, b] = pixel.0.map(|c| c as Float / 255.0);
+            canvas.put_pixel(Color::new(r, g, b), (x as usize, y as usize));
+        }
+
+        canvas
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn levels(canvas: &Canvas) -> Vec<Float> {
+        canvas.pixels.iter().map(|c| c.r * 255.0).collect()
+    }
+
+    #[test]
+    fn test_dithering_keeps_the_average_of_a_flat_area() {
+        let mut canvas = Canvas::new(16, 16);
+        let value = 100.25 / 255.0;
+        for c in canvas.pixels.iter_mut() {
+            *c = Color::new(value, value, value);
+        }
+
+        for dither in [Dither::Ordered, Dither::FloydSteinberg] {
+            let levels = levels(&canvas.dither(dither));
+            let mean = levels.iter().sum::<Float>() / levels.len() as Float;
+
+            assert!(levels.iter().all(|&l| {
+                let rounded = l.round();
+                (l - rounded).abs() < 1e-3 && (rounded == 100.0 || rounded == 101.0)
+            }));
+            assert!((mean - 100.25).abs() < 0.02, "{:?}: {}", dither, mean);
+        }
+        let plain = levels(&canvas.dither(Dither::None));
+        assert!(plain.iter().all(|&l| (l - 100.0).abs() < 1e-3));
+    }
+
     #[test]
     fn test_creating_new_canvas() {
         let canvas = Canvas::new(10, 20);
@@ -91,4 +418,111 @@ mod tests {
 
         assert_eq!(*canvas.get_pixel((2, 3)), pixel);
     }
+
+    #[test]
+    fn test_signed_positions_are_clipped_to_the_canvas() {
+        let mut canvas = Canvas::new(4, 3);
+        let pixel = Color::new(1.0, 0.5, 0.0);
+
+        for at in [(-1, 0), (0, -1), (4, 0), (0, 3), (i64::MIN, i64::MAX)] {
+            canvas.put_pixel_clipped(pixel, at);
+            assert_eq!(canvas.get_pixel_clipped(at), None);
+        }
+        canvas.put_pixel_clipped(pixel, (3, 2));
+
+        assert_eq!(canvas.get_pixel_clipped((3, 2)), Some(&pixel));
+        assert_eq!(canvas.into_iter().filter(|c| *c == pixel).count(), 1);
+    }
+
+    #[test]
+    fn test_diffing_images() {
+        let a = Canvas::new(2, 1);
+        let mut b = Canvas::new(2, 1);
+        b.put_pixel(Color::new(0.3, 0.0, 0.0), (1, 0));
+
+        let diff = a.diff(&b).unwrap();
+
+        assert_eq!(diff.max, 0.3);
+        assert!((diff.mean - 0.05).abs() < 1e-9);
+        assert_eq!(
+            a.diff(&a),
+            Some(ImageDiff {
+                max: 0.0,
+                mean: 0.0
+            })
+        );
+        assert_eq!(a.diff(&Canvas::new(1, 2)), None);
+    }
+
+    #[test]
+    fn test_downscaling_averages_blocks() {
+        let mut canvas = Canvas::new(4, 2);
+        canvas.put_pixel(Color::new(1.0, 0.0, 0.0), (0, 0));
+        canvas.put_pixel(Color::new(0.0, 0.0, 1.0), (3, 1));
+
+        let small = canvas.downscale(2);
+        let same = canvas.downscale(100);
+
+        assert_eq!((small.get_width(), small.get_height()), (2, 1));
+        assert_eq!(*small.get_pixel((0, 0)), Color::new(0.25, 0.0, 0.0));
+        assert_eq!(*small.get_pixel((1, 0)), Color::new(0.0, 0.0, 0.25));
+        assert_eq!((same.get_width(), same.get_height()), (4, 2));
+        assert!(same.diff(&canvas).unwrap().max == 0.0);
+    }
+
+    #[test]
+    fn test_a_contact_sheet_places_and_labels_every_canvas() {
+        let canvases: Vec<_> = (0..3)
+            .map(|i| {
+                let mut canvas = Canvas::new(4, 3);
+                canvas.put_pixel(Color::new(i as Float, 0.5, 0.25), (1, 1));
+                canvas
+            })
+            .collect();
+
+        let sheet = Canvas::contact_sheet(&canvases, 2);
+
+        assert_eq!(sheet.get_width(), 2 * (4 + SHEET_GAP) + SHEET_GAP);
+        assert_eq!(
+            sheet.get_height(),
+            2 * (3 + LABEL_HEIGHT + SHEET_GAP) + SHEET_GAP
+        );
+        assert_eq!(*sheet.get_pixel((3, 3)), Color::new(0.0, 0.5, 0.25));
+        assert_eq!(*sheet.get_pixel((9, 3)), Color::new(1.0, 0.5, 0.25));
+        assert_eq!(*sheet.get_pixel((3, 15)), Color::new(2.0, 0.5, 0.25));
+        // The "1" under the second cell has a pixel in its middle column.
+        assert_eq!(*sheet.get_pixel((9, 6)), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(*sheet.get_pixel((10, 7)), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_converting_to_and_from_image_buffers() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.put_pixel(Color::new(1.5, 0.2, -1.0), (2, 1));
+
+        let image = image::RgbImage::from(&canvas);
+        let back = Canvas::from(&image);
+
+        assert_eq!(image.dimensions(), (3, 2));
+        assert_eq!(image.get_pixel(2, 1).0, [255, 51, 0]);
+        assert_eq!(*back.get_pixel((2, 1)), Color::new(1.0, 0.2, 0.0));
+        assert_eq!(*back.get_pixel((0, 0)), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_converting_to_an_srgb_image_buffer() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.put_pixel(Color::new(0.5, 0.5, 0.5), (0, 0));
+        canvas.put_pixel(Color::new(1.0, 0.0, 0.0), (1, 0));
+
+        let linear = canvas.to_rgb_image(false);
+        let srgb = canvas.to_rgb_image(true);
+
+        assert_eq!(linear, image::RgbImage::from(&canvas));
+        assert_eq!(linear.get_pixel(0, 0).0, [128, 128, 128]);
+        assert_eq!(srgb.get_pixel(0, 0).0, [188, 188, 188]);
+        assert_eq!(srgb.get_pixel(1, 0).0, [255, 0, 0]);
+    }
 }
@@ -0,0 +1,124 @@
+use std::fmt;
+#[cfg(not(feature = "rayon"))]
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::simd;
+
+/// Which optional accelerations this build uses, for bug reports and for
+/// telling benchmark numbers from different machines apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Lanes processed at once by vectorised math; 1 means scalar code.
+    pub simd_width: usize,
+    /// Worker threads `Camera::render_tiled` spreads tiles over, and photon
+    /// mapping spreads photons over.
+    pub threads: usize,
+    pub thread_pool: &'static str,
+    pub acceleration: &'static str,
+    pub float: &'static str,
+}
+
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        simd_width: simd::WIDTH,
+        threads: threads(),
+        thread_pool: if cfg!(feature = "rayon") {
+            "rayon"
+        } else {
+            "std::thread::scope"
+        },
+        acceleration: "bvh",
+        float: if cfg!(feature = "f32") { "f32" } else { "f64" },
+    }
+}
+
+#[cfg(feature = "rayon")]
+fn threads() -> usize {
+    rayon::current_num_threads()
+}
+
+#[cfg(not(feature = "rayon"))]
+fn threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// `f` applied to every index below `count` on the worker threads, with
+/// the results in index order. Threads take the next index whenever they
+/// finish one, so expensive items do not hold the others up.
+#[cfg(feature = "rayon")]
+pub(crate) fn parallel_map<T: Send, F: Fn(usize) -> T + Sync>(count: usize, f: F) -> Vec<T> {
+    (0..count).into_par_iter().map(&f).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+pub(crate) fn parallel_map<T: Send, F: Fn(usize) -> T + Sync>(count: usize, f: F) -> Vec<T> {
+    let threads = threads().min(count.max(1));
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, T)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                let (next, f) = (&next, &f);
+                scope.spawn(move || {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= count {
+                            return done;
+                        }
+                        done.push((i, f(i)));
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "simd width: {}", self.simd_width)?;
+        writeln!(f, "threads: {} ({})", self.threads, self.thread_pool)?;
+        writeln!(f, "acceleration: {}", self.acceleration)?;
+        write!(f, "float: {}", self.float)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_report_the_build() {
+        let caps = capabilities();
+
+        assert!(caps.threads >= 1);
+        assert!(caps.simd_width >= 1);
+        assert_eq!(
+            caps.float,
+            if cfg!(feature = "f32") { "f32" } else { "f64" }
+        );
+        assert_eq!(caps.to_string().lines().count(), 4);
+    }
+
+    #[test]
+    fn test_parallel_map_keeps_the_index_order() {
+        let squares = parallel_map(100, |i| i * i);
+
+        assert_eq!(squares, (0..100).map(|i| i * i).collect::<Vec<_>>());
+        assert!(parallel_map(0, |i| i).is_empty());
+    }
+}
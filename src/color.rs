@@ -1,18 +1,148 @@
-use std::ops::{Add, Mul, Sub};
+use std::{
+    iter::Sum,
+    ops::{Add, Mul, Sub},
+};
 
+use crate::float::Float;
 use crate::ppm::RGB;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Color {
-    pub r: f64,
-    pub g: f64,
-    pub b: f64,
+    pub r: Float,
+    pub g: Float,
+    pub b: Float,
 }
 
 impl Color {
-    pub fn new(r: f64, g: f64, b: f64) -> Self {
+    pub fn new(r: Float, g: Float, b: Float) -> Self {
         Color { r, g, b }
     }
+
+    /// Applies the sRGB transfer curve to each linear channel, which is how
+    /// most viewers expect 8-bit images to be stored. Negative channels
+    /// become zero.
+    pub fn to_srgb(&self) -> Color {
+        let encode = |c: Float| {
+            let c = c.max(0.0);
+            if c <= 0.0031308 {
+                12.92 * c
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        };
+
+        Color::new(encode(self.r), encode(self.g), encode(self.b))
+    }
+
+    /// Inverse of `to_srgb`, for reading 8-bit images back as linear light.
+    pub fn from_srgb(&self) -> Color {
+        let decode = |c: Float| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        Color::new(decode(self.r), decode(self.g), decode(self.b))
+    }
+
+    /// Relative luminance with Rec. 709 weights.
+    pub fn luminance(&self) -> Float {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// Bradford chromatic adaptation: maps a color seen under the `from`
+    /// white to the color that looks the same under `to`. Adapting from the
+    /// scene's illuminant to `WhitePoint::D65` neutralizes its tint.
+    pub fn adapt(&self, from: WhitePoint, to: WhitePoint) -> Color {
+        let (source, target) = (apply(&BRADFORD, from.xyz()), apply(&BRADFORD, to.xyz()));
+        let lms = apply(&BRADFORD, apply(&RGB_TO_XYZ, [self.r, self.g, self.b]));
+        let scaled = [
+            lms[0] * target[0] / source[0],
+            lms[1] * target[1] / source[1],
+            lms[2] * target[2] / source[2],
+        ];
+        let [r, g, b] = apply(&XYZ_TO_RGB, apply(&BRADFORD_INVERSE, scaled));
+
+        Color::new(r, g, b)
+    }
+}
+
+type Matrix = [[Float; 3]; 3];
+
+const RGB_TO_XYZ: Matrix = [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.072175],
+    [0.0193339, 0.119192, 0.9503041],
+];
+
+const XYZ_TO_RGB: Matrix = [
+    [3.2404542, -1.5371385, -0.4985314],
+    [-0.969266, 1.8760108, 0.041556],
+    [0.0556434, -0.2040259, 1.0572252],
+];
+
+const BRADFORD: Matrix = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+const BRADFORD_INVERSE: Matrix = [
+    [0.9869929, -0.1470543, 0.1599627],
+    [0.4323053, 0.5183603, 0.0492912],
+    [-0.0085287, 0.0400428, 0.9684867],
+];
+
+fn apply(m: &Matrix, v: [Float; 3]) -> [Float; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+/// Color of a light source as a CIE xy chromaticity. Linear RGB colors are
+/// relative to `D65`, the white point of sRGB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WhitePoint {
+    pub x: Float,
+    pub y: Float,
+}
+
+impl WhitePoint {
+    /// Incandescent light.
+    pub const A: WhitePoint = WhitePoint {
+        x: 0.44757,
+        y: 0.40745,
+    };
+    /// Horizon light, the usual white for print.
+    pub const D50: WhitePoint = WhitePoint {
+        x: 0.34567,
+        y: 0.35850,
+    };
+    /// Noon daylight, the white of sRGB.
+    pub const D65: WhitePoint = WhitePoint {
+        x: 0.31271,
+        y: 0.32902,
+    };
+
+    /// Chromaticity of a light's linear RGB color, which must not be black.
+    pub fn from_color(color: Color) -> Self {
+        let [x, y, z] = apply(&RGB_TO_XYZ, [color.r, color.g, color.b]);
+        let sum = x + y + z;
+
+        WhitePoint {
+            x: x / sum,
+            y: y / sum,
+        }
+    }
+
+    /// Tristimulus values scaled to a luminance of one.
+    fn xyz(&self) -> [Float; 3] {
+        [self.x / self.y, 1.0, (1.0 - self.x - self.y) / self.y]
+    }
 }
 
 impl Add for Color {
@@ -31,10 +161,10 @@ impl Sub for Color {
     }
 }
 
-impl Mul<f64> for Color {
+impl Mul<Float> for Color {
     type Output = Self;
 
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: Float) -> Self::Output {
         Self::new(self.r * rhs, self.g * rhs, self.b * rhs)
     }
 }
@@ -47,6 +177,83 @@ impl Mul for Color {
     }
 }
 
+impl Sum for Color {
+    /// Compensated, so the result barely depends on the order of the terms.
+    fn sum<I: Iterator<Item = Color>>(iter: I) -> Self {
+        let mut sum = ColorSum::new();
+        for color in iter {
+            sum.add(color);
+        }
+
+        sum.total()
+    }
+}
+
+/// Running sum that carries the rounding error of every addition along
+/// (Neumaier's variant of Kahan summation). Long accumulations then agree
+/// to within an ulp or so however their terms are grouped, which keeps
+/// serial and tiled renders in step.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CompensatedSum {
+    sum: Float,
+    compensation: Float,
+}
+
+impl CompensatedSum {
+    pub fn new() -> Self {
+        CompensatedSum::default()
+    }
+
+    pub fn add(&mut self, x: Float) {
+        let t = self.sum + x;
+        if self.sum.abs() >= x.abs() {
+            self.compensation += (self.sum - t) + x;
+        } else {
+            self.compensation += (x - t) + self.sum;
+        }
+        self.sum = t;
+    }
+
+    pub fn merge(&mut self, other: &CompensatedSum) {
+        self.add(other.sum);
+        self.add(other.compensation);
+    }
+
+    pub fn total(&self) -> Float {
+        self.sum + self.compensation
+    }
+}
+
+/// `CompensatedSum` for each channel of a color.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ColorSum {
+    r: CompensatedSum,
+    g: CompensatedSum,
+    b: CompensatedSum,
+}
+
+impl ColorSum {
+    pub fn new() -> Self {
+        ColorSum::default()
+    }
+
+    pub fn add(&mut self, color: Color) {
+        self.r.add(color.r);
+        self.g.add(color.g);
+        self.b.add(color.b);
+    }
+
+    pub fn merge(&mut self, other: &ColorSum) {
+        self.r.merge(&other.r);
+        self.g.merge(&other.g);
+        self.b.merge(&other.b);
+    }
+
+    pub fn total(&self) -> Color {
+        Color::new(self.r.total(), self.g.total(), self.b.total())
+    }
+}
+
 impl RGB for Color {
     fn r(&self) -> u8 {
         clamp_to_u8(self.r)
@@ -61,20 +268,65 @@ impl RGB for Color {
     }
 }
 
-fn clamp_to_u8(n: f64) -> u8 {
+fn clamp_to_u8(n: Float) -> u8 {
     (n * 255.0).clamp(0.0, 255.0).round() as u8
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::float::Float;
 
-    const EPSILON: f64 = 1e-6;
+    const EPSILON: Float = 1e-6;
 
-    fn equal(a: f64, b: f64) -> bool {
+    fn equal(a: Float, b: Float) -> bool {
         (a - b).abs() < EPSILON
     }
 
+    #[test]
+    fn test_srgb_encoding_round_trips() {
+        let linear = Color::new(0.0, 0.002, 0.5);
+
+        let encoded = linear.to_srgb();
+        let decoded = encoded.from_srgb();
+
+        assert!(equal(encoded.g, 0.02584));
+        assert!(equal(encoded.b, 0.735357));
+        assert!(equal(decoded.g, linear.g) && equal(decoded.b, linear.b));
+        let clipped = Color::new(-1.0, 1.0, 0.0).to_srgb();
+        assert!(equal(clipped.r, 0.0) && equal(clipped.g, 1.0));
+    }
+
+    #[test]
+    fn test_white_of_an_rgb_light_is_d65() {
+        let white = WhitePoint::from_color(Color::new(1.0, 1.0, 1.0));
+
+        assert!((white.x - WhitePoint::D65.x).abs() < 1e-4);
+        assert!((white.y - WhitePoint::D65.y).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_adapting_to_the_same_white_changes_nothing() {
+        let c = Color::new(0.2, 0.5, 0.9);
+
+        let adapted = c.adapt(WhitePoint::D65, WhitePoint::D65);
+
+        assert!(equal(adapted.r, c.r) && equal(adapted.g, c.g) && equal(adapted.b, c.b));
+    }
+
+    #[test]
+    fn test_adapting_from_a_warm_light_neutralizes_it() {
+        let warm = Color::new(1.0, 0.7, 0.4);
+        let illuminant = WhitePoint::from_color(warm);
+
+        let adapted = (warm * 0.5).adapt(illuminant, WhitePoint::D65);
+
+        assert!((adapted.r - adapted.g).abs() < 1e-3, "{:?}", adapted);
+        assert!((adapted.g - adapted.b).abs() < 1e-3, "{:?}", adapted);
+        let back = adapted.adapt(WhitePoint::D65, illuminant);
+        assert!((back.b - 0.2).abs() < 1e-4, "{:?}", back);
+    }
+
     #[test]
     fn test_adding_colors() {
         let c1 = Color::new(0.9, 0.6, 0.75);
@@ -82,7 +334,9 @@ mod tests {
 
         let c3 = c1 + c2;
 
-        assert_eq!(c3, Color::new(1.6, 0.7, 1.0));
+        assert!(equal(c3.r, 1.6));
+        assert!(equal(c3.g, 0.7));
+        assert!(equal(c3.b, 1.0));
     }
 
     #[test]
@@ -117,4 +371,36 @@ mod tests {
         assert!(equal(c3.g, 0.2));
         assert!(equal(c3.b, 0.04));
     }
+
+    #[test]
+    fn test_compensated_sums_keep_small_terms() {
+        let mut sum = CompensatedSum::new();
+        for x in [1.0, 1e30, 1.0, -1e30] {
+            sum.add(x);
+        }
+
+        assert_eq!(sum.total(), 2.0);
+    }
+
+    #[test]
+    fn test_summing_colors_does_not_depend_on_grouping() {
+        let colors: Vec<Color> = (1..=1000)
+            .map(|i| Color::new(0.1 * i as Float, 1.0 / i as Float, 1e8 + 0.3))
+            .collect();
+        let mut left = ColorSum::new();
+        let mut right = ColorSum::new();
+        for (i, &c) in colors.iter().enumerate() {
+            if i % 3 == 0 {
+                left.add(c);
+            } else {
+                right.add(c);
+            }
+        }
+        left.merge(&right);
+
+        let reversed: Color = colors.iter().rev().copied().sum();
+
+        assert_eq!(colors.into_iter().sum::<Color>(), reversed);
+        assert_eq!(left.total(), reversed);
+    }
 }
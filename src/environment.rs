@@ -0,0 +1,253 @@
+use std::{fmt, sync::Arc};
+
+use crate::{
+    color::Color,
+    float::{consts::PI, Float},
+    texture::{Texture, UvMapping},
+    tuple::Tuple4,
+};
+
+/// An equirectangular environment image together with a piecewise-constant
+/// distribution over its pixels, weighted by luminance and by the solid
+/// angle each row covers, for importance sampling directions towards the
+/// bright parts of the sky.
+#[derive(Clone, PartialEq)]
+pub struct EnvironmentMap {
+    texture: Arc<Texture>,
+    marginal: Vec<Float>,
+    conditional: Vec<Float>,
+    weights: Vec<Float>,
+    total: Float,
+}
+
+impl EnvironmentMap {
+    pub fn new(texture: Arc<Texture>) -> Self {
+        let (width, height) = (texture.get_width(), texture.get_height());
+        let mut weights = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let sin_theta = (PI * (y as Float + 0.5) / height as Float).sin();
+            for x in 0..width {
+                weights.push(texture.get_pixel((x, y)).luminance().max(0.0) * sin_theta);
+            }
+        }
+        // A black map carries no information, so fall back to sampling the
+        // sphere uniformly.
+        if weights.iter().all(|&w| w == 0.0) {
+            for y in 0..height {
+                let sin_theta = (PI * (y as Float + 0.5) / height as Float).sin();
+                weights[y * width..(y + 1) * width].fill(sin_theta);
+            }
+        }
+
+        let mut conditional = Vec::with_capacity(width * height);
+        let mut marginal = Vec::with_capacity(height);
+        let mut total = 0.0;
+        for row in weights.chunks(width) {
+            let mut sum = 0.0;
+            for &w in row {
+                sum += w;
+                conditional.push(sum);
+            }
+            total += sum;
+            marginal.push(total);
+        }
+
+        EnvironmentMap {
+            texture,
+            marginal,
+            conditional,
+            weights,
+            total,
+        }
+    }
+
+    pub fn get_texture(&self) -> &Arc<Texture> {
+        &self.texture
+    }
+
+    /// Radiance from `direction`, read from the same pixel whose weight
+    /// `sample` and `pdf` use, so sampled estimates stay free of fireflies.
+    pub fn color_at(&self, direction: Tuple4) -> Color {
+        *self.texture.get_pixel(self.cell(direction))
+    }
+
+    /// Picks a direction from two uniform numbers in `[0, 1)`, returning it
+    /// with its probability density per unit solid angle.
+    pub fn sample(&self, u1: Float, u2: Float) -> (Tuple4, Float) {
+        let width = self.texture.get_width();
+        let (y, dy) = pick(&self.marginal, u1 * self.total);
+        let row = &self.conditional[y * width..(y + 1) * width];
+        let (x, dx) = pick(row, u2 * row[width - 1]);
+
+        let u = (x as Float + dx) / width as Float;
+        let theta = PI * (y as Float + dy) / self.texture.get_height() as Float;
+        let phi = 2.0 * PI * (0.5 - u);
+        let direction = Tuple4::vector(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            theta.sin() * phi.cos(),
+        );
+
+        (direction, self.cell_pdf(x, y, theta.sin()))
+    }
+
+    /// Density `sample` would give to `direction`.
+    pub fn pdf(&self, direction: Tuple4) -> Float {
+        let (x, y) = self.cell(direction);
+        let sin_theta = direction.x.hypot(direction.z) / direction.magnitude();
+
+        self.cell_pdf(x, y, sin_theta)
+    }
+
+    /// Pixel a direction falls in, the one `sample` picks it from.
+    fn cell(&self, direction: Tuple4) -> (usize, usize) {
+        let (width, height) = (self.texture.get_width(), self.texture.get_height());
+        let (u, v) = UvMapping::Spherical.map(direction);
+        let x = ((u * width as Float) as usize).min(width - 1);
+        let y = (((1.0 - v) * height as Float) as usize).min(height - 1);
+
+        (x, y)
+    }
+
+    /// Density of a direction `theta` away from straight up that falls in
+    /// pixel `(x, y)`; the sampler is uniform in `theta` within a row, so
+    /// the density per solid angle varies with the direction itself.
+    fn cell_pdf(&self, x: usize, y: usize, sin_theta: Float) -> Float {
+        let (width, height) = (self.texture.get_width(), self.texture.get_height());
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        self.weights[y * width + x] / self.total * (width * height) as Float
+            / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+impl fmt::Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnvironmentMap")
+            .field("width", &self.texture.get_width())
+            .field("height", &self.texture.get_height())
+            .finish()
+    }
+}
+
+/// Finds the bucket of a running-sum table that `target` falls into, along
+/// with how far into the bucket it lands.
+fn pick(cdf: &[Float], target: Float) -> (usize, Float) {
+    let i = cdf.partition_point(|&c| c <= target).min(cdf.len() - 1);
+    let start = if i == 0 { 0.0 } else { cdf[i - 1] };
+    let width = cdf[i] - start;
+    let offset = if width > 0.0 {
+        ((target - start) / width).clamp(0.0, 1.0)
+    } else {
+        0.5
+    };
+
+    (i, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        canvas::Canvas,
+        float::{Float, EPSILON},
+        rng::{RandomSource, Rng},
+    };
+
+    use super::*;
+
+    fn sky_with_a_sun() -> EnvironmentMap {
+        let mut canvas = Canvas::new(16, 8);
+        for y in 0..8 {
+            for x in 0..16 {
+                canvas.put_pixel(Color::new(0.1, 0.1, 0.2), (x, y));
+            }
+        }
+        canvas.put_pixel(Color::new(100.0, 100.0, 90.0), (5, 2));
+
+        EnvironmentMap::new(Arc::new(Texture::from_canvas(&canvas)))
+    }
+
+    #[test]
+    fn test_environment_lookup_matches_the_texture() {
+        let env = sky_with_a_sun();
+
+        assert_eq!(
+            env.color_at(Tuple4::vector(0.0, -1.0, 0.0)),
+            Color::new(0.1, 0.1, 0.2)
+        );
+    }
+
+    #[test]
+    fn test_samples_favour_bright_pixels() {
+        let env = sky_with_a_sun();
+        let mut rng = Rng::new(1);
+
+        let bright = (0..1000)
+            .filter(|_| {
+                let (d, pdf) = env.sample(rng.next_float(), rng.next_float());
+                assert!((d.magnitude() - 1.0).abs() < EPSILON);
+                assert!((env.pdf(d) - pdf).abs() < EPSILON * pdf.max(1.0));
+                let (u, v) = UvMapping::Spherical.map(d);
+                (u * 16.0) as usize == 5 && ((1.0 - v) * 8.0) as usize == 2
+            })
+            .count();
+
+        assert!(bright > 900);
+    }
+
+    #[test]
+    fn test_sampled_radiance_over_pdf_follows_the_weights() {
+        let env = sky_with_a_sun();
+        let mut rng = Rng::new(4);
+        let expected = env.total * 2.0 * PI * PI / (16.0 * 8.0);
+
+        for _ in 0..2000 {
+            let (d, pdf) = env.sample(rng.next_float(), rng.next_float());
+            let (_, y) = env.cell(d);
+            let sin_row = (PI * (y as Float + 0.5) / 8.0).sin();
+            let sin_theta = d.x.hypot(d.z);
+
+            let ratio = env.color_at(d).luminance() / pdf * sin_row / sin_theta;
+
+            assert!(
+                (ratio - expected).abs() < 1e-3 * expected,
+                "{} {}",
+                ratio,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_pdf_integrates_to_one_over_the_sphere() {
+        let env = sky_with_a_sun();
+        let n = 400;
+        let mut sum = 0.0;
+        for i in 0..n {
+            let theta = PI * (i as Float + 0.5) / n as Float;
+            for j in 0..2 * n {
+                let phi = PI * (j as Float + 0.5) / n as Float;
+                let d = Tuple4::vector(
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                    theta.sin() * phi.cos(),
+                );
+                sum += env.pdf(d) * theta.sin() * (PI / n as Float).powi(2);
+            }
+        }
+
+        assert!((sum - 1.0).abs() < 0.02, "{}", sum);
+    }
+
+    #[test]
+    fn test_a_black_map_is_sampled_uniformly() {
+        let env = EnvironmentMap::new(Arc::new(Texture::from_canvas(&Canvas::new(8, 4))));
+
+        let (d, pdf) = env.sample(0.3, 0.7);
+
+        assert!((pdf - 1.0 / (4.0 * PI)).abs() < 0.01);
+        assert!((env.pdf(d) - pdf).abs() < EPSILON);
+    }
+}
@@ -0,0 +1,378 @@
+use crate::{
+    canvas::Canvas,
+    color::{Color, ColorSum, CompensatedSum},
+    float::Float,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Box(Float),
+    Tent(Float),
+    Gaussian { radius: Float, alpha: Float },
+    Mitchell { radius: Float, b: Float, c: Float },
+}
+
+impl Filter {
+    pub fn radius(&self) -> Float {
+        match *self {
+            Filter::Box(radius) | Filter::Tent(radius) => radius,
+            Filter::Gaussian { radius, .. } | Filter::Mitchell { radius, .. } => radius,
+        }
+    }
+
+    pub fn weight(&self, dx: Float, dy: Float) -> Float {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(&self, d: Float) -> Float {
+        match *self {
+            Filter::Box(radius) => {
+                if -radius <= d && d < radius {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Tent(radius) => (1.0 - d.abs() / radius).max(0.0),
+            Filter::Gaussian { radius, alpha } => {
+                ((-alpha * d * d).exp() - (-alpha * radius * radius).exp()).max(0.0)
+            }
+            Filter::Mitchell { radius, b, c } => {
+                let x = (2.0 * d / radius).abs();
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                } else if x < 2.0 {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Box(0.5)
+    }
+}
+
+/// Accumulates samples at continuous image positions, splatting each one into
+/// every pixel whose center lies within the filter footprint. Pixel centers
+/// sit at `(x + 0.5, y + 0.5)`. A film may cover only a window of the image,
+/// in which case samples are still given in image coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Film {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    filter: Filter,
+    sums: Vec<ColorSum>,
+    weights: Vec<CompensatedSum>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: Filter) -> Self {
+        Film::window(0, 0, width, height, filter)
+    }
+
+    pub fn window(x: usize, y: usize, width: usize, height: usize, filter: Filter) -> Self {
+        let size = width * height;
+
+        Film {
+            x,
+            y,
+            width,
+            height,
+            filter,
+            sums: vec![ColorSum::new(); size],
+            weights: vec![CompensatedSum::new(); size],
+        }
+    }
+
+    pub fn get_offset(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_filter(&self) -> Filter {
+        self.filter
+    }
+
+    pub fn add_sample(&mut self, x: Float, y: Float, color: Color) {
+        let x = x - self.x as Float;
+        let y = y - self.y as Float;
+        let radius = self.filter.radius();
+        let x0 = (x - radius - 0.5).ceil().max(0.0) as usize;
+        let y0 = (y - radius - 0.5).ceil().max(0.0) as usize;
+        let x1 = (x + radius - 0.5).floor().min(self.width as Float - 1.0);
+        let y1 = (y + radius - 0.5).floor().min(self.height as Float - 1.0);
+        if x1 < 0.0 || y1 < 0.0 {
+            return;
+        }
+
+        for py in y0..=y1 as usize {
+            for px in x0..=x1 as usize {
+                let weight = self
+                    .filter
+                    .weight(x - (px as Float + 0.5), y - (py as Float + 0.5));
+                if weight != 0.0 {
+                    let i = py * self.width + px;
+                    self.sums[i].add(color * weight);
+                    self.weights[i].add(weight);
+                }
+            }
+        }
+    }
+
+    /// Adds the accumulated samples of another film into the pixels the two
+    /// films share. Merging tiles in a fixed order keeps results reproducible.
+    pub fn merge(&mut self, other: &Film) {
+        for oy in 0..other.height {
+            for ox in 0..other.width {
+                let (gx, gy) = (other.x + ox, other.y + oy);
+                if gx < self.x || gy < self.y {
+                    continue;
+                }
+                let (x, y) = (gx - self.x, gy - self.y);
+                if x >= self.width || y >= self.height {
+                    continue;
+                }
+
+                let i = y * self.width + x;
+                let j = oy * other.width + ox;
+                self.sums[i].merge(&other.sums[j]);
+                self.weights[i].merge(&other.weights[j]);
+            }
+        }
+    }
+
+    /// Resolved colors of an image-space rectangle inside the film, row by
+    /// row. Pixels without samples are black.
+    pub fn resolve(&self, x: usize, y: usize, width: usize, height: usize) -> Vec<Color> {
+        let mut colors = Vec::with_capacity(width * height);
+
+        for py in y - self.y..y - self.y + height {
+            for px in x - self.x..x - self.x + width {
+                let color = self.pixel(py * self.width + px);
+                colors.push(color.unwrap_or(Color::new(0.0, 0.0, 0.0)));
+            }
+        }
+
+        colors
+    }
+
+    pub fn to_canvas(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if let Some(color) = self.pixel(y * self.width + x) {
+                    canvas.put_pixel(color, (x, y));
+                }
+            }
+        }
+
+        canvas
+    }
+
+    fn pixel(&self, i: usize) -> Option<Color> {
+        let weight = self.weights[i].total();
+        if weight > 0.0 {
+            Some(self.sums[i].total() * (1.0 / weight))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::Float;
+
+    const EPSILON: Float = 1e-6;
+
+    fn equal(a: Float, b: Float) -> bool {
+        (a - b).abs() < EPSILON
+    }
+
+    fn colors_equal(a: &Color, b: &Color) -> bool {
+        equal(a.r, b.r) && equal(a.g, b.g) && equal(a.b, b.b)
+    }
+
+    #[test]
+    fn test_box_filter_covers_a_half_open_square() {
+        let f = Filter::Box(0.5);
+
+        assert_eq!(f.weight(0.0, 0.0), 1.0);
+        assert_eq!(f.weight(-0.5, 0.2), 1.0);
+        assert_eq!(f.weight(0.5, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_tent_filter_falls_off_linearly() {
+        let f = Filter::Tent(2.0);
+
+        assert!(equal(f.weight(0.0, 0.0), 1.0));
+        assert!(equal(f.weight(1.0, 0.0), 0.5));
+        assert!(equal(f.weight(1.0, 1.0), 0.25));
+        assert_eq!(f.weight(2.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_gaussian_filter_reaches_zero_at_its_radius() {
+        let f = Filter::Gaussian {
+            radius: 1.5,
+            alpha: 2.0,
+        };
+
+        assert!(f.weight(0.0, 0.0) > f.weight(0.5, 0.0));
+        assert!(equal(f.weight(1.5, 0.0), 0.0));
+        assert_eq!(f.weight(2.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_mitchell_filter_has_negative_lobes() {
+        let f = Filter::Mitchell {
+            radius: 2.0,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        };
+
+        assert!(equal(f.weight(0.0, 0.0), (8.0 / 9.0) * (8.0 / 9.0)));
+        assert!(f.weight(1.5, 0.0) < 0.0);
+        assert_eq!(f.weight(2.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_box_filter_keeps_a_sample_in_its_own_pixel() {
+        let mut film = Film::new(3, 3, Filter::default());
+
+        film.add_sample(1.5, 1.5, Color::new(1.0, 0.5, 0.25));
+
+        let canvas = film.to_canvas();
+        assert_eq!(*canvas.get_pixel((1, 1)), Color::new(1.0, 0.5, 0.25));
+        assert_eq!(*canvas.get_pixel((0, 1)), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(*canvas.get_pixel((2, 2)), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_wide_filters_splat_into_neighbouring_pixels() {
+        let mut film = Film::new(3, 1, Filter::Tent(1.5));
+
+        film.add_sample(0.5, 0.5, Color::new(1.0, 1.0, 1.0));
+        film.add_sample(2.5, 0.5, Color::new(0.0, 0.0, 0.0));
+
+        let canvas = film.to_canvas();
+        assert!(colors_equal(
+            canvas.get_pixel((1, 0)),
+            &Color::new(0.5, 0.5, 0.5)
+        ));
+        assert!(canvas.get_pixel((0, 0)).r > 0.5);
+        assert!(canvas.get_pixel((2, 0)).r < 0.5);
+    }
+
+    #[test]
+    fn test_window_takes_samples_in_image_coordinates() {
+        let mut film = Film::window(2, 1, 2, 2, Filter::default());
+
+        film.add_sample(3.5, 1.5, Color::new(1.0, 1.0, 1.0));
+        film.add_sample(0.5, 0.5, Color::new(1.0, 0.0, 0.0));
+
+        let canvas = film.to_canvas();
+        assert_eq!(film.get_offset(), (2, 1));
+        assert_eq!(*canvas.get_pixel((1, 0)), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(*canvas.get_pixel((0, 0)), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_resolving_a_rectangle_of_a_window() {
+        let mut film = Film::window(2, 1, 3, 2, Filter::default());
+
+        film.add_sample(3.5, 1.5, Color::new(1.0, 0.5, 0.0));
+        film.add_sample(3.5, 1.5, Color::new(0.0, 0.5, 1.0));
+
+        assert_eq!(
+            film.resolve(3, 1, 2, 1),
+            [Color::new(0.5, 0.5, 0.5), Color::new(0.0, 0.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn test_merging_overlapping_windows_matches_a_single_film() {
+        let filter = Filter::Tent(1.5);
+        let samples = [
+            (0.3, 0.5, Color::new(1.0, 0.0, 0.0)),
+            (1.9, 0.2, Color::new(0.0, 1.0, 0.0)),
+            (2.1, 0.8, Color::new(0.0, 0.0, 1.0)),
+            (3.6, 0.4, Color::new(1.0, 1.0, 1.0)),
+        ];
+        let mut whole = Film::new(4, 1, filter);
+        let mut left = Film::window(0, 0, 3, 1, filter);
+        let mut right = Film::window(1, 0, 3, 1, filter);
+        for &(x, y, color) in &samples {
+            whole.add_sample(x, y, color);
+            if x < 2.0 {
+                left.add_sample(x, y, color);
+            } else {
+                right.add_sample(x, y, color);
+            }
+        }
+
+        let mut merged = Film::new(4, 1, filter);
+        merged.merge(&left);
+        merged.merge(&right);
+
+        for (a, b) in whole.to_canvas().into_iter().zip(merged.to_canvas()) {
+            assert!(colors_equal(&a, &b));
+        }
+    }
+
+    #[test]
+    fn test_filtering_a_constant_image_leaves_it_unchanged() {
+        let filters = [
+            Filter::Box(0.5),
+            Filter::Tent(1.0),
+            Filter::Gaussian {
+                radius: 1.5,
+                alpha: 2.0,
+            },
+            Filter::Mitchell {
+                radius: 2.0,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            },
+        ];
+        let color = Color::new(0.2, 0.4, 0.6);
+
+        for filter in filters {
+            let mut film = Film::new(4, 4, filter);
+            for y in 0..4 {
+                for x in 0..4 {
+                    film.add_sample(x as Float + 0.25, y as Float + 0.75, color);
+                    film.add_sample(x as Float + 0.75, y as Float + 0.25, color);
+                }
+            }
+
+            for pixel in film.to_canvas() {
+                assert!(colors_equal(&pixel, &color));
+            }
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! Scalar type used throughout the renderer: `f64` by default, or `f32`
+//! with the `f32` feature to halve the memory of large scenes.
+
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+
+#[cfg(feature = "f32")]
+pub type Float = f32;
+
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+
+/// Tolerance for comparing values near one after a chain of arithmetic;
+/// `f32` keeps about seven significant digits, so it gets a looser one.
+#[cfg(not(feature = "f32"))]
+pub const EPSILON: Float = 1e-9;
+
+#[cfg(feature = "f32")]
+pub const EPSILON: Float = 1e-4;
@@ -1,9 +1,31 @@
+pub mod bounds;
+pub mod bvh;
+pub mod camera;
 pub mod canvas;
+pub mod capabilities;
 pub mod color;
+pub mod environment;
+pub mod film;
+pub mod float;
 pub mod lights;
 pub mod materials;
 pub mod matrix;
+pub mod noise;
+pub mod normal_map;
+pub mod palette;
+pub mod patterns;
+pub mod photons;
 pub mod ppm;
+pub mod presets;
+pub mod queue;
 pub mod ray;
+pub mod rng;
+pub mod sampling;
+pub mod scene;
+pub mod settings;
+mod simd;
 pub mod sphere;
+pub mod texture;
+pub mod transform;
 pub mod tuple;
+pub mod world;
@@ -1,9 +1,10 @@
-use crate::{color::Color, tuple::Tuple4};
+use crate::{color::Color, float::Float, tuple::Tuple4};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct PointLight {
     position: Tuple4,
     intensity: Color,
+    radius: Float,
 }
 
 impl PointLight {
@@ -11,6 +12,7 @@ impl PointLight {
         PointLight {
             position,
             intensity,
+            radius: 0.0,
         }
     }
 
@@ -21,10 +23,140 @@ impl PointLight {
     pub fn intensity(&self) -> &Color {
         &self.intensity
     }
+
+    pub fn set_radius(&mut self, radius: Float) {
+        self.radius = radius.max(0.0);
+    }
+
+    pub fn radius(&self) -> Float {
+        self.radius
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpotLight {
+    position: Tuple4,
+    direction: Tuple4,
+    intensity: Color,
+    inner_angle: Float,
+    outer_angle: Float,
+    radius: Float,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Tuple4,
+        direction: Tuple4,
+        intensity: Color,
+        inner_angle: Float,
+        outer_angle: Float,
+    ) -> Self {
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            intensity,
+            inner_angle,
+            outer_angle: outer_angle.max(inner_angle),
+            radius: 0.0,
+        }
+    }
+
+    pub fn position(&self) -> &Tuple4 {
+        &self.position
+    }
+
+    pub fn direction(&self) -> &Tuple4 {
+        &self.direction
+    }
+
+    pub fn intensity(&self) -> &Color {
+        &self.intensity
+    }
+
+    pub fn inner_angle(&self) -> Float {
+        self.inner_angle
+    }
+
+    pub fn outer_angle(&self) -> Float {
+        self.outer_angle
+    }
+
+    pub fn set_radius(&mut self, radius: Float) {
+        self.radius = radius.max(0.0);
+    }
+
+    pub fn radius(&self) -> Float {
+        self.radius
+    }
+
+    pub fn falloff(&self, point: Tuple4) -> Float {
+        let cos_angle = (point - self.position).normalize().dot(&self.direction);
+        let cos_inner = self.inner_angle.cos();
+        let cos_outer = self.outer_angle.cos();
+
+        if cos_angle >= cos_inner {
+            1.0
+        } else if cos_angle <= cos_outer {
+            0.0
+        } else {
+            let t = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Light {
+    Point(PointLight),
+    Spot(SpotLight),
+}
+
+impl Light {
+    pub fn position(&self) -> &Tuple4 {
+        match self {
+            Light::Point(light) => light.position(),
+            Light::Spot(light) => light.position(),
+        }
+    }
+
+    pub fn intensity(&self) -> &Color {
+        match self {
+            Light::Point(light) => light.intensity(),
+            Light::Spot(light) => light.intensity(),
+        }
+    }
+
+    pub fn radius(&self) -> Float {
+        match self {
+            Light::Point(light) => light.radius(),
+            Light::Spot(light) => light.radius(),
+        }
+    }
+
+    pub fn falloff(&self, point: Tuple4) -> Float {
+        match self {
+            Light::Point(_) => 1.0,
+            Light::Spot(light) => light.falloff(point),
+        }
+    }
+}
+
+impl From<PointLight> for Light {
+    fn from(light: PointLight) -> Self {
+        Light::Point(light)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(light: SpotLight) -> Self {
+        Light::Spot(light)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::float::consts::PI;
+
     use super::*;
 
     #[test]
@@ -37,4 +169,63 @@ mod tests {
         assert_eq!(point_light.intensity, intensity);
         assert_eq!(point_light.position, position);
     }
+
+    fn spot_light() -> SpotLight {
+        SpotLight::new(
+            Tuple4::point(0.0, 10.0, 0.0),
+            Tuple4::vector(0.0, -1.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            PI / 8.0,
+            PI / 4.0,
+        )
+    }
+
+    #[test]
+    fn test_spot_light_fully_lights_points_inside_the_inner_cone() {
+        let light = spot_light();
+
+        assert_eq!(light.falloff(Tuple4::point(0.0, 0.0, 0.0)), 1.0);
+        assert_eq!(light.falloff(Tuple4::point(2.0, 0.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn test_spot_light_does_not_light_points_outside_the_outer_cone() {
+        let light = spot_light();
+
+        assert_eq!(light.falloff(Tuple4::point(20.0, 0.0, 0.0)), 0.0);
+        assert_eq!(light.falloff(Tuple4::point(0.0, 20.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn test_spot_light_falls_off_smoothly_between_the_cones() {
+        let light = spot_light();
+        let angle = (PI / 8.0 + PI / 4.0) / 2.0;
+
+        let middle = light.falloff(Tuple4::point(10.0 * angle.tan(), 0.0, 0.0));
+        let near_inner = light.falloff(Tuple4::point(10.0 * (PI / 8.0 + 0.01).tan(), 0.0, 0.0));
+
+        assert!(middle > 0.0 && middle < 1.0);
+        assert!(near_inner > middle && near_inner < 1.0);
+    }
+
+    #[test]
+    fn test_point_light_has_no_falloff() {
+        let light = Light::from(PointLight::new(
+            Tuple4::point(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+
+        assert_eq!(light.falloff(Tuple4::point(100.0, -3.0, 2.0)), 1.0);
+    }
+
+    #[test]
+    fn test_lights_default_to_a_zero_radius() {
+        let mut light = PointLight::new(Tuple4::point(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+
+        assert_eq!(Light::from(light).radius(), 0.0);
+        light.set_radius(0.5);
+        assert_eq!(Light::from(light).radius(), 0.5);
+        light.set_radius(-1.0);
+        assert_eq!(light.radius(), 0.0);
+    }
 }
@@ -1,54 +1,172 @@
-use std::io;
+use std::{env, fs, io, time::Instant};
 
 use ray_tracer_rs::{
-    canvas::Canvas, color::Color, lights::PointLight, materials::Material, ppm::PPMEncoder,
-    ray::Ray, sphere::Sphere, tuple::Tuple4,
+    camera::Antialiasing,
+    capabilities::capabilities,
+    ppm::{PPMEncoder, PPMFormat},
+    presets,
+    queue::RenderQueue,
 };
 
-const WALL_Z: f64 = 10.0;
-const WALL_SIZE: f64 = 7.0;
-const CANVAS_PIXELS: usize = 800;
-const PIXEL_SIZE: f64 = WALL_SIZE / CANVAS_PIXELS as f64;
-const HALF: f64 = WALL_SIZE / 2.0;
+const CANVAS_WIDTH: usize = 800;
+const CANVAS_HEIGHT: usize = 400;
 
-fn main() -> io::Result<()> {
-    let mut canvas = Canvas::new(CANVAS_PIXELS, CANVAS_PIXELS);
-    let ray_origin = Tuple4::point(0.0, 0.0, -5.0);
-    let mut sphere = Sphere::new();
-    let material = Material {
-        color: Color::new(1.0, 0.2, 1.0),
-        ..Default::default()
-    };
-    sphere.set_material(material);
-    let light = PointLight::new(
-        Tuple4::point(-10.0, -10.0, -10.0),
-        Color::new(1.0, 1.0, 1.0),
-    );
+const BENCH_WIDTH: usize = 320;
+const BENCH_HEIGHT: usize = 160;
+const BENCH_TILE: usize = 16;
 
-    for y in 0..CANVAS_PIXELS {
-        let world_y = -HALF + PIXEL_SIZE * y as f64;
-        for x in 0..CANVAS_PIXELS {
-            let world_x = -HALF + PIXEL_SIZE * x as f64;
-            let pos = Tuple4::point(world_x, world_y, WALL_Z);
-            let ray = Ray::new(ray_origin, (pos - ray_origin).normalize());
-            let xs = sphere.intersect(&ray);
-
-            if let Some(hit) = xs.hit() {
-                let point = ray.position(hit.t);
-                let normal = hit.sphere.normal_at(point);
-                let eye = -1.0 * ray.direction;
-                let color = hit
-                    .sphere
-                    .get_material()
-                    .lighting(light, point, eye, normal);
-                canvas.put_pixel(color, (x, y));
+fn render(format: PPMFormat, srgb: bool) -> io::Result<()> {
+    let (world, mut camera) = presets::three_spheres(CANVAS_WIDTH, CANVAS_HEIGHT);
+    camera.set_antialiasing(Antialiasing::Grid(2));
+    eprintln!("{}", world.summary());
+
+    let canvas = camera.try_render_with_progress(&world, |progress| {
+        let remaining = progress
+            .estimated_remaining()
+            .map_or(String::new(), |t| format!(", {:.0}s left", t.as_secs_f64()));
+        eprint!(
+            "\r{:>3.0}% ({}/{} tiles{})   ",
+            progress.fraction() * 100.0,
+            progress.tiles_done,
+            progress.tiles_total,
+            remaining
+        );
+    });
+    eprintln!();
+    let canvas = match canvas {
+        Ok(canvas) => canvas,
+        Err(partial) => {
+            eprintln!("{}", partial);
+            for failure in &partial.failures {
+                eprintln!(
+                    "  tile at ({}, {}): {}",
+                    failure.rect.x, failure.rect.y, failure.message
+                );
             }
+            std::process::exit(1);
         }
-    }
+    };
+    let canvas = if srgb { canvas.to_srgb() } else { canvas };
 
     let stdout = io::stdout();
     let mut handle = stdout.lock();
-    let mut encoder = PPMEncoder::new(&mut handle);
+    let mut encoder = PPMEncoder::with_format(&mut handle, format);
 
     encoder.write(&canvas)
 }
+
+fn batch(path: &str) -> bool {
+    let queue = match fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| RenderQueue::from_yaml(&text).map_err(|e| e.to_string()))
+    {
+        Ok(mut queue) => {
+            queue.set_parallel(true);
+            queue
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return false;
+        }
+    };
+
+    let results = queue.run(|progress, job| {
+        eprintln!(
+            "[{}/{}] {} -> {}",
+            progress.finished,
+            progress.total,
+            job.preset,
+            job.output.display()
+        );
+    });
+
+    let mut ok = true;
+    for (job, result) in queue.get_jobs().iter().zip(results) {
+        if let Err(e) = result {
+            eprintln!("{}: {}", job.output.display(), e);
+            ok = false;
+        }
+    }
+
+    ok
+}
+
+fn peak_memory_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn bench() {
+    println!("{}\n", capabilities());
+    println!(
+        "{:<16} {:>10} {:>10} {:>10} {:>12} {:>14}",
+        "scene", "build ms", "render ms", "tiled ms", "camera rays", "rays/sec"
+    );
+
+    for (name, preset) in presets::PRESETS {
+        let start = Instant::now();
+        let (world, mut camera) = preset(BENCH_WIDTH, BENCH_HEIGHT);
+        camera.set_tile_size(BENCH_TILE);
+        let build = start.elapsed();
+
+        let rays = camera.ray_table().len();
+        let start = Instant::now();
+        camera.render(&world);
+        let render = start.elapsed();
+
+        let start = Instant::now();
+        camera.render_parallel(&world);
+        let tiled = start.elapsed();
+
+        println!(
+            "{:<16} {:>10.2} {:>10.2} {:>10.2} {:>12} {:>14.0}",
+            name,
+            build.as_secs_f64() * 1e3,
+            render.as_secs_f64() * 1e3,
+            tiled.as_secs_f64() * 1e3,
+            rays,
+            rays as f64 / render.as_secs_f64()
+        );
+    }
+
+    match peak_memory_kb() {
+        Some(kb) => println!("peak memory: {} KiB", kb),
+        None => println!("peak memory: unavailable"),
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args[..] {
+        [] => render(PPMFormat::Plain, false),
+        ["render", "--batch", path] => {
+            if !batch(path) {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        ["render", ref flags @ ..] if flags.iter().all(|f| ["--raw", "--srgb"].contains(f)) => {
+            let format = if flags.contains(&"--raw") {
+                PPMFormat::Raw
+            } else {
+                PPMFormat::Plain
+            };
+            render(format, flags.contains(&"--srgb"))
+        }
+        ["bench"] => {
+            bench();
+            Ok(())
+        }
+        _ => {
+            eprintln!(
+                "unknown command: {}\nusage: ray-tracer-rs [bench | render [--raw] [--srgb] | render --batch jobs.yaml]",
+                args.join(" ")
+            );
+            std::process::exit(2);
+        }
+    }
+}
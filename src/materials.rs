@@ -1,37 +1,172 @@
-use crate::{color::Color, lights::PointLight, tuple::Tuple4};
+use std::{fmt, sync::Arc};
+
+use crate::{
+    color::Color,
+    float::{consts::PI, Float},
+    lights::Light,
+    normal_map::NormalMap,
+    patterns::Pattern,
+    sphere::Sphere,
+    tuple::Tuple4,
+};
+
+const SPECULAR_LUT_SIZE: usize = 4096;
+
+/// Table of `x.powf(shininess)` over `[0, 1]`, looked up with linear
+/// interpolation in place of the `powf` call in the specular term.
+#[derive(Clone, PartialEq)]
+pub struct SpecularLut {
+    shininess: Float,
+    values: Vec<Float>,
+}
+
+impl SpecularLut {
+    pub fn new(shininess: Float) -> Self {
+        let values = (0..=SPECULAR_LUT_SIZE)
+            .map(|i| (i as Float / SPECULAR_LUT_SIZE as Float).powf(shininess))
+            .collect();
+
+        SpecularLut { shininess, values }
+    }
+
+    pub fn shininess(&self) -> Float {
+        self.shininess
+    }
+
+    pub fn lookup(&self, x: Float) -> Float {
+        let f = x.clamp(0.0, 1.0) * SPECULAR_LUT_SIZE as Float;
+        let i = (f as usize).min(SPECULAR_LUT_SIZE - 1);
+        let t = f - i as Float;
+
+        self.values[i] + (self.values[i + 1] - self.values[i]) * t
+    }
+}
+
+impl fmt::Debug for SpecularLut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpecularLut")
+            .field("shininess", &self.shininess)
+            .field("entries", &self.values.len())
+            .finish()
+    }
+}
+
+/// Reflectance model used by `Material::lighting`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Brdf {
+    /// Classic Phong using `diffuse`, `specular` and `shininess`.
+    Phong,
+    /// Cook-Torrance with a GGX distribution. `metallic` blends from a
+    /// dielectric with a 4% base reflectance to one tinted by the surface
+    /// color; `roughness` runs from mirror-like (0) to fully rough (1).
+    Pbr { metallic: Float, roughness: Float },
+}
+
+/// Turns a surface into a shadow catcher: it shows the background behind it,
+/// darkened only by the shadows and ambient occlusion it receives, so renders
+/// can be composited over a photograph used as the background.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowCatcher {
+    /// Occluders further away than this do not darken the surface.
+    pub ao_distance: Float,
+}
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Material {
     pub color: Color,
-    pub ambient: f64,
-    pub diffuse: f64,
-    pub specular: f64,
-    pub shininess: f64,
+    pub ambient: Float,
+    pub diffuse: Float,
+    pub specular: Float,
+    pub shininess: Float,
+    pub reflective: Float,
+    /// Blurs reflections by jittering the mirror direction; 0 is a
+    /// perfect mirror.
+    pub roughness: Float,
+    pub pattern: Option<Box<dyn Pattern>>,
+    pub specular_lut: Option<Arc<SpecularLut>>,
+    pub normal_map: Option<NormalMap>,
+    pub brdf: Brdf,
+    pub shadow_catcher: Option<ShadowCatcher>,
 }
 
 impl Material {
-    pub fn new(color: Color, ambient: f64, diffuse: f64, specular: f64, shininess: f64) -> Self {
+    pub fn new(
+        color: Color,
+        ambient: Float,
+        diffuse: Float,
+        specular: Float,
+        shininess: Float,
+    ) -> Self {
         Material {
             color,
             ambient,
             diffuse,
             specular,
             shininess,
+            reflective: 0.0,
+            roughness: 0.0,
+            pattern: None,
+            specular_lut: None,
+            normal_map: None,
+            brdf: Brdf::Phong,
+            shadow_catcher: None,
         }
     }
 
-    pub fn lighting(
+    /// Precomputes the specular falloff for the current shininess. The table
+    /// is ignored if `shininess` is changed afterwards.
+    pub fn bake(&mut self) {
+        self.specular_lut = Some(Arc::new(SpecularLut::new(self.shininess)));
+    }
+
+    /// Surface color at a world-space point, from the pattern if there is one.
+    pub fn color_at(&self, object: &Sphere, point: Tuple4) -> Color {
+        match &self.pattern {
+            Some(pattern) => pattern.color_at(object.get_inverse_transform() * point),
+            None => self.color,
+        }
+    }
+
+    pub fn lighting<L: Into<Light>>(
         &self,
-        light: PointLight,
+        object: &Sphere,
+        light: L,
         point: Tuple4,
         eyev: Tuple4,
         normalv: Tuple4,
+        in_shadow: bool,
     ) -> Color {
-        let effective_color = self.color * *light.intensity();
+        let light = light.into();
+        let color = self.color_at(object, point);
+        let effective_color = color * *light.intensity();
         let lightv = (*light.position() - point).normalize();
         let ambient = effective_color * self.ambient;
+        let falloff = light.falloff(point);
+        if in_shadow || falloff <= 0.0 {
+            return ambient;
+        }
 
         let light_dot_normal = lightv.dot(&normalv);
+        if let Brdf::Pbr {
+            metallic,
+            roughness,
+        } = self.brdf
+        {
+            if light_dot_normal <= 0.0 {
+                return ambient;
+            }
+            let reflected = cook_torrance(
+                color,
+                metallic,
+                roughness,
+                lightv,
+                eyev,
+                normalv,
+                light_dot_normal,
+            );
+            return ambient + reflected * *light.intensity() * (light_dot_normal * falloff);
+        }
+
         let diffuse;
         let specular;
         if light_dot_normal < 0.0 {
@@ -46,15 +181,54 @@ impl Material {
             if reflect_dot_eye <= 0.0 {
                 specular = Color::new(0.0, 0.0, 0.0);
             } else {
-                let factor = reflect_dot_eye.powf(self.shininess);
+                let factor = match &self.specular_lut {
+                    Some(lut) if lut.shininess() == self.shininess => lut.lookup(reflect_dot_eye),
+                    _ => reflect_dot_eye.powf(self.shininess),
+                };
                 specular = *light.intensity() * self.specular * factor;
             }
         }
 
-        ambient + diffuse + specular
+        ambient + diffuse * falloff + specular * falloff
     }
 }
 
+/// Reflected fraction of light arriving along `lightv`, with the `1/PI` of
+/// the Lambert term folded into the light intensity so a white dielectric
+/// facing the light reflects about as much as a Phong surface with
+/// `diffuse` of 1.
+fn cook_torrance(
+    albedo: Color,
+    metallic: Float,
+    roughness: Float,
+    lightv: Tuple4,
+    eyev: Tuple4,
+    normalv: Tuple4,
+    n_dot_l: Float,
+) -> Color {
+    let metallic = metallic.clamp(0.0, 1.0);
+    let roughness = roughness.clamp(0.02, 1.0);
+    let n_dot_v = normalv.dot(&eyev).max(1e-4);
+    let halfv = (lightv + eyev).normalize();
+    let n_dot_h = normalv.dot(&halfv).max(0.0);
+    let v_dot_h = eyev.dot(&halfv).max(0.0);
+
+    let alpha2 = roughness.powi(4);
+    let d = alpha2 / (PI * (n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0).powi(2));
+    let k = (roughness + 1.0).powi(2) / 8.0;
+    let g = n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+
+    let dielectric = Color::new(0.04, 0.04, 0.04);
+    let f0 = dielectric * (1.0 - metallic) + albedo * metallic;
+    let fresnel = (1.0 - v_dot_h).powi(5);
+    let f = f0 + (Color::new(1.0, 1.0, 1.0) - f0) * fresnel;
+
+    let specular = f * (d * g / (4.0 * n_dot_l * n_dot_v) * PI);
+    let kd = (Color::new(1.0, 1.0, 1.0) - f) * (1.0 - metallic);
+
+    kd * albedo + specular
+}
+
 impl Default for Material {
     fn default() -> Self {
         Material {
@@ -63,19 +237,34 @@ impl Default for Material {
             diffuse: 0.9,
             specular: 0.9,
             shininess: 200.0,
+            reflective: 0.0,
+            roughness: 0.0,
+            pattern: None,
+            specular_lut: None,
+            normal_map: None,
+            brdf: Brdf::Phong,
+            shadow_catcher: None,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{color::Color, lights::PointLight, tuple::Tuple4};
+    use crate::{
+        color::Color,
+        float::{consts::PI, Float},
+        lights::{PointLight, SpotLight},
+        matrix::Matrix4x4,
+        patterns::StripePattern,
+        sphere::Sphere,
+        tuple::Tuple4,
+    };
 
-    use super::Material;
+    use super::{Brdf, Material, SpecularLut};
 
-    const EPSILON: f64 = 1e-6;
+    const EPSILON: Float = 1e-6;
 
-    fn equal(a: f64, b: f64) -> bool {
+    fn equal(a: Float, b: Float) -> bool {
         (a - b).abs() < EPSILON
     }
 
@@ -88,6 +277,7 @@ mod tests {
         assert_eq!(m.diffuse, 0.9);
         assert_eq!(m.specular, 0.9);
         assert_eq!(m.shininess, 200.0);
+        assert_eq!(m.reflective, 0.0);
     }
 
     #[test]
@@ -98,7 +288,7 @@ mod tests {
         let normalv = Tuple4::vector(0.0, 0.0, -1.0);
         let light = PointLight::new(Tuple4::point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
 
-        let result = m.lighting(light, position, eyev, normalv);
+        let result = m.lighting(&Sphere::new(), light, position, eyev, normalv, false);
 
         assert_eq!(result, Color::new(1.9, 1.9, 1.9));
     }
@@ -107,11 +297,11 @@ mod tests {
     fn test_lighting_with_eye_between_the_light_and_the_surface_eye_offset_45_deg() {
         let m = Material::default();
         let position = Tuple4::point(0.0, 0.0, 0.0);
-        let eyev = Tuple4::vector(0.0, 2.0_f64.sqrt(), -(2.0_f64.sqrt()) / 2.0);
+        let eyev = Tuple4::vector(0.0, Float::sqrt(2.0), -(Float::sqrt(2.0)) / 2.0);
         let normalv = Tuple4::vector(0.0, 0.0, -1.0);
         let light = PointLight::new(Tuple4::point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
 
-        let result = m.lighting(light, position, eyev, normalv);
+        let result = m.lighting(&Sphere::new(), light, position, eyev, normalv, false);
 
         assert_eq!(result, Color::new(1.0, 1.0, 1.0));
     }
@@ -124,7 +314,7 @@ mod tests {
         let normalv = Tuple4::vector(0.0, 0.0, -1.0);
         let light = PointLight::new(Tuple4::point(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
 
-        let result = m.lighting(light, position, eyev, normalv);
+        let result = m.lighting(&Sphere::new(), light, position, eyev, normalv, false);
 
         assert!(equal(result.r, 0.736396));
         assert!(equal(result.g, 0.736396));
@@ -135,11 +325,11 @@ mod tests {
     fn test_lighting_with_eye_in_the_path_of_the_reflection_vector() {
         let m = Material::default();
         let position = Tuple4::point(0.0, 0.0, 0.0);
-        let eyev = Tuple4::vector(0.0, -(2.0_f64.sqrt() / 2.0), -(2.0_f64.sqrt()) / 2.0);
+        let eyev = Tuple4::vector(0.0, -(Float::sqrt(2.0) / 2.0), -(Float::sqrt(2.0)) / 2.0);
         let normalv = Tuple4::vector(0.0, 0.0, -1.0);
         let light = PointLight::new(Tuple4::point(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
 
-        let result = m.lighting(light, position, eyev, normalv);
+        let result = m.lighting(&Sphere::new(), light, position, eyev, normalv, false);
 
         assert!(equal(result.r, 1.636396));
         assert!(equal(result.g, 1.636396));
@@ -154,8 +344,230 @@ mod tests {
         let normalv = Tuple4::vector(0.0, 0.0, -1.0);
         let light = PointLight::new(Tuple4::point(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0));
 
-        let result = m.lighting(light, position, eyev, normalv);
+        let result = m.lighting(&Sphere::new(), light, position, eyev, normalv, false);
 
         assert_eq!(result, Color::new(0.1, 0.1, 0.1));
     }
+
+    #[test]
+    fn test_lighting_with_the_surface_in_shadow() {
+        let m = Material::default();
+        let position = Tuple4::point(0.0, 0.0, 0.0);
+        let eyev = Tuple4::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4::vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(Tuple4::point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+
+        let result = m.lighting(&Sphere::new(), light, position, eyev, normalv, true);
+
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn test_lighting_with_a_spot_light_pointing_at_the_surface() {
+        let m = Material::default();
+        let position = Tuple4::point(0.0, 0.0, 0.0);
+        let eyev = Tuple4::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4::vector(0.0, 0.0, -1.0);
+        let light = SpotLight::new(
+            Tuple4::point(0.0, 0.0, -10.0),
+            Tuple4::vector(0.0, 0.0, 1.0),
+            Color::new(1.0, 1.0, 1.0),
+            PI / 8.0,
+            PI / 4.0,
+        );
+
+        let result = m.lighting(&Sphere::new(), light, position, eyev, normalv, false);
+
+        assert_eq!(result, Color::new(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn test_lighting_with_a_spot_light_pointing_away_from_the_surface() {
+        let m = Material::default();
+        let position = Tuple4::point(0.0, 0.0, 0.0);
+        let eyev = Tuple4::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4::vector(0.0, 0.0, -1.0);
+        let light = SpotLight::new(
+            Tuple4::point(0.0, 0.0, -10.0),
+            Tuple4::vector(0.0, 1.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            PI / 8.0,
+            PI / 4.0,
+        );
+
+        let result = m.lighting(&Sphere::new(), light, position, eyev, normalv, false);
+
+        assert_eq!(result, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn test_lighting_with_a_pattern_applied() {
+        let m = Material {
+            ambient: 1.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            pattern: Some(Box::new(StripePattern::new(
+                Color::new(1.0, 1.0, 1.0),
+                Color::new(0.0, 0.0, 0.0),
+            ))),
+            ..Default::default()
+        };
+        let eyev = Tuple4::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4::vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(Tuple4::point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let object = Sphere::new();
+
+        let c1 = m.lighting(
+            &object,
+            light,
+            Tuple4::point(0.9, 0.0, 0.0),
+            eyev,
+            normalv,
+            false,
+        );
+        let c2 = m.lighting(
+            &object,
+            light,
+            Tuple4::point(1.1, 0.0, 0.0),
+            eyev,
+            normalv,
+            false,
+        );
+
+        assert_eq!(c1, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(c2, Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_pattern_is_sampled_in_object_space() {
+        let m = Material {
+            ambient: 1.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            pattern: Some(Box::new(StripePattern::new(
+                Color::new(1.0, 1.0, 1.0),
+                Color::new(0.0, 0.0, 0.0),
+            ))),
+            ..Default::default()
+        };
+        let eyev = Tuple4::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4::vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(Tuple4::point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let mut object = Sphere::new();
+        object.set_transform(Matrix4x4::scaling(2.0, 2.0, 2.0));
+
+        let c = m.lighting(
+            &object,
+            light,
+            Tuple4::point(1.5, 0.0, 0.0),
+            eyev,
+            normalv,
+            false,
+        );
+
+        assert_eq!(c, Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_specular_lut_matches_powf() {
+        let lut = SpecularLut::new(200.0);
+
+        for i in 0..=1000 {
+            let x = i as Float / 1000.0;
+            assert!((lut.lookup(x) - x.powf(200.0)).abs() < 1e-3);
+        }
+        assert_eq!(lut.lookup(1.0), 1.0);
+        assert_eq!(lut.lookup(0.0), 0.0);
+    }
+
+    #[test]
+    fn test_lighting_with_a_baked_material() {
+        let mut m = Material::default();
+        m.bake();
+        let position = Tuple4::point(0.0, 0.0, 0.0);
+        let eyev = Tuple4::vector(0.0, -(Float::sqrt(2.0) / 2.0), -(Float::sqrt(2.0)) / 2.0);
+        let normalv = Tuple4::vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(Tuple4::point(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+
+        let result = m.lighting(&Sphere::new(), light, position, eyev, normalv, false);
+
+        assert!((result.r - 1.636396).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_a_stale_lut_is_ignored() {
+        let mut m = Material::default();
+        m.bake();
+        m.shininess = 10.0;
+        let plain = Material {
+            shininess: 10.0,
+            ..Default::default()
+        };
+        let position = Tuple4::point(0.0, 0.0, 0.0);
+        let eyev = Tuple4::vector(0.0, -0.6, -0.8);
+        let normalv = Tuple4::vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(Tuple4::point(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+
+        let baked = m.lighting(&Sphere::new(), light, position, eyev, normalv, false);
+        let expected = plain.lighting(&Sphere::new(), light, position, eyev, normalv, false);
+
+        assert_eq!(baked, expected);
+    }
+
+    fn pbr(metallic: Float, roughness: Float) -> Material {
+        Material {
+            color: Color::new(0.8, 0.2, 0.2),
+            brdf: Brdf::Pbr {
+                metallic,
+                roughness,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pbr_lighting_facing_the_light() {
+        let m = pbr(0.0, 1.0);
+        let position = Tuple4::point(0.0, 0.0, 0.0);
+        let eyev = Tuple4::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4::vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(Tuple4::point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+
+        let result = m.lighting(&Sphere::new(), light, position, eyev, normalv, false);
+
+        assert!(equal(result.r, 0.858));
+        assert!(equal(result.g, 0.222));
+        assert!(equal(result.b, 0.222));
+    }
+
+    #[test]
+    fn test_pbr_lighting_with_the_light_behind_the_surface() {
+        let m = pbr(0.5, 0.5);
+        let position = Tuple4::point(0.0, 0.0, 0.0);
+        let eyev = Tuple4::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple4::vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(Tuple4::point(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0));
+
+        let result = m.lighting(&Sphere::new(), light, position, eyev, normalv, false);
+
+        assert_eq!(result, Color::new(0.8, 0.2, 0.2) * 0.1);
+    }
+
+    #[test]
+    fn test_smoother_pbr_surfaces_have_sharper_highlights() {
+        let position = Tuple4::point(0.0, 0.0, 0.0);
+        let normalv = Tuple4::vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(Tuple4::point(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let mirror = Tuple4::vector(0.0, -(Float::sqrt(2.0) / 2.0), -(Float::sqrt(2.0)) / 2.0);
+        let off = Tuple4::vector(0.0, 0.0, -1.0);
+
+        let highlight = |m: &Material, eyev| {
+            m.lighting(&Sphere::new(), light, position, eyev, normalv, false)
+                .g
+        };
+        let (smooth, rough) = (pbr(1.0, 0.1), pbr(1.0, 0.8));
+
+        assert!(highlight(&smooth, mirror) > highlight(&rough, mirror));
+        assert!(highlight(&smooth, off) < highlight(&rough, off));
+    }
 }
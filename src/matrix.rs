@@ -1,6 +1,10 @@
-use std::ops::Mul;
+use std::{
+    error::Error,
+    fmt,
+    ops::{Mul, MulAssign},
+};
 
-use crate::tuple::Tuple4;
+use crate::{float::Float, simd, tuple::Tuple4};
 
 fn to_index(size: usize, y: usize, x: usize) -> usize {
     y * size + x
@@ -13,7 +17,7 @@ fn to_yx(size: usize, i: usize) -> (usize, usize) {
     (y, x)
 }
 
-type Elem = f64;
+type Elem = Float;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 struct Matrix2x2 {
@@ -104,6 +108,35 @@ impl Matrix3x3 {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixError {
+    WrongLength { found: usize },
+    WrongRowCount { found: usize },
+    WrongRowLength { row: usize, found: usize },
+}
+
+impl fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatrixError::WrongLength { found } => {
+                write!(f, "expected 16 matrix elements, found {}", found)
+            }
+            MatrixError::WrongRowCount { found } => {
+                write!(f, "expected 4 matrix rows, found {}", found)
+            }
+            MatrixError::WrongRowLength { row, found } => {
+                write!(
+                    f,
+                    "expected 4 elements in matrix row {}, found {}",
+                    row, found
+                )
+            }
+        }
+    }
+}
+
+impl Error for MatrixError {}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Matrix4x4 {
     data: [Elem; Matrix4x4::size()],
@@ -111,7 +144,7 @@ pub struct Matrix4x4 {
 
 impl Matrix4x4 {
     const N: usize = 4;
-    const PRECISION: f64 = 1e-12;
+    const PRECISION: Float = 1e-12;
 
     const fn size() -> usize {
         Matrix4x4::N * Matrix4x4::N
@@ -125,6 +158,33 @@ impl Matrix4x4 {
         Matrix4x4::new([0.0; Matrix4x4::size()])
     }
 
+    pub fn from_rows<I, R>(rows: I) -> Result<Self, MatrixError>
+    where
+        I: IntoIterator<Item = R>,
+        R: AsRef<[Elem]>,
+    {
+        let mut matrix = Matrix4x4::zero();
+        let mut count = 0;
+        for (y, row) in rows.into_iter().enumerate() {
+            let row = row.as_ref();
+            if row.len() != Matrix4x4::N {
+                return Err(MatrixError::WrongRowLength {
+                    row: y,
+                    found: row.len(),
+                });
+            }
+            if y < Matrix4x4::N {
+                matrix.data[y * Matrix4x4::N..(y + 1) * Matrix4x4::N].copy_from_slice(row);
+            }
+            count += 1;
+        }
+        if count != Matrix4x4::N {
+            return Err(MatrixError::WrongRowCount { found: count });
+        }
+
+        Ok(matrix)
+    }
+
     pub fn identity() -> Self {
         let mut matrix = Matrix4x4::zero();
         for i in 0..Matrix4x4::N {
@@ -194,6 +254,34 @@ impl Matrix4x4 {
         m
     }
 
+    pub fn view_transform(from: Tuple4, to: Tuple4, up: Tuple4) -> Self {
+        let forward = (to - from).normalize();
+        let left = forward.cross(up.normalize());
+        let true_up = left.cross(forward);
+        let orientation = Matrix4x4::new([
+            left.x, left.y, left.z, 0.0, true_up.x, true_up.y, true_up.z, 0.0, -forward.x,
+            -forward.y, -forward.z, 0.0, 0.0, 0.0, 0.0, 1.0,
+        ]);
+
+        orientation * Matrix4x4::translation(-from.x, -from.y, -from.z)
+    }
+
+    /// Applies `self` first and `next` after it, i.e. `next * self`. Reads
+    /// in the order the transforms happen:
+    /// `Matrix4x4::scaling(2.0, 2.0, 2.0).then(&Matrix4x4::translation(1.0, 0.0, 0.0))`
+    /// scales about the origin, then moves.
+    pub fn then(&self, next: &Matrix4x4) -> Self {
+        next * self
+    }
+
+    /// Chains transforms in the order they are applied, so the first one
+    /// listed acts on points first. An empty list gives the identity.
+    pub fn compose(transforms: &[Matrix4x4]) -> Self {
+        transforms
+            .iter()
+            .fold(Matrix4x4::identity(), |m, next| m.then(next))
+    }
+
     pub fn get(&self, y: usize, x: usize) -> Elem {
         let i = self.get_index(y, x);
         self.data[i]
@@ -213,29 +301,115 @@ impl Matrix4x4 {
     }
 
     pub fn det(&self) -> Elem {
-        let mut det = 0.0;
-        for (i, &n) in self.data[..Self::N].iter().enumerate() {
-            det += n * self.cofactor(0, i);
-        }
+        let (s, c) = self.sub_determinants();
 
-        det
+        Self::det_from(&s, &c)
     }
 
     pub fn is_invertible(&self) -> bool {
         self.is_invertible_with_det().0
     }
 
+    /// True when the bottom row is `0 0 0 1`, as for any mix of
+    /// translation, rotation, scaling and shearing.
+    pub fn is_affine(&self) -> bool {
+        self.data[12..] == [0.0, 0.0, 0.0, 1.0]
+    }
+
     pub fn inverse(self) -> Option<Self> {
-        let (is_invertible, det) = self.is_invertible_with_det();
-        if !is_invertible {
+        if self.is_affine() {
+            self.affine_inverse()
+        } else {
+            self.general_inverse()
+        }
+    }
+
+    /// Inverts the upper 3x3 on its own and undoes the translation with it,
+    /// skipping the full 4x4 adjugate of the general path.
+    fn affine_inverse(&self) -> Option<Self> {
+        let mut matrix = self.normal_matrix()?.transpose();
+        let translation = Tuple4::vector(self.get(0, 3), self.get(1, 3), self.get(2, 3));
+        let moved = matrix * translation;
+        matrix.data[3] = -moved.x;
+        matrix.data[7] = -moved.y;
+        matrix.data[11] = -moved.z;
+
+        Some(matrix)
+    }
+
+    /// Analytic adjugate over the 2x2 determinants of the top two and the
+    /// bottom two rows, which every cofactor shares.
+    fn general_inverse(&self) -> Option<Self> {
+        let (s, c) = self.sub_determinants();
+        let det = Self::det_from(&s, &c);
+        if det.abs() < Self::PRECISION {
             return None;
         }
-        let mut matrix = Matrix4x4::zero();
-        for y in 0..Matrix4x4::N {
-            for x in 0..Matrix4x4::N {
-                let c = self.cofactor(y, x);
-                let i = self.get_index(x, y);
-                matrix.data[i] = c / det;
+        let a = &self.data;
+        let adjugate = [
+            a[5] * c[5] - a[6] * c[4] + a[7] * c[3],
+            -a[1] * c[5] + a[2] * c[4] - a[3] * c[3],
+            a[13] * s[5] - a[14] * s[4] + a[15] * s[3],
+            -a[9] * s[5] + a[10] * s[4] - a[11] * s[3],
+            -a[4] * c[5] + a[6] * c[2] - a[7] * c[1],
+            a[0] * c[5] - a[2] * c[2] + a[3] * c[1],
+            -a[12] * s[5] + a[14] * s[2] - a[15] * s[1],
+            a[8] * s[5] - a[10] * s[2] + a[11] * s[1],
+            a[4] * c[4] - a[5] * c[2] + a[7] * c[0],
+            -a[0] * c[4] + a[1] * c[2] - a[3] * c[0],
+            a[12] * s[4] - a[13] * s[2] + a[15] * s[0],
+            -a[8] * s[4] + a[9] * s[2] - a[11] * s[0],
+            -a[4] * c[3] + a[5] * c[1] - a[6] * c[0],
+            a[0] * c[3] - a[1] * c[1] + a[2] * c[0],
+            -a[12] * s[3] + a[13] * s[1] - a[14] * s[0],
+            a[8] * s[3] - a[9] * s[1] + a[10] * s[0],
+        ];
+
+        Some(Matrix4x4 {
+            data: adjugate.map(|n| n / det),
+        })
+    }
+
+    fn sub_determinants(&self) -> ([Elem; 6], [Elem; 6]) {
+        let a = &self.data;
+        let s = [
+            a[0] * a[5] - a[4] * a[1],
+            a[0] * a[6] - a[4] * a[2],
+            a[0] * a[7] - a[4] * a[3],
+            a[1] * a[6] - a[5] * a[2],
+            a[1] * a[7] - a[5] * a[3],
+            a[2] * a[7] - a[6] * a[3],
+        ];
+        let c = [
+            a[8] * a[13] - a[12] * a[9],
+            a[8] * a[14] - a[12] * a[10],
+            a[8] * a[15] - a[12] * a[11],
+            a[9] * a[14] - a[13] * a[10],
+            a[9] * a[15] - a[13] * a[11],
+            a[10] * a[15] - a[14] * a[11],
+        ];
+
+        (s, c)
+    }
+
+    fn det_from(s: &[Elem; 6], c: &[Elem; 6]) -> Elem {
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+    }
+
+    /// Inverse-transpose of the upper 3x3, which carries surface normals
+    /// from object to world space. Translation drops out, so the result maps
+    /// vectors to vectors.
+    pub fn normal_matrix(&self) -> Option<Self> {
+        let upper = self.submatrix(3, 3);
+        let det = upper.det();
+        if det.abs() < Self::PRECISION {
+            return None;
+        }
+        let mut matrix = Matrix4x4::identity();
+        for y in 0..Matrix3x3::N {
+            for x in 0..Matrix3x3::N {
+                let i = self.get_index(y, x);
+                matrix.data[i] = upper.cofactor(y, x) / det;
             }
         }
 
@@ -262,15 +436,6 @@ impl Matrix4x4 {
         Matrix3x3 { data }
     }
 
-    fn minor(&self, row: usize, col: usize) -> Elem {
-        self.submatrix(row, col).det()
-    }
-
-    fn cofactor(&self, row: usize, col: usize) -> Elem {
-        let n = if (row + col) % 2 == 1 { -1.0 } else { 1.0 };
-        n * self.minor(row, col)
-    }
-
     fn get_index(&self, y: usize, x: usize) -> usize {
         to_index(Matrix4x4::N, y, x)
     }
@@ -280,10 +445,23 @@ impl Matrix4x4 {
     }
 }
 
-impl Mul<Matrix4x4> for Matrix4x4 {
-    type Output = Self;
+impl TryFrom<&[Elem]> for Matrix4x4 {
+    type Error = MatrixError;
 
-    fn mul(self, rhs: Matrix4x4) -> Self::Output {
+    /// Reads 16 elements in row-major order.
+    fn try_from(elements: &[Elem]) -> Result<Self, Self::Error> {
+        let data = elements.try_into().map_err(|_| MatrixError::WrongLength {
+            found: elements.len(),
+        })?;
+
+        Ok(Matrix4x4::new(data))
+    }
+}
+
+impl Mul<&Matrix4x4> for &Matrix4x4 {
+    type Output = Matrix4x4;
+
+    fn mul(self, rhs: &Matrix4x4) -> Self::Output {
         let mut data = [0.0; Matrix4x4::size()];
 
         for y in 0..Matrix4x4::N {
@@ -300,33 +478,238 @@ impl Mul<Matrix4x4> for Matrix4x4 {
     }
 }
 
+impl Mul<Matrix4x4> for Matrix4x4 {
+    type Output = Matrix4x4;
+
+    fn mul(self, rhs: Matrix4x4) -> Self::Output {
+        Mul::mul(&self, &rhs)
+    }
+}
+
+impl Mul<&Matrix4x4> for Matrix4x4 {
+    type Output = Matrix4x4;
+
+    fn mul(self, rhs: &Matrix4x4) -> Self::Output {
+        Mul::mul(&self, rhs)
+    }
+}
+
+impl Mul<Matrix4x4> for &Matrix4x4 {
+    type Output = Matrix4x4;
+
+    fn mul(self, rhs: Matrix4x4) -> Self::Output {
+        Mul::mul(self, &rhs)
+    }
+}
+
+/// `a *= b` is `a = a * b`, so `b` is applied to points before `a`.
+impl MulAssign<Matrix4x4> for Matrix4x4 {
+    fn mul_assign(&mut self, rhs: Matrix4x4) {
+        *self = Mul::mul(&*self, &rhs);
+    }
+}
+
+impl MulAssign<&Matrix4x4> for Matrix4x4 {
+    fn mul_assign(&mut self, rhs: &Matrix4x4) {
+        *self = Mul::mul(&*self, rhs);
+    }
+}
+
+impl Mul<&Tuple4> for &Matrix4x4 {
+    type Output = Tuple4;
+
+    fn mul(self, rhs: &Tuple4) -> Self::Output {
+        simd::transform(&self.data, rhs)
+    }
+}
+
 impl Mul<Tuple4> for Matrix4x4 {
     type Output = Tuple4;
 
     fn mul(self, rhs: Tuple4) -> Self::Output {
-        let mut data = [0.0; Matrix4x4::N];
+        Mul::mul(&self, &rhs)
+    }
+}
 
-        for (i, row) in self.data.chunks(Matrix4x4::N).enumerate() {
-            let n = row[0] * rhs.x + row[1] * rhs.y + row[2] * rhs.z + row[3] * rhs.w;
-            data[i] = n;
-        }
+impl Mul<&Tuple4> for Matrix4x4 {
+    type Output = Tuple4;
+
+    fn mul(self, rhs: &Tuple4) -> Self::Output {
+        Mul::mul(&self, rhs)
+    }
+}
+
+impl Mul<Tuple4> for &Matrix4x4 {
+    type Output = Tuple4;
 
-        Tuple4::new(data[0], data[1], data[2], data[3])
+    fn mul(self, rhs: Tuple4) -> Self::Output {
+        Mul::mul(self, &rhs)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::f64::consts::PI;
+    use crate::float::{consts::PI, Float};
 
     use super::*;
 
-    const EPSILON: f64 = 1e-6;
+    const EPSILON: Float = 1e-6;
 
-    fn equal(a: f64, b: f64) -> bool {
+    fn equal(a: Float, b: Float) -> bool {
         (a - b).abs() < EPSILON
     }
 
+    fn matrices_equal(a: &Matrix4x4, b: &Matrix4x4) -> bool {
+        a.data.iter().zip(b.data.iter()).all(|(&a, &b)| equal(a, b))
+    }
+
+    #[test]
+    fn test_normal_matrix_is_the_inverse_transpose_without_translation() {
+        let m = Matrix4x4::translation(4.0, -2.0, 7.0)
+            * Matrix4x4::rotation_z(PI / 5.0)
+            * Matrix4x4::scaling(1.0, 0.5, 3.0);
+        let mut expected = m.general_inverse().unwrap().transpose();
+        for i in [3, 7, 11, 12, 13, 14] {
+            expected.data[i] = 0.0;
+        }
+
+        assert!(matrices_equal(&m.normal_matrix().unwrap(), &expected));
+        assert_eq!(Matrix4x4::scaling(1.0, 0.0, 1.0).normal_matrix(), None);
+    }
+
+    #[test]
+    fn test_affine_inverse_matches_the_general_inverse() {
+        let m = Matrix4x4::translation(4.0, -2.0, 7.0)
+            * Matrix4x4::rotation_y(PI / 3.0)
+            * Matrix4x4::shearing(1.0, 0.0, 0.5, 0.0, 0.0, 2.0)
+            * Matrix4x4::scaling(1.0, 0.5, 3.0);
+
+        assert!(m.is_affine());
+        assert!(matrices_equal(
+            &m.affine_inverse().unwrap(),
+            &m.general_inverse().unwrap()
+        ));
+        assert!(matrices_equal(
+            &(m * m.inverse().unwrap()),
+            &Matrix4x4::identity()
+        ));
+        assert_eq!(Matrix4x4::scaling(0.0, 1.0, 1.0).inverse(), None);
+    }
+
+    #[test]
+    fn test_projective_matrices_use_the_general_inverse() {
+        let m = Matrix4x4::new([
+            -5.0, 2.0, 6.0, -8.0, 1.0, -5.0, 1.0, 8.0, 7.0, 7.0, -6.0, -7.0, 1.0, -3.0, 7.0, 4.0,
+        ]);
+
+        assert!(!m.is_affine());
+        assert_eq!(m.inverse(), m.general_inverse());
+    }
+
+    #[test]
+    fn test_general_inverse_undoes_the_matrix() {
+        let m = Matrix4x4::new([
+            6.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 6.0, 4.0, -9.0, 3.0, -7.0, 9.0, 1.0, 7.0, -6.0,
+        ]);
+
+        let inverse = m.general_inverse().unwrap();
+
+        assert!(matrices_equal(&(m * inverse), &Matrix4x4::identity()));
+        assert!(matrices_equal(&(inverse * m), &Matrix4x4::identity()));
+    }
+
+    #[test]
+    fn test_multiply_assign() {
+        let mut m = Matrix4x4::translation(1.0, 0.0, 0.0);
+        m *= Matrix4x4::scaling(2.0, 2.0, 2.0);
+        m *= &Matrix4x4::rotation_z(PI / 2.0);
+
+        assert_eq!(
+            m,
+            Matrix4x4::translation(1.0, 0.0, 0.0)
+                * Matrix4x4::scaling(2.0, 2.0, 2.0)
+                * Matrix4x4::rotation_z(PI / 2.0)
+        );
+    }
+
+    #[test]
+    fn test_then_applies_transforms_in_reading_order() {
+        let p = Tuple4::point(1.0, 0.0, 0.0);
+        let scale_then_move =
+            Matrix4x4::scaling(2.0, 2.0, 2.0).then(&Matrix4x4::translation(1.0, 0.0, 0.0));
+        let move_then_scale =
+            Matrix4x4::translation(1.0, 0.0, 0.0).then(&Matrix4x4::scaling(2.0, 2.0, 2.0));
+
+        assert_eq!(scale_then_move * p, Tuple4::point(3.0, 0.0, 0.0));
+        assert_eq!(move_then_scale * p, Tuple4::point(4.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_composing_a_chain_of_transforms() {
+        let a = Matrix4x4::rotation_x(PI / 2.0);
+        let b = Matrix4x4::scaling(5.0, 5.0, 5.0);
+        let c = Matrix4x4::translation(10.0, 5.0, 7.0);
+
+        assert_eq!(Matrix4x4::compose(&[a, b, c]), c * b * a);
+        assert_eq!(Matrix4x4::compose(&[]), Matrix4x4::identity());
+    }
+
+    #[test]
+    #[allow(clippy::op_ref)]
+    fn test_operators_accept_references() {
+        let a = Matrix4x4::translation(1.0, 2.0, 3.0);
+        let b = Matrix4x4::scaling(2.0, 2.0, 2.0);
+        let p = Tuple4::point(1.0, 1.0, 1.0);
+
+        assert_eq!(&a * &b, a * b);
+        assert_eq!(a * &b, a * b);
+        assert_eq!(&a * b, a * b);
+        assert_eq!(&a * &p, a * p);
+        assert_eq!(a * &p, a * p);
+        assert_eq!(&a * p, a * p);
+    }
+
+    #[test]
+    fn test_building_a_matrix_from_rows() {
+        let rows = vec![
+            vec![1.0, 2.0, 3.0, 4.0],
+            vec![5.0, 6.0, 7.0, 8.0],
+            vec![9.0, 8.0, 7.0, 6.0],
+            vec![5.0, 4.0, 3.0, 2.0],
+        ];
+        let flat: Vec<Float> = rows.iter().flatten().copied().collect();
+
+        let m = Matrix4x4::from_rows(&rows).unwrap();
+
+        assert_eq!(m.get(1, 2), 7.0);
+        assert_eq!(m.get(3, 0), 5.0);
+        assert_eq!(Matrix4x4::try_from(flat.as_slice()), Ok(m));
+    }
+
+    #[test]
+    fn test_building_a_matrix_from_bad_input_fails() {
+        let short = [[1.0, 0.0, 0.0, 0.0]; 3];
+        let long = [[1.0, 0.0, 0.0, 0.0]; 5];
+        let ragged: [&[Float]; 4] = [&[1.0; 4], &[1.0; 4], &[1.0; 3], &[1.0; 4]];
+
+        assert_eq!(
+            Matrix4x4::from_rows(short),
+            Err(MatrixError::WrongRowCount { found: 3 })
+        );
+        assert_eq!(
+            Matrix4x4::from_rows(long),
+            Err(MatrixError::WrongRowCount { found: 5 })
+        );
+        assert_eq!(
+            Matrix4x4::from_rows(ragged),
+            Err(MatrixError::WrongRowLength { row: 2, found: 3 })
+        );
+        assert_eq!(
+            Matrix4x4::try_from(&[0.0; 15][..]),
+            Err(MatrixError::WrongLength { found: 15 })
+        );
+    }
+
     fn tuples_equal(a: &Tuple4, b: &Tuple4) -> bool {
         equal(a.x, b.x) && equal(a.y, b.y) && equal(a.z, b.z) && a.w == b.w
     }
@@ -526,7 +909,7 @@ mod tests {
 
         let is_invertible = matrix.is_invertible();
 
-        assert_eq!(is_invertible, true);
+        assert!(is_invertible);
     }
 
     #[test]
@@ -537,7 +920,7 @@ mod tests {
 
         let is_invertible = matrix.is_invertible();
 
-        assert_eq!(is_invertible, false);
+        assert!(!is_invertible);
     }
 
     #[test]
@@ -573,7 +956,7 @@ mod tests {
             for x in 0..4 {
                 let a = matrix.get(y, x);
                 let b = double_inversed.get(y, x);
-                assert!((a - b).abs() < Matrix4x4::PRECISION)
+                assert!((a - b).abs() < crate::float::EPSILON)
             }
         }
     }
@@ -669,8 +1052,8 @@ mod tests {
         let r2 = full_quarter * p;
 
         assert_eq!(r1.x, 0.0);
-        assert!(equal(r1.y, 2.0_f64.sqrt() / 2.0));
-        assert!(equal(r1.z, 2.0_f64.sqrt() / 2.0));
+        assert!(equal(r1.y, Float::sqrt(2.0) / 2.0));
+        assert!(equal(r1.z, Float::sqrt(2.0) / 2.0));
 
         assert_eq!(r2.x, 0.0);
         assert!(equal(r2.y, 0.0));
@@ -685,8 +1068,8 @@ mod tests {
         let result = half_quarter.inverse().unwrap() * p;
 
         assert_eq!(result.x, 0.0);
-        assert!(equal(result.y, 2.0_f64.sqrt() / 2.0));
-        assert!(equal(result.z, -2.0_f64.sqrt() / 2.0));
+        assert!(equal(result.y, Float::sqrt(2.0) / 2.0));
+        assert!(equal(result.z, -Float::sqrt(2.0) / 2.0));
     }
 
     #[test]
@@ -698,9 +1081,9 @@ mod tests {
         let r1 = half_quarter * p;
         let r2 = full_quarter * p;
 
-        assert!(equal(r1.x, 2.0_f64.sqrt() / 2.0));
+        assert!(equal(r1.x, Float::sqrt(2.0) / 2.0));
         assert_eq!(r1.y, 0.0);
-        assert!(equal(r1.z, 2.0_f64.sqrt() / 2.0));
+        assert!(equal(r1.z, Float::sqrt(2.0) / 2.0));
 
         assert!(equal(r2.x, 1.0));
         assert_eq!(r2.y, 0.0);
@@ -716,8 +1099,8 @@ mod tests {
         let r1 = half_quarter * p;
         let r2 = full_quarter * p;
 
-        assert!(equal(r1.x, -2.0_f64.sqrt() / 2.0));
-        assert!(equal(r1.y, 2.0_f64.sqrt() / 2.0));
+        assert!(equal(r1.x, -Float::sqrt(2.0) / 2.0));
+        assert!(equal(r1.y, Float::sqrt(2.0) / 2.0));
         assert_eq!(r1.z, 0.0);
 
         assert!(equal(r2.x, -1.0));
@@ -814,4 +1197,58 @@ mod tests {
 
         assert!(tuples_equal(&result, &Tuple4::point(15.0, 0.0, 7.0)));
     }
+
+    #[test]
+    fn test_view_transform_for_default_orientation() {
+        let from = Tuple4::point(0.0, 0.0, 0.0);
+        let to = Tuple4::point(0.0, 0.0, -1.0);
+        let up = Tuple4::vector(0.0, 1.0, 0.0);
+
+        let t = Matrix4x4::view_transform(from, to, up);
+
+        assert_eq!(t, Matrix4x4::identity());
+    }
+
+    #[test]
+    fn test_view_transform_looking_in_positive_z_direction() {
+        let from = Tuple4::point(0.0, 0.0, 0.0);
+        let to = Tuple4::point(0.0, 0.0, 1.0);
+        let up = Tuple4::vector(0.0, 1.0, 0.0);
+
+        let t = Matrix4x4::view_transform(from, to, up);
+
+        assert_eq!(t, Matrix4x4::scaling(-1.0, 1.0, -1.0));
+    }
+
+    #[test]
+    fn test_view_transform_moves_the_world() {
+        let from = Tuple4::point(0.0, 0.0, 8.0);
+        let to = Tuple4::point(0.0, 0.0, 0.0);
+        let up = Tuple4::vector(0.0, 1.0, 0.0);
+
+        let t = Matrix4x4::view_transform(from, to, up);
+
+        assert_eq!(t, Matrix4x4::translation(0.0, 0.0, -8.0));
+    }
+
+    #[test]
+    fn test_arbitrary_view_transform() {
+        let from = Tuple4::point(1.0, 3.0, 2.0);
+        let to = Tuple4::point(4.0, -2.0, 8.0);
+        let up = Tuple4::vector(1.0, 1.0, 0.0);
+
+        let t = Matrix4x4::view_transform(from, to, up);
+
+        let expected = Matrix4x4::new([
+            -0.50709, 0.50709, 0.67612, -2.36643, 0.76772, 0.60609, 0.12122, -2.82843, -0.35857,
+            0.59761, -0.71714, 0.00000, 0.00000, 0.00000, 0.00000, 1.00000,
+        ]);
+        for y in 0..4 {
+            for x in 0..4 {
+                let a = expected.get(y, x);
+                let b = t.get(y, x);
+                assert!((a - b).abs() < 1e-5)
+            }
+        }
+    }
 }
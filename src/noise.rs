@@ -0,0 +1,173 @@
+use crate::{
+    float::Float,
+    rng::{RandomSource, Rng},
+    tuple::Tuple4,
+};
+
+/// Improved Perlin gradient noise with a permutation table shuffled from a seed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Perlin {
+    permutation: [u8; 512],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut table = [0u8; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = i as u8;
+        }
+        let mut rng = Rng::new(seed);
+        for i in (1..256).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            table.swap(i, j);
+        }
+
+        let mut permutation = [0u8; 512];
+        for (i, entry) in permutation.iter_mut().enumerate() {
+            *entry = table[i % 256];
+        }
+
+        Perlin { permutation }
+    }
+
+    /// Noise value in roughly [-1, 1]; zero at every integer lattice point.
+    pub fn noise(&self, point: Tuple4) -> Float {
+        let (xi, yi, zi) = (
+            point.x.floor() as i64 as usize & 255,
+            point.y.floor() as i64 as usize & 255,
+            point.z.floor() as i64 as usize & 255,
+        );
+        let (x, y, z) = (
+            point.x - point.x.floor(),
+            point.y - point.y.floor(),
+            point.z - point.z.floor(),
+        );
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let p = |i: usize| self.permutation[i] as usize;
+        let a = p(xi) + yi;
+        let aa = p(a) + zi;
+        let ab = p(a + 1) + zi;
+        let b = p(xi + 1) + yi;
+        let ba = p(b) + zi;
+        let bb = p(b + 1) + zi;
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(p(aa), x, y, z), grad(p(ba), x - 1.0, y, z)),
+                lerp(
+                    u,
+                    grad(p(ab), x, y - 1.0, z),
+                    grad(p(bb), x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(p(aa + 1), x, y, z - 1.0),
+                    grad(p(ba + 1), x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(p(ab + 1), x, y - 1.0, z - 1.0),
+                    grad(p(bb + 1), x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+
+    /// Sum of `octaves` layers of absolute noise, each at double the
+    /// frequency and half the amplitude of the previous one.
+    pub fn turbulence(&self, point: Tuple4, octaves: usize) -> Float {
+        let mut sum = 0.0;
+        let mut scale = 1.0;
+        for _ in 0..octaves {
+            let p = Tuple4::point(point.x * scale, point.y * scale, point.z * scale);
+            sum += self.noise(p).abs() / scale;
+            scale *= 2.0;
+        }
+
+        sum
+    }
+}
+
+fn fade(t: Float) -> Float {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: Float, a: Float, b: Float) -> Float {
+    a + t * (b - a)
+}
+
+fn grad(hash: usize, x: Float, y: Float, z: Float) -> Float {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::Float;
+
+    #[test]
+    fn test_noise_vanishes_on_lattice_points() {
+        let perlin = Perlin::new(0);
+
+        for p in [
+            Tuple4::point(0.0, 0.0, 0.0),
+            Tuple4::point(1.0, 2.0, 3.0),
+            Tuple4::point(-4.0, 7.0, -1.0),
+        ] {
+            assert_eq!(perlin.noise(p), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_noise_is_bounded_and_varies() {
+        let perlin = Perlin::new(1);
+        let mut rng = Rng::new(2);
+        let values: Vec<Float> = (0..1000)
+            .map(|_| {
+                let p = Tuple4::point(
+                    rng.next_float() * 20.0 - 10.0,
+                    rng.next_float() * 20.0 - 10.0,
+                    rng.next_float() * 20.0 - 10.0,
+                );
+                perlin.noise(p)
+            })
+            .collect();
+
+        assert!(values.iter().all(|v| (-1.1..=1.1).contains(v)));
+        assert!(values.iter().any(|&v| v > 0.2));
+        assert!(values.iter().any(|&v| v < -0.2));
+    }
+
+    #[test]
+    fn test_noise_depends_on_the_seed() {
+        let p = Tuple4::point(0.3, 1.7, -2.2);
+
+        assert_eq!(Perlin::new(5).noise(p), Perlin::new(5).noise(p));
+        assert_ne!(Perlin::new(5).noise(p), Perlin::new(6).noise(p));
+    }
+
+    #[test]
+    fn test_turbulence_is_non_negative() {
+        let perlin = Perlin::new(3);
+
+        let t = perlin.turbulence(Tuple4::point(0.4, 0.6, 0.2), 4);
+
+        assert!(t > 0.0);
+    }
+}
@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+use crate::{
+    float::Float,
+    patterns::Pattern,
+    sampling::Onb,
+    sphere::Sphere,
+    texture::{Texture, UvMapping},
+    tuple::Tuple4,
+};
+
+const BUMP_DELTA: Float = 1e-4;
+
+/// Perturbs the shading normal of a surface without changing its geometry.
+#[derive(Debug, Clone)]
+pub enum NormalMap {
+    /// Tangent-space normals stored in a texture, with each channel mapped
+    /// from `[0, 1]` to `[-1, 1]` and blue pointing along the surface normal.
+    Tangent {
+        texture: Arc<Texture>,
+        mapping: UvMapping,
+    },
+    /// A height field read from the average channel of a pattern; the normal
+    /// tilts against its object-space gradient scaled by `strength`.
+    Bump {
+        height: Box<dyn Pattern>,
+        strength: Float,
+    },
+}
+
+impl NormalMap {
+    pub fn perturb(&self, object: &Sphere, point: Tuple4, normal: Tuple4) -> Tuple4 {
+        let object_point = object.get_inverse_transform() * point;
+
+        match self {
+            NormalMap::Tangent { texture, mapping } => {
+                let (u, v) = mapping.map(object_point);
+                let c = texture.color_at(u, v);
+                let (tangent, bitangent) = tangent_frame(object, *mapping, object_point, normal);
+
+                (tangent * (2.0 * c.r - 1.0)
+                    + bitangent * (2.0 * c.g - 1.0)
+                    + normal * (2.0 * c.b - 1.0))
+                    .normalize()
+            }
+            NormalMap::Bump { height, strength } => {
+                let h = |dx: Float, dy: Float, dz: Float| {
+                    let c = height.color_at(object_point + Tuple4::vector(dx, dy, dz));
+                    (c.r + c.g + c.b) / 3.0
+                };
+                let d = 2.0 * BUMP_DELTA;
+                let gradient = Tuple4::vector(
+                    (h(BUMP_DELTA, 0.0, 0.0) - h(-BUMP_DELTA, 0.0, 0.0)) / d,
+                    (h(0.0, BUMP_DELTA, 0.0) - h(0.0, -BUMP_DELTA, 0.0)) / d,
+                    (h(0.0, 0.0, BUMP_DELTA) - h(0.0, 0.0, -BUMP_DELTA)) / d,
+                );
+                let gradient = object.get_normal_matrix() * gradient;
+                let surface_gradient = gradient - normal * gradient.dot(&normal);
+
+                (normal - surface_gradient * *strength).normalize()
+            }
+        }
+    }
+}
+
+impl PartialEq for NormalMap {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                NormalMap::Tangent { texture, mapping },
+                NormalMap::Tangent {
+                    texture: other_texture,
+                    mapping: other_mapping,
+                },
+            ) => texture == other_texture && mapping == other_mapping,
+            (
+                NormalMap::Bump { height, strength },
+                NormalMap::Bump {
+                    height: other_height,
+                    strength: other_strength,
+                },
+            ) => height.as_ref() == other_height.as_ref() && strength == other_strength,
+            _ => false,
+        }
+    }
+}
+
+/// Tangent along growing u and bitangent along growing v, taken from the
+/// mapping so the texture's axes line up with how it is laid on the object.
+fn tangent_frame(
+    object: &Sphere,
+    mapping: UvMapping,
+    object_point: Tuple4,
+    normal: Tuple4,
+) -> (Tuple4, Tuple4) {
+    let (du, dv) = mapping.tangents(object_point);
+    let du = object.get_transform() * du;
+    let dv = object.get_transform() * dv;
+    let along = du - normal * du.dot(&normal);
+    let tangent = if along.magnitude() > 1e-6 {
+        along.normalize()
+    } else {
+        // At the poles of a spherical mapping u has no direction.
+        Onb::from_normal(normal).u
+    };
+    let bitangent = normal.cross(tangent);
+
+    if bitangent.dot(&dv) < 0.0 {
+        (tangent, -1.0 * bitangent)
+    } else {
+        (tangent, bitangent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        canvas::Canvas,
+        color::Color,
+        float::Float,
+        matrix::Matrix4x4,
+        patterns::{GradientPattern, StripePattern},
+    };
+
+    use super::*;
+
+    const EPSILON: Float = 1e-6;
+
+    fn tuples_equal(a: &Tuple4, b: &Tuple4) -> bool {
+        (a.x - b.x).abs() < EPSILON && (a.y - b.y).abs() < EPSILON && (a.z - b.z).abs() < EPSILON
+    }
+
+    fn flat_texture(color: Color) -> Arc<Texture> {
+        let mut canvas = Canvas::new(1, 1);
+        canvas.put_pixel(color, (0, 0));
+
+        Arc::new(Texture::from_canvas(&canvas))
+    }
+
+    #[test]
+    fn test_a_flat_tangent_map_keeps_the_normal() {
+        let map = NormalMap::Tangent {
+            texture: flat_texture(Color::new(0.5, 0.5, 1.0)),
+            mapping: UvMapping::Spherical,
+        };
+        let sphere = Sphere::new();
+        let point = Tuple4::point(0.0, 0.0, -1.0);
+        let normal = sphere.normal_at(point);
+
+        assert!(tuples_equal(&map.perturb(&sphere, point, normal), &normal));
+    }
+
+    #[test]
+    fn test_a_tangent_map_tilts_the_normal() {
+        let map = NormalMap::Tangent {
+            texture: flat_texture(Color::new(1.0, 0.5, 0.5)),
+            mapping: UvMapping::Spherical,
+        };
+        let sphere = Sphere::new();
+        let point = Tuple4::point(0.0, 0.0, -1.0);
+        let normal = sphere.normal_at(point);
+
+        let perturbed = map.perturb(&sphere, point, normal);
+
+        assert!(tuples_equal(&perturbed, &Tuple4::vector(1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_tangent_maps_follow_the_uv_axes_of_the_mapping() {
+        let sphere = Sphere::new();
+        let point = Tuple4::point(0.0, 1.0, 0.0);
+        let normal = sphere.normal_at(point);
+        let tilt = |color| {
+            let map = NormalMap::Tangent {
+                texture: flat_texture(color),
+                mapping: UvMapping::Planar,
+            };
+            map.perturb(&sphere, point, normal)
+        };
+
+        let along_u = tilt(Color::new(1.0, 0.5, 0.5));
+        let along_v = tilt(Color::new(0.5, 1.0, 0.5));
+
+        assert!(tuples_equal(&along_u, &Tuple4::vector(1.0, 0.0, 0.0)));
+        assert!(tuples_equal(&along_v, &Tuple4::vector(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn test_tangent_maps_have_no_seam_near_the_pole() {
+        let map = NormalMap::Tangent {
+            texture: flat_texture(Color::new(1.0, 0.5, 0.5)),
+            mapping: UvMapping::Planar,
+        };
+        let sphere = Sphere::new();
+        let tilt = |y: Float| {
+            let point = Tuple4::point(0.0, y, -(1.0 - y * y).sqrt());
+            map.perturb(&sphere, point, sphere.normal_at(point))
+        };
+
+        let a = tilt(0.9989);
+        let b = tilt(0.9991);
+
+        assert!((a - b).magnitude() < 1e-3);
+    }
+
+    #[test]
+    fn test_tangent_maps_at_a_spherical_pole_still_tilt_along_the_surface() {
+        let map = NormalMap::Tangent {
+            texture: flat_texture(Color::new(1.0, 0.5, 0.5)),
+            mapping: UvMapping::Spherical,
+        };
+        let sphere = Sphere::new();
+        let point = Tuple4::point(0.0, 1.0, 0.0);
+        let normal = sphere.normal_at(point);
+
+        let perturbed = map.perturb(&sphere, point, normal);
+
+        assert!(tuples_equal(&perturbed, &Onb::from_normal(normal).u));
+        assert!(perturbed.dot(&normal).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_a_constant_height_field_keeps_the_normal() {
+        let map = NormalMap::Bump {
+            height: Box::new(StripePattern::new(
+                Color::new(0.5, 0.5, 0.5),
+                Color::new(0.5, 0.5, 0.5),
+            )),
+            strength: 1.0,
+        };
+        let sphere = Sphere::new();
+        let point = Tuple4::point(0.0, 1.0, 0.0);
+        let normal = sphere.normal_at(point);
+
+        assert!(tuples_equal(&map.perturb(&sphere, point, normal), &normal));
+    }
+
+    #[test]
+    fn test_a_bump_tilts_the_normal_downhill() {
+        let mut ramp = GradientPattern::new(Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        ramp.set_transform(Matrix4x4::translation(-0.5, 0.0, 0.0));
+        let map = NormalMap::Bump {
+            height: Box::new(ramp),
+            strength: 0.5,
+        };
+        let sphere = Sphere::new();
+        let point = Tuple4::point(0.0, 1.0, 0.0);
+        let normal = sphere.normal_at(point);
+
+        let perturbed = map.perturb(&sphere, point, normal);
+
+        assert!(perturbed.x < 0.0);
+        assert!((perturbed.magnitude() - 1.0).abs() < EPSILON);
+    }
+}
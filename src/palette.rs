@@ -0,0 +1,104 @@
+use crate::{color::Color, float::Float};
+
+/// Colors for false-color diagnostic renders. `Viridis` and `Magma` change
+/// evenly in lightness and stay readable with red-green colorblindness,
+/// unlike a plain red-to-green or hue ramp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Palette {
+    Grayscale,
+    #[default]
+    Viridis,
+    Magma,
+}
+
+const VIRIDIS: [[Float; 3]; 9] = [
+    [0.267004, 0.004874, 0.329415],
+    [0.282623, 0.140926, 0.457517],
+    [0.229739, 0.322361, 0.545706],
+    [0.172719, 0.448791, 0.557885],
+    [0.127568, 0.566949, 0.550556],
+    [0.157851, 0.683765, 0.501686],
+    [0.369214, 0.788888, 0.382914],
+    [0.678489, 0.863742, 0.189503],
+    [0.993248, 0.906157, 0.143936],
+];
+
+const MAGMA: [[Float; 3]; 9] = [
+    [0.001462, 0.000466, 0.013866],
+    [0.078815, 0.054184, 0.211667],
+    [0.232077, 0.059889, 0.437695],
+    [0.390384, 0.100379, 0.501864],
+    [0.550287, 0.161158, 0.505719],
+    [0.716387, 0.214982, 0.47472],
+    [0.868793, 0.287728, 0.409303],
+    [0.967671, 0.439703, 0.35981],
+    [0.987053, 0.991438, 0.749504],
+];
+
+impl Palette {
+    /// Color for `t` between 0 and 1; values outside are clamped. The
+    /// colors are already encoded for display, so images made from them
+    /// should be written without `Canvas::to_srgb`.
+    pub fn color(&self, t: Float) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let stops = match self {
+            Palette::Grayscale => return Color::new(t, t, t),
+            Palette::Viridis => &VIRIDIS,
+            Palette::Magma => &MAGMA,
+        };
+
+        let x = t * (stops.len() - 1) as Float;
+        let i = (x.floor() as usize).min(stops.len() - 2);
+        let f = x - i as Float;
+        let [r0, g0, b0] = stops[i];
+        let [r1, g1, b1] = stops[i + 1];
+
+        Color::new(r0 + (r1 - r0) * f, g0 + (g1 - g0) * f, b0 + (b1 - b0) * f)
+    }
+
+    /// Color for pixels without a value, e.g. rays that hit nothing. It is
+    /// kept off the ramp so it cannot be read as a measurement.
+    pub fn missing(&self) -> Color {
+        match self {
+            Palette::Grayscale => Color::new(0.5, 0.0, 0.0),
+            Palette::Viridis | Palette::Magma => Color::new(0.5, 0.5, 0.5),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palettes_get_lighter_from_end_to_end() {
+        for palette in [Palette::Grayscale, Palette::Viridis, Palette::Magma] {
+            let lightness: Vec<Float> = (0..=20)
+                .map(|i| palette.color(i as Float / 20.0).luminance())
+                .collect();
+
+            assert!(
+                lightness.windows(2).all(|w| w[1] > w[0]),
+                "{:?}: {:?}",
+                palette,
+                lightness
+            );
+            assert_eq!(palette.color(-1.0), palette.color(0.0));
+            assert_eq!(palette.color(2.0), palette.color(1.0));
+        }
+    }
+
+    #[test]
+    fn test_missing_values_stand_out_from_the_ramp() {
+        for palette in [Palette::Grayscale, Palette::Viridis, Palette::Magma] {
+            let missing = palette.missing();
+
+            for i in 0..=100 {
+                let c = palette.color(i as Float / 100.0);
+                let distance =
+                    (c.r - missing.r).abs() + (c.g - missing.g).abs() + (c.b - missing.b).abs();
+                assert!(distance > 0.1, "{:?} at {}: {:?}", palette, i, c);
+            }
+        }
+    }
+}
@@ -0,0 +1,468 @@
+use std::{any::Any, fmt::Debug};
+
+use crate::{
+    color::Color,
+    float::{consts::PI, Float},
+    matrix::Matrix4x4,
+    noise::Perlin,
+    tuple::Tuple4,
+};
+
+pub trait Pattern: PatternClone + Debug + Send + Sync {
+    fn get_transform(&self) -> &Matrix4x4;
+
+    fn get_inverse_transform(&self) -> &Matrix4x4;
+
+    fn pattern_at(&self, point: Tuple4) -> Color;
+
+    fn color_at(&self, object_point: Tuple4) -> Color {
+        self.pattern_at(self.get_inverse_transform() * object_point)
+    }
+}
+
+/// Lets `Box<dyn Pattern>` be cloned and compared, so materials holding a
+/// pattern can keep deriving `Clone` and `PartialEq`.
+pub trait PatternClone {
+    fn box_clone(&self) -> Box<dyn Pattern>;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn dyn_eq(&self, other: &dyn Pattern) -> bool;
+}
+
+impl<T: Pattern + Clone + PartialEq + 'static> PatternClone for T {
+    fn box_clone(&self) -> Box<dyn Pattern> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn dyn_eq(&self, other: &dyn Pattern) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+}
+
+impl Clone for Box<dyn Pattern> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+impl PartialEq for dyn Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.dyn_eq(other)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StripePattern {
+    a: Color,
+    b: Color,
+    transform: Matrix4x4,
+    inverse: Matrix4x4,
+}
+
+impl StripePattern {
+    pub fn new(a: Color, b: Color) -> Self {
+        StripePattern {
+            a,
+            b,
+            transform: Matrix4x4::identity(),
+            inverse: Matrix4x4::identity(),
+        }
+    }
+
+    pub fn set_transform(&mut self, m: Matrix4x4) {
+        self.inverse = m.inverse().expect("Can't inverse singular matrix");
+        self.transform = m;
+    }
+}
+
+impl Pattern for StripePattern {
+    fn get_transform(&self) -> &Matrix4x4 {
+        &self.transform
+    }
+
+    fn get_inverse_transform(&self) -> &Matrix4x4 {
+        &self.inverse
+    }
+
+    fn pattern_at(&self, point: Tuple4) -> Color {
+        if point.x.floor().rem_euclid(2.0) == 0.0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradientPattern {
+    a: Color,
+    b: Color,
+    transform: Matrix4x4,
+    inverse: Matrix4x4,
+}
+
+impl GradientPattern {
+    pub fn new(a: Color, b: Color) -> Self {
+        GradientPattern {
+            a,
+            b,
+            transform: Matrix4x4::identity(),
+            inverse: Matrix4x4::identity(),
+        }
+    }
+
+    pub fn set_transform(&mut self, m: Matrix4x4) {
+        self.inverse = m.inverse().expect("Can't inverse singular matrix");
+        self.transform = m;
+    }
+}
+
+impl Pattern for GradientPattern {
+    fn get_transform(&self) -> &Matrix4x4 {
+        &self.transform
+    }
+
+    fn get_inverse_transform(&self) -> &Matrix4x4 {
+        &self.inverse
+    }
+
+    fn pattern_at(&self, point: Tuple4) -> Color {
+        let fraction = point.x - point.x.floor();
+
+        self.a + (self.b - self.a) * fraction
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RingPattern {
+    a: Color,
+    b: Color,
+    transform: Matrix4x4,
+    inverse: Matrix4x4,
+}
+
+impl RingPattern {
+    pub fn new(a: Color, b: Color) -> Self {
+        RingPattern {
+            a,
+            b,
+            transform: Matrix4x4::identity(),
+            inverse: Matrix4x4::identity(),
+        }
+    }
+
+    pub fn set_transform(&mut self, m: Matrix4x4) {
+        self.inverse = m.inverse().expect("Can't inverse singular matrix");
+        self.transform = m;
+    }
+}
+
+impl Pattern for RingPattern {
+    fn get_transform(&self) -> &Matrix4x4 {
+        &self.transform
+    }
+
+    fn get_inverse_transform(&self) -> &Matrix4x4 {
+        &self.inverse
+    }
+
+    fn pattern_at(&self, point: Tuple4) -> Color {
+        if point.x.hypot(point.z).floor().rem_euclid(2.0) == 0.0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckerPattern {
+    a: Color,
+    b: Color,
+    transform: Matrix4x4,
+    inverse: Matrix4x4,
+}
+
+impl CheckerPattern {
+    pub fn new(a: Color, b: Color) -> Self {
+        CheckerPattern {
+            a,
+            b,
+            transform: Matrix4x4::identity(),
+            inverse: Matrix4x4::identity(),
+        }
+    }
+
+    pub fn set_transform(&mut self, m: Matrix4x4) {
+        self.inverse = m.inverse().expect("Can't inverse singular matrix");
+        self.transform = m;
+    }
+}
+
+impl Pattern for CheckerPattern {
+    fn get_transform(&self) -> &Matrix4x4 {
+        &self.transform
+    }
+
+    fn get_inverse_transform(&self) -> &Matrix4x4 {
+        &self.inverse
+    }
+
+    fn pattern_at(&self, point: Tuple4) -> Color {
+        let sum = point.x.floor() + point.y.floor() + point.z.floor();
+
+        if sum.rem_euclid(2.0) == 0.0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+/// Jitters lookups into another pattern by Perlin noise, offsetting each
+/// axis by up to `scale` in pattern space.
+#[derive(Debug, Clone)]
+pub struct PerturbPattern {
+    pattern: Box<dyn Pattern>,
+    noise: Perlin,
+    scale: Float,
+    transform: Matrix4x4,
+    inverse: Matrix4x4,
+}
+
+impl PerturbPattern {
+    pub fn new<P: Pattern + 'static>(pattern: P, scale: Float, seed: u64) -> Self {
+        PerturbPattern {
+            pattern: Box::new(pattern),
+            noise: Perlin::new(seed),
+            scale,
+            transform: Matrix4x4::identity(),
+            inverse: Matrix4x4::identity(),
+        }
+    }
+
+    pub fn set_transform(&mut self, m: Matrix4x4) {
+        self.inverse = m.inverse().expect("Can't inverse singular matrix");
+        self.transform = m;
+    }
+}
+
+impl PartialEq for PerturbPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_ref() == other.pattern.as_ref()
+            && self.noise == other.noise
+            && self.scale == other.scale
+            && self.transform == other.transform
+    }
+}
+
+impl Pattern for PerturbPattern {
+    fn get_transform(&self) -> &Matrix4x4 {
+        &self.transform
+    }
+
+    fn get_inverse_transform(&self) -> &Matrix4x4 {
+        &self.inverse
+    }
+
+    fn pattern_at(&self, point: Tuple4) -> Color {
+        let jitter = Tuple4::vector(
+            self.noise.noise(point),
+            self.noise.noise(point + Tuple4::vector(31.4, 0.0, 0.0)),
+            self.noise.noise(point + Tuple4::vector(0.0, 0.0, 27.1)),
+        );
+
+        self.pattern.color_at(point + jitter * self.scale)
+    }
+}
+
+pub fn marble(vein: Color, base: Color) -> PerturbPattern {
+    let mut stripes = GradientPattern::new(vein, base);
+    stripes.set_transform(Matrix4x4::rotation_z(PI / 5.0) * Matrix4x4::scaling(0.6, 1.0, 1.0));
+    let mut pattern = PerturbPattern::new(stripes, 0.7, 0);
+    pattern.set_transform(Matrix4x4::scaling(0.8, 0.8, 0.8));
+
+    pattern
+}
+
+pub fn wood(light: Color, dark: Color) -> PerturbPattern {
+    let mut rings = RingPattern::new(light, dark);
+    rings.set_transform(Matrix4x4::scaling(0.1, 0.1, 0.1));
+    let mut pattern = PerturbPattern::new(rings, 0.05, 1);
+    pattern.set_transform(Matrix4x4::scaling(1.0, 4.0, 1.0));
+
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::Float;
+
+    fn white() -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    fn black() -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    #[test]
+    fn test_creating_a_stripe_pattern() {
+        let pattern = StripePattern::new(white(), black());
+
+        assert_eq!(pattern.a, white());
+        assert_eq!(pattern.b, black());
+        assert_eq!(*pattern.get_transform(), Matrix4x4::identity());
+    }
+
+    #[test]
+    fn test_stripe_pattern_is_constant_in_y_and_z() {
+        let pattern = StripePattern::new(white(), black());
+
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 1.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 2.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 0.0, 1.0)), white());
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 0.0, 2.0)), white());
+    }
+
+    #[test]
+    fn test_stripe_pattern_alternates_in_x() {
+        let pattern = StripePattern::new(white(), black());
+
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.9, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Tuple4::point(1.0, 0.0, 0.0)), black());
+        assert_eq!(pattern.pattern_at(Tuple4::point(-0.1, 0.0, 0.0)), black());
+        assert_eq!(pattern.pattern_at(Tuple4::point(-1.0, 0.0, 0.0)), black());
+        assert_eq!(pattern.pattern_at(Tuple4::point(-1.1, 0.0, 0.0)), white());
+    }
+
+    #[test]
+    fn test_pattern_transformation() {
+        let mut pattern = StripePattern::new(white(), black());
+        pattern.set_transform(Matrix4x4::scaling(2.0, 2.0, 2.0));
+
+        assert_eq!(pattern.color_at(Tuple4::point(1.5, 0.0, 0.0)), white());
+        assert_eq!(pattern.color_at(Tuple4::point(2.5, 0.0, 0.0)), black());
+    }
+
+    #[test]
+    fn test_boxed_patterns_clone_and_compare() {
+        let a: Box<dyn Pattern> = Box::new(StripePattern::new(white(), black()));
+        let b: Box<dyn Pattern> = Box::new(StripePattern::new(black(), white()));
+
+        assert!(a == a.clone());
+        assert!(a != b);
+    }
+
+    #[test]
+    fn test_gradient_linearly_interpolates_between_colors() {
+        let pattern = GradientPattern::new(white(), black());
+
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 0.0, 0.0)), white());
+        assert_eq!(
+            pattern.pattern_at(Tuple4::point(0.25, 0.0, 0.0)),
+            Color::new(0.75, 0.75, 0.75)
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4::point(0.5, 0.0, 0.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            pattern.pattern_at(Tuple4::point(0.75, 0.0, 0.0)),
+            Color::new(0.25, 0.25, 0.25)
+        );
+    }
+
+    #[test]
+    fn test_ring_extends_in_both_x_and_z() {
+        let pattern = RingPattern::new(white(), black());
+
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Tuple4::point(1.0, 0.0, 0.0)), black());
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 0.0, 1.0)), black());
+        assert_eq!(
+            pattern.pattern_at(Tuple4::point(0.708, 0.0, 0.708)),
+            black()
+        );
+    }
+
+    #[test]
+    fn test_checkers_repeat_in_x() {
+        let pattern = CheckerPattern::new(white(), black());
+
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.99, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Tuple4::point(1.01, 0.0, 0.0)), black());
+    }
+
+    #[test]
+    fn test_checkers_repeat_in_y() {
+        let pattern = CheckerPattern::new(white(), black());
+
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 0.99, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 1.01, 0.0)), black());
+    }
+
+    #[test]
+    fn test_checkers_repeat_in_z() {
+        let pattern = CheckerPattern::new(white(), black());
+
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 0.0, 0.0)), white());
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 0.0, 0.99)), white());
+        assert_eq!(pattern.pattern_at(Tuple4::point(0.0, 0.0, 1.01)), black());
+    }
+
+    #[test]
+    fn test_different_pattern_types_are_not_equal() {
+        let a: Box<dyn Pattern> = Box::new(StripePattern::new(white(), black()));
+        let b: Box<dyn Pattern> = Box::new(CheckerPattern::new(white(), black()));
+
+        assert!(a != b);
+    }
+
+    #[test]
+    fn test_perturbing_by_zero_leaves_the_pattern_unchanged() {
+        let stripes = StripePattern::new(white(), black());
+        let pattern = PerturbPattern::new(stripes.clone(), 0.0, 9);
+
+        for x in [-1.5, -0.2, 0.3, 0.99, 1.4, 2.7] {
+            let p = Tuple4::point(x, 0.3, -0.8);
+            assert_eq!(pattern.pattern_at(p), stripes.pattern_at(p));
+        }
+    }
+
+    #[test]
+    fn test_perturbing_moves_stripe_boundaries() {
+        let stripes = StripePattern::new(white(), black());
+        let pattern = PerturbPattern::new(stripes.clone(), 0.5, 9);
+
+        let changed = (0..200)
+            .map(|i| Tuple4::point(i as Float * 0.037, 0.41, 0.73))
+            .filter(|&p| pattern.pattern_at(p) != stripes.pattern_at(p))
+            .count();
+
+        assert!(changed > 0);
+    }
+
+    #[test]
+    fn test_marble_and_wood_blend_their_colors() {
+        let a = Color::new(1.0, 0.0, 0.0);
+        let b = Color::new(0.0, 0.0, 1.0);
+
+        for pattern in [marble(a, b), wood(a, b)] {
+            let colors: Vec<Color> = (0..50)
+                .map(|i| pattern.color_at(Tuple4::point(i as Float * 0.13, 0.2, i as Float * 0.07)))
+                .collect();
+            assert!(colors.contains(&a) || colors.iter().any(|c| c.r > 0.5));
+            assert!(colors.contains(&b) || colors.iter().any(|c| c.b > 0.5));
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use crate::{
+    color::{Color, ColorSum},
+    float::{consts::PI, Float},
+    tuple::Tuple4,
+};
+
+/// Share of newly found photons kept when a gather shrinks its radius;
+/// lower values shrink faster.
+const PHOTON_ALPHA: Float = 0.7;
+
+/// Light that landed on a surface after being traced from a light source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Photon {
+    pub position: Tuple4,
+    /// Direction the photon was travelling in when it landed.
+    pub direction: Tuple4,
+    pub power: Color,
+}
+
+/// Photons bucketed into a uniform grid, so gathering around a point only
+/// looks at the cells the search radius overlaps.
+#[derive(Debug, Clone, Default)]
+pub struct PhotonMap {
+    cell_size: Float,
+    cells: HashMap<(i64, i64, i64), Vec<Photon>>,
+    len: usize,
+}
+
+impl PhotonMap {
+    /// Lookups are quickest with a radius no larger than `cell_size`.
+    pub fn new(photons: Vec<Photon>, cell_size: Float) -> Self {
+        let mut map = PhotonMap {
+            cell_size,
+            cells: HashMap::new(),
+            len: photons.len(),
+        };
+        for photon in photons {
+            let cell = map.cell(photon.position);
+            map.cells.entry(cell).or_default().push(photon);
+        }
+
+        map
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Calls `visit` with every photon within `radius` of `point`.
+    pub fn for_each_within<F: FnMut(&Photon)>(&self, point: Tuple4, radius: Float, mut visit: F) {
+        let reach = (radius / self.cell_size).ceil() as i64;
+        let (cx, cy, cz) = self.cell(point);
+        let radius2 = radius * radius;
+        for x in cx - reach..=cx + reach {
+            for y in cy - reach..=cy + reach {
+                for z in cz - reach..=cz + reach {
+                    let photons = match self.cells.get(&(x, y, z)) {
+                        Some(photons) => photons,
+                        None => continue,
+                    };
+                    for photon in photons {
+                        let d = photon.position - point;
+                        if d.dot(&d) <= radius2 {
+                            visit(photon);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn cell(&self, point: Tuple4) -> (i64, i64, i64) {
+        let index = |n: Float| (n / self.cell_size).floor() as i64;
+
+        (index(point.x), index(point.y), index(point.z))
+    }
+}
+
+/// Running estimate for one camera ray over the passes of stochastic
+/// progressive photon mapping. After each pass the search radius shrinks
+/// and the gathered flux is rescaled to match, as in Hachisuka and Jensen,
+/// so the photon map itself can be dropped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhotonGather {
+    radius2: Float,
+    found: Float,
+    flux: Color,
+    direct: ColorSum,
+    passes: usize,
+}
+
+impl PhotonGather {
+    pub fn new(radius: Float) -> Self {
+        PhotonGather {
+            radius2: radius * radius,
+            found: 0.0,
+            flux: Color::new(0.0, 0.0, 0.0),
+            direct: ColorSum::new(),
+            passes: 0,
+        }
+    }
+
+    /// Radius to gather the next pass's photons in.
+    pub fn radius(&self) -> Float {
+        self.radius2.sqrt()
+    }
+
+    /// Records one pass: the `direct` light seen along the ray, and `count`
+    /// photons found within `radius` that reflect `flux` towards the eye.
+    pub fn add_pass(&mut self, direct: Color, count: usize, flux: Color) {
+        self.passes += 1;
+        self.direct.add(direct);
+        if count == 0 {
+            return;
+        }
+
+        let count = count as Float;
+        let shrink = (self.found + PHOTON_ALPHA * count) / (self.found + count);
+        self.found += PHOTON_ALPHA * count;
+        self.flux = (self.flux + flux) * shrink;
+        self.radius2 *= shrink;
+    }
+
+    pub fn estimate(&self) -> Color {
+        let passes = self.passes.max(1) as Float;
+
+        self.direct.total() * (1.0 / passes) + self.flux * (1.0 / (PI * self.radius2 * passes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn photon_at(x: Float, y: Float, z: Float) -> Photon {
+        Photon {
+            position: Tuple4::point(x, y, z),
+            direction: Tuple4::vector(0.0, -1.0, 0.0),
+            power: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn test_gathering_finds_only_photons_inside_the_radius() {
+        let photons = vec![
+            photon_at(0.0, 0.0, 0.0),
+            photon_at(0.15, 0.0, 0.0),
+            photon_at(-0.05, 0.1, -0.1),
+            photon_at(0.3, 0.0, 0.0),
+            photon_at(5.0, 5.0, 5.0),
+        ];
+        let map = PhotonMap::new(photons, 0.1);
+        let mut found = Vec::new();
+
+        map.for_each_within(Tuple4::point(0.0, 0.0, 0.0), 0.2, |p| {
+            found.push(p.position.x)
+        });
+
+        found.sort_by(Float::total_cmp);
+        assert_eq!(map.len(), 5);
+        assert_eq!(found, vec![-0.05, 0.0, 0.15]);
+    }
+
+    #[test]
+    fn test_a_gather_shrinks_its_radius_but_keeps_the_density() {
+        let mut gather = PhotonGather::new(1.0);
+
+        gather.add_pass(Color::new(0.0, 0.0, 0.0), 0, Color::new(0.0, 0.0, 0.0));
+        assert_eq!(gather.radius(), 1.0);
+        for _ in 0..3 {
+            let area = PI * gather.radius() * gather.radius();
+            gather.add_pass(Color::new(0.0, 0.0, 0.0), 10, Color::new(area, area, area));
+        }
+
+        assert!(gather.radius() < 1.0);
+        // Each pass found one unit of flux per unit area, and one pass in
+        // four found nothing.
+        assert!(
+            (gather.estimate().r - 0.75).abs() < 1e-3,
+            "{:?}",
+            gather.estimate()
+        );
+    }
+}
@@ -1,4 +1,10 @@
-use std::io::{self, Write};
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read, Write},
+};
+
+use crate::{canvas::Canvas, color::Color, float::Float};
 
 pub trait RGB {
     fn r(&self) -> u8;
@@ -12,38 +18,75 @@ pub trait PPM<T> {
     fn colors(&self) -> &[T];
 }
 
+/// `Plain` writes every channel as decimal text (P3), `Raw` as one byte
+/// (P6), which is several times smaller and much faster to write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PPMFormat {
+    #[default]
+    Plain,
+    Raw,
+}
+
 pub struct PPMEncoder<'a, T: Write> {
     writer: &'a mut T,
+    format: PPMFormat,
 }
 
 impl<'a, T: Write> PPMEncoder<'a, T> {
-    const PPM_HEADER: &'static str = "P3";
     const PPM_MAX: &'static str = "255";
+    const MAX_LINE: usize = 70;
 
     pub fn new(writer: &'a mut T) -> Self {
-        PPMEncoder { writer }
+        Self::with_format(writer, PPMFormat::Plain)
+    }
+
+    pub fn with_format(writer: &'a mut T, format: PPMFormat) -> Self {
+        PPMEncoder { writer, format }
     }
 
     fn write_header(&mut self, width: usize, height: usize) -> io::Result<()> {
-        let header = format!(
-            "{}\n{} {}\n{}\n",
-            Self::PPM_HEADER,
-            width,
-            height,
-            Self::PPM_MAX
-        );
+        let magic = match self.format {
+            PPMFormat::Plain => "P3",
+            PPMFormat::Raw => "P6",
+        };
+        let header = format!("{}\n{} {}\n{}\n", magic, width, height, Self::PPM_MAX);
         self.writer.write_all(header.as_bytes())
     }
 
+    fn write_raw_data<H: RGB>(&mut self, colors: &[H]) -> io::Result<()> {
+        let bytes: Vec<u8> = colors
+            .iter()
+            .flat_map(|color| [color.r(), color.g(), color.b()])
+            .collect();
+
+        self.writer.write_all(&bytes)
+    }
+
+    /// Writes each row on its own line, wrapping it so no line is longer
+    /// than `MAX_LINE` characters.
     fn write_data<H: RGB>(&mut self, width: usize, colors: &[H]) -> io::Result<()> {
-        for (i, color) in colors.iter().enumerate() {
-            let s = if (i + 1) % width == 0 {
-                format!("{} {} {}\n", color.r(), color.g(), color.b())
-            } else {
-                format!("{} {} {} ", color.r(), color.g(), color.b())
-            };
-
-            self.writer.write_all(s.as_bytes())?;
+        for row in colors.chunks(width.max(1)) {
+            let mut line = String::new();
+            let mut text = String::new();
+            for value in row
+                .iter()
+                .flat_map(|color| [color.r(), color.g(), color.b()])
+            {
+                let value = value.to_string();
+                if !line.is_empty() && line.len() + 1 + value.len() > Self::MAX_LINE {
+                    text.push_str(&line);
+                    text.push('\n');
+                    line.clear();
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(&value);
+            }
+            text.push_str(&line);
+            text.push('\n');
+
+            self.writer.write_all(text.as_bytes())?;
         }
 
         Ok(())
@@ -51,12 +94,131 @@ impl<'a, T: Write> PPMEncoder<'a, T> {
 
     pub fn write<H: RGB, P: PPM<H>>(&mut self, ppm: &P) -> io::Result<()> {
         self.write_header(ppm.width(), ppm.height())?;
-        self.write_data(ppm.width(), ppm.colors())?;
+        match self.format {
+            PPMFormat::Plain => self.write_data(ppm.width(), ppm.colors())?,
+            PPMFormat::Raw => self.write_raw_data(ppm.colors())?,
+        }
 
         Ok(())
     }
 }
 
+#[derive(Debug)]
+pub enum PPMError {
+    Io(io::Error),
+    Format(String),
+}
+
+impl fmt::Display for PPMError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PPMError::Io(e) => write!(f, "could not read image: {}", e),
+            PPMError::Format(message) => write!(f, "invalid PPM: {}", message),
+        }
+    }
+}
+
+impl Error for PPMError {}
+
+impl From<io::Error> for PPMError {
+    fn from(e: io::Error) -> Self {
+        PPMError::Io(e)
+    }
+}
+
+/// Reads plain (P3) and raw (P6) PPM images, including `#` comments in the
+/// header and raw files with two bytes per channel.
+pub struct PPMDecoder<'a, T: Read> {
+    reader: &'a mut T,
+}
+
+impl<'a, T: Read> PPMDecoder<'a, T> {
+    pub fn new(reader: &'a mut T) -> Self {
+        PPMDecoder { reader }
+    }
+
+    pub fn read(&mut self) -> Result<Canvas, PPMError> {
+        let mut bytes = Vec::new();
+        self.reader.read_to_end(&mut bytes)?;
+        let mut pos = 0;
+
+        let magic = next_token(&bytes, &mut pos).unwrap_or_default();
+        if magic != "P3" && magic != "P6" {
+            return Err(PPMError::Format(format!(
+                "unknown magic number '{}'",
+                magic
+            )));
+        }
+        let mut header = |name: &str| -> Result<usize, PPMError> {
+            next_token(&bytes, &mut pos)
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| PPMError::Format(format!("missing {}", name)))
+        };
+        let (width, height, max) = (header("width")?, header("height")?, header("maximum")?);
+        if max == 0 || max > 65535 {
+            return Err(PPMError::Format(format!("maximum {} out of range", max)));
+        }
+
+        let count = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(3))
+            .ok_or_else(|| PPMError::Format(format!("{}x{} is too large", width, height)))?;
+        let values: Vec<usize> = if magic == "P3" {
+            (0..count)
+                .map(|_| next_token(&bytes, &mut pos).and_then(|token| token.parse().ok()))
+                .collect::<Option<_>>()
+                .ok_or_else(|| PPMError::Format("pixel data ends early".to_string()))?
+        } else {
+            // A single whitespace byte separates the header from the raster.
+            let raster = bytes.get(pos + 1..).unwrap_or_default();
+            let size = if max < 256 { 1 } else { 2 };
+            if raster.len() / size < count {
+                return Err(PPMError::Format("pixel data ends early".to_string()));
+            }
+            raster
+                .chunks(size)
+                .take(count)
+                .map(|chunk| chunk.iter().fold(0, |n, &b| n << 8 | b as usize))
+                .collect()
+        };
+
+        let mut canvas = Canvas::new(width, height);
+        let scale = 1.0 / max as Float;
+        for (i, rgb) in values.chunks(3).enumerate() {
+            let color = Color::new(
+                rgb[0] as Float * scale,
+                rgb[1] as Float * scale,
+                rgb[2] as Float * scale,
+            );
+            canvas.put_pixel(color, (i % width, i / width));
+        }
+
+        Ok(canvas)
+    }
+}
+
+/// Next whitespace-separated header or P3 token, skipping comments that run
+/// from `#` to the end of the line. `pos` is left just past the token.
+fn next_token<'b>(bytes: &'b [u8], pos: &mut usize) -> Option<&'b str> {
+    loop {
+        match bytes.get(*pos)? {
+            b'#' => {
+                while bytes.get(*pos).is_some_and(|&b| b != b'\n') {
+                    *pos += 1;
+                }
+            }
+            b if b.is_ascii_whitespace() => *pos += 1,
+            _ => break,
+        }
+    }
+    let start = *pos;
+    while bytes.get(*pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+        *pos += 1;
+    }
+
+    std::str::from_utf8(&bytes[start..*pos]).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,4 +297,122 @@ mod tests {
         assert_eq!(Some("0 0 0 0 0 0 0 128 0 0 0 0 0 0 0"), l.next());
         assert_eq!(Some("0 0 0 0 0 0 0 0 0 0 0 0 0 0 255"), l.next());
     }
+
+    #[test]
+    fn test_to_raw_ppm() {
+        let mut c = Canvas {
+            width: 2,
+            height: 1,
+            colors: vec![Tuple3(0, 0, 0); 2],
+        };
+        c.colors[0] = Tuple3(255, 10, 0);
+        c.colors[1] = Tuple3(1, 2, 3);
+        let mut buffer = Vec::new();
+        let mut encoder = PPMEncoder::with_format(&mut buffer, PPMFormat::Raw);
+
+        encoder.write(&c).unwrap();
+
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend([255, 10, 0, 1, 2, 3]);
+        assert_eq!(expected, buffer);
+    }
+
+    #[test]
+    fn test_splitting_long_lines_in_ppm_files() {
+        let c = Canvas {
+            width: 10,
+            height: 2,
+            colors: vec![Tuple3(255, 204, 153); 20],
+        };
+        let mut buffer = Vec::new();
+        let mut encoder = PPMEncoder::new(&mut buffer);
+
+        encoder.write(&c).unwrap();
+
+        let s = String::from_utf8(buffer).unwrap();
+        let lines: Vec<_> = s.lines().skip(3).collect();
+        let first = "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204";
+        let second = "153 255 204 153 255 204 153 255 204 153 255 204 153";
+        assert_eq!(lines, vec![first, second, first, second]);
+        assert!(s.lines().all(|line| line.len() <= 70));
+    }
+
+    #[test]
+    fn test_ppm_files_end_with_a_newline() {
+        let c = Canvas {
+            width: 5,
+            height: 3,
+            colors: vec![Tuple3(0, 0, 0); 15],
+        };
+        let mut buffer = Vec::new();
+        let mut encoder = PPMEncoder::new(&mut buffer);
+
+        encoder.write(&c).unwrap();
+
+        assert_eq!(buffer.last(), Some(&b'\n'));
+    }
+
+    #[test]
+    fn test_decoding_what_the_encoder_wrote() {
+        let c = Canvas {
+            width: 30,
+            height: 2,
+            colors: (0..60).map(|i| Tuple3(i * 4, 255 - i, 17)).collect(),
+        };
+
+        for format in [PPMFormat::Plain, PPMFormat::Raw] {
+            let mut buffer = Vec::new();
+            PPMEncoder::with_format(&mut buffer, format)
+                .write(&c)
+                .unwrap();
+
+            let decoded = PPMDecoder::new(&mut buffer.as_slice()).read().unwrap();
+
+            assert_eq!(decoded.get_width(), 30);
+            assert_eq!(decoded.get_height(), 2);
+            for (i, color) in c.colors.iter().enumerate() {
+                let pixel = decoded.get_pixel((i % 30, i / 30));
+                assert_eq!(
+                    (pixel.r(), pixel.g(), pixel.b()),
+                    (color.0, color.1, color.2)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_decoding_comments_and_wide_raw_channels() {
+        let mut plain = b"P3\n# made by hand\n1 1 # width and height\n10\n10 5 0\n".as_slice();
+        let mut raw = b"P6 1 1 65535\n".to_vec();
+        raw.extend([0xff, 0xff, 0x80, 0x00, 0x00, 0x00]);
+
+        let plain = PPMDecoder::new(&mut plain).read().unwrap();
+        let raw = PPMDecoder::new(&mut raw.as_slice()).read().unwrap();
+
+        assert_eq!(plain.get_pixel((0, 0)).r, 1.0);
+        assert_eq!(plain.get_pixel((0, 0)).g, 0.5);
+        assert_eq!(raw.get_pixel((0, 0)).r, 1.0);
+        assert_eq!(raw.get_pixel((0, 0)).g(), 128);
+    }
+
+    #[test]
+    fn test_decoding_rejects_broken_files() {
+        let decode = |bytes: &[u8]| PPMDecoder::new(&mut &bytes[..]).read();
+
+        assert!(matches!(
+            decode(b"P5\n1 1\n255\n\0"),
+            Err(PPMError::Format(_))
+        ));
+        assert!(matches!(decode(b"P3\n2 1\n"), Err(PPMError::Format(_))));
+        assert!(matches!(
+            decode(b"P3\n2 1\n255\n1 2 3 4"),
+            Err(PPMError::Format(_))
+        ));
+        assert!(matches!(
+            decode(b"P6\n2 1\n255\n\x01\x02"),
+            Err(PPMError::Format(_))
+        ));
+        let huge = format!("P6\n{} {}\n255\n\0", usize::MAX / 2, 3);
+        assert!(matches!(decode(huge.as_bytes()), Err(PPMError::Format(_))));
+    }
 }
@@ -0,0 +1,294 @@
+use crate::{
+    camera::{Camera, Projection},
+    canvas::Canvas,
+    color::Color,
+    film::Filter,
+    float::{consts::PI, Float},
+    lights::{PointLight, SpotLight},
+    materials::Material,
+    matrix::Matrix4x4,
+    patterns::CheckerPattern,
+    rng::RandomSource,
+    scene::{SceneBuilder, SphereBuilder},
+    sphere::Sphere,
+    tuple::Tuple4,
+    world::{Background, World},
+};
+
+pub type Preset = fn(usize, usize) -> (World, Camera);
+
+pub const PRESETS: [(&str, Preset); 3] = [
+    ("three_spheres", three_spheres),
+    ("depth_of_field", depth_of_field),
+    ("spotlight", spotlight),
+];
+
+fn wall(s: SphereBuilder) -> SphereBuilder {
+    s.scale(10.0, 0.01, 10.0).color(1.0, 0.9, 0.9).specular(0.0)
+}
+
+pub fn three_spheres(hsize: usize, vsize: usize) -> (World, Camera) {
+    let world = SceneBuilder::new()
+        .sphere(wall)
+        .sphere(|s| {
+            wall(s)
+                .rotate_x(PI / 2.0)
+                .rotate_y(-PI / 4.0)
+                .translate(0.0, 0.0, 5.0)
+        })
+        .sphere(|s| {
+            wall(s)
+                .rotate_x(PI / 2.0)
+                .rotate_y(PI / 4.0)
+                .translate(0.0, 0.0, 5.0)
+        })
+        .sphere(|s| {
+            s.translate(-0.5, 1.0, 0.5)
+                .color(0.1, 1.0, 0.5)
+                .diffuse(0.7)
+                .specular(0.3)
+        })
+        .sphere(|s| {
+            s.uniform_scale(0.5)
+                .translate(1.5, 0.5, -0.5)
+                .color(0.5, 1.0, 0.1)
+                .diffuse(0.7)
+                .specular(0.3)
+        })
+        .sphere(|s| {
+            s.uniform_scale(0.33)
+                .translate(-1.5, 0.33, -0.75)
+                .color(1.0, 0.8, 0.1)
+                .diffuse(0.7)
+                .specular(0.3)
+        })
+        .light(PointLight::new(
+            Tuple4::point(-10.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))
+        .build();
+
+    let mut camera = Camera::new(hsize, vsize, Projection::Perspective(PI / 3.0));
+    camera.set_transform(Matrix4x4::view_transform(
+        Tuple4::point(0.0, 1.5, -5.0),
+        Tuple4::point(0.0, 1.0, 0.0),
+        Tuple4::vector(0.0, 1.0, 0.0),
+    ));
+
+    (world, camera)
+}
+
+pub fn depth_of_field(hsize: usize, vsize: usize) -> (World, Camera) {
+    let world = SceneBuilder::new()
+        .sphere(wall)
+        .scatter(7, 5, |rng, s| {
+            let i = rng.next_float();
+            s.uniform_scale(0.4)
+                .translate(2.0 * i - 1.0, 0.4, 3.0 * i - 1.0)
+                .color(0.3 + 0.7 * i, 0.5, 1.0 - 0.7 * i)
+                .diffuse(0.8)
+                .specular(0.4)
+        })
+        .light(PointLight::new(
+            Tuple4::point(-5.0, 8.0, -8.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))
+        .build();
+
+    let mut camera = Camera::new(hsize, vsize, Projection::Perspective(PI / 3.0));
+    camera.set_transform(Matrix4x4::view_transform(
+        Tuple4::point(0.0, 1.5, -4.0),
+        Tuple4::point(0.0, 0.4, 0.5),
+        Tuple4::vector(0.0, 1.0, 0.0),
+    ));
+    camera.set_aperture(0.15);
+    camera.set_focal_distance(4.5);
+    camera.set_lens_samples(8);
+    camera.set_filter(Filter::Mitchell {
+        radius: 2.0,
+        b: 1.0 / 3.0,
+        c: 1.0 / 3.0,
+    });
+
+    (world, camera)
+}
+
+pub fn spotlight(hsize: usize, vsize: usize) -> (World, Camera) {
+    let world = SceneBuilder::new()
+        .sphere(wall)
+        .sphere(|s| {
+            s.translate(0.0, 1.0, 0.0)
+                .color(0.9, 0.2, 0.2)
+                .specular(0.6)
+        })
+        .light(SpotLight::new(
+            Tuple4::point(0.0, 6.0, 0.0),
+            Tuple4::vector(0.0, -1.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            PI / 8.0,
+            PI / 5.0,
+        ))
+        .background(Background::shader(|ray| {
+            let t = 0.5 * (ray.direction.y + 1.0);
+            Color::new(1.0, 1.0, 1.0) * (1.0 - t) + Color::new(0.5, 0.7, 1.0) * t
+        }))
+        .build();
+
+    let mut camera = Camera::new(hsize, vsize, Projection::Perspective(PI / 3.0));
+    camera.set_transform(Matrix4x4::view_transform(
+        Tuple4::point(0.0, 2.5, -6.0),
+        Tuple4::point(0.0, 0.8, 0.0),
+        Tuple4::vector(0.0, 1.0, 0.0),
+    ));
+
+    (world, camera)
+}
+
+/// Places `subject` on a checkered floor in front of a backdrop, lit by key,
+/// fill and rim lights, with the camera framed on the subject alone.
+pub fn studio(subject: Sphere, hsize: usize, vsize: usize) -> (World, Camera) {
+    let bounds = subject.bounds();
+    let center = bounds.center();
+    let floor = bounds.get_min().y;
+    let size = (bounds.get_max() - bounds.get_min()).magnitude();
+
+    let mut framing = World::new();
+    framing.add_object(subject.clone());
+    let mut camera = Camera::new(hsize, vsize, Projection::Perspective(PI / 3.0));
+    camera.frame(&framing, Tuple4::vector(0.0, -0.3, 1.0), 1.3);
+
+    let mut checkers = CheckerPattern::new(Color::new(0.9, 0.9, 0.9), Color::new(0.3, 0.3, 0.3));
+    checkers.set_transform(Matrix4x4::scaling(0.04, 4.0, 0.04));
+    let key = Tuple4::vector(-1.5, 2.0, -2.0) * size;
+    let fill = Tuple4::vector(2.0, 1.0, -1.5) * size;
+    let rim = Tuple4::vector(0.5, 2.0, 2.0) * size;
+
+    let mut world = SceneBuilder::new()
+        .sphere(|s| {
+            s.scale(10.0 * size, 0.01, 10.0 * size)
+                .translate(center.x, floor - 0.01, center.z)
+                .pattern(checkers)
+                .specular(0.0)
+        })
+        .sphere(|s| {
+            wall(s).scale(size, 1.0, size).rotate_x(PI / 2.0).translate(
+                center.x,
+                floor,
+                center.z + 3.0 * size,
+            )
+        })
+        .light(PointLight::new(center + key, Color::new(0.9, 0.9, 0.85)))
+        .light(PointLight::new(center + fill, Color::new(0.3, 0.3, 0.35)))
+        .light(PointLight::new(center + rim, Color::new(0.5, 0.5, 0.5)))
+        .build();
+    world.add_object(subject);
+
+    (world, camera)
+}
+
+/// Renders `subject` in the `studio` once per cell of a `rows` by `columns`
+/// grid and lays the renders out with `Canvas::contact_sheet`. `vary` gets
+/// each copy's material along with the cell's position as fractions from 0
+/// to 1, column first, so two material parameters can be swept against
+/// each other, e.g. metallic across and roughness down.
+pub fn material_sweep<F>(
+    subject: &Sphere,
+    rows: usize,
+    columns: usize,
+    hsize: usize,
+    vsize: usize,
+    vary: F,
+) -> Canvas
+where
+    F: Fn(&mut Material, Float, Float),
+{
+    let fraction = |i: usize, n: usize| {
+        if n > 1 {
+            i as Float / (n - 1) as Float
+        } else {
+            0.0
+        }
+    };
+
+    let mut renders = Vec::with_capacity(rows * columns);
+    for row in 0..rows {
+        for column in 0..columns {
+            let mut material = subject.get_material().clone();
+            vary(
+                &mut material,
+                fraction(column, columns),
+                fraction(row, rows),
+            );
+            let mut cell = subject.clone();
+            cell.set_material(material);
+
+            let (world, camera) = studio(cell, hsize, vsize);
+            renders.push(camera.render(&world));
+        }
+    }
+
+    Canvas::contact_sheet(&renders, columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::materials::Brdf;
+
+    use super::*;
+
+    #[test]
+    fn test_presets_build_non_empty_scenes() {
+        for (name, preset) in PRESETS {
+            let (world, camera) = preset(16, 8);
+
+            assert!(!world.get_objects().is_empty(), "{}", name);
+            assert!(!world.get_lights().is_empty(), "{}", name);
+            assert_eq!(camera.get_hsize(), 16);
+            assert_eq!(camera.get_vsize(), 8);
+        }
+    }
+
+    #[test]
+    fn test_studio_frames_the_subject() {
+        let subject = SphereBuilder::new()
+            .uniform_scale(2.0)
+            .translate(5.0, 3.0, 1.0)
+            .color(0.8, 0.2, 0.2)
+            .build();
+
+        let (world, camera) = studio(subject.clone(), 11, 11);
+
+        assert_eq!(world.get_objects().len(), 3);
+        assert_eq!(world.get_objects()[2], subject);
+        assert_eq!(world.get_lights().len(), 3);
+        let ray = camera.ray_for_pixel(5, 5);
+        let xs = world.intersect(&ray);
+        assert_eq!(xs.hit().unwrap().sphere, &subject);
+    }
+
+    #[test]
+    fn test_a_material_sweep_varies_each_cell() {
+        let subject = SphereBuilder::new().color(0.8, 0.2, 0.2).build();
+
+        let sheet = material_sweep(&subject, 2, 3, 9, 9, |material, u, v| {
+            material.brdf = Brdf::Pbr {
+                metallic: u,
+                roughness: 0.2 + 0.8 * v,
+            };
+        });
+
+        let blanks: Vec<Canvas> = (0..6).map(|_| Canvas::new(9, 9)).collect();
+        let layout = Canvas::contact_sheet(&blanks, 3);
+        assert_eq!(sheet.get_width(), layout.get_width());
+        assert_eq!(sheet.get_height(), layout.get_height());
+        let (step_x, step_y) = ((sheet.get_width() - 2) / 3, (sheet.get_height() - 2) / 2);
+        let centers: Vec<Color> = (0..6)
+            .map(|i| *sheet.get_pixel((6 + (i % 3) * step_x, 6 + (i / 3) * step_y)))
+            .collect();
+        for (i, a) in centers.iter().enumerate() {
+            for b in &centers[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(not(feature = "image"))]
+use crate::ppm::PPMFormat;
+use crate::{
+    camera::{catch_tile, PartialRender},
+    canvas::{Canvas, Dither},
+    capabilities::parallel_map,
+    color::WhitePoint,
+    ppm::PPMEncoder,
+    presets,
+};
+
+/// Extension of the proxy images `RenderJob` writes next to its output.
+#[cfg(feature = "image")]
+const PROXY_EXTENSION: &str = "proxy.png";
+#[cfg(not(feature = "image"))]
+const PROXY_EXTENSION: &str = "proxy.ppm";
+
+#[derive(Debug)]
+pub enum QueueError {
+    Parse { line: usize, message: String },
+    UnknownPreset(String),
+    Render(PartialRender),
+    Panicked(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for QueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueueError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            QueueError::UnknownPreset(name) => write!(f, "unknown preset '{}'", name),
+            QueueError::Render(e) => write!(f, "render failed: {}", e),
+            QueueError::Panicked(message) => write!(f, "job panicked: {}", message),
+            QueueError::Io(e) => write!(f, "could not write image: {}", e),
+        }
+    }
+}
+
+impl Error for QueueError {}
+
+impl From<PartialRender> for QueueError {
+    fn from(e: PartialRender) -> Self {
+        QueueError::Render(e)
+    }
+}
+
+impl From<io::Error> for QueueError {
+    fn from(e: io::Error) -> Self {
+        QueueError::Io(e)
+    }
+}
+
+/// One image to render: a preset scene at a given size, written as PPM.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderJob {
+    pub preset: String,
+    pub width: usize,
+    pub height: usize,
+    pub output: PathBuf,
+    /// Also writes a copy at most this wide next to `output`, for checking
+    /// on renders over slow links. It is a PNG when built with the `image`
+    /// feature and a raw PPM otherwise, see `proxy_path`.
+    pub proxy_width: Option<usize>,
+    /// Stores sRGB-encoded values instead of linear ones, which look right
+    /// in ordinary image viewers.
+    pub srgb: bool,
+    /// Color of the scene's lighting. The render is adapted from it to
+    /// `WhitePoint::D65` before encoding, so a warm-lit scene comes out
+    /// neutral; leave it unset to keep the tint.
+    pub white_point: Option<WhitePoint>,
+    /// Applied to the output and proxy as they are quantized to 8 bits.
+    pub dither: Dither,
+}
+
+impl RenderJob {
+    pub fn run(&self) -> Result<(), QueueError> {
+        let (_, preset) = presets::PRESETS
+            .iter()
+            .find(|(name, _)| *name == self.preset)
+            .ok_or_else(|| QueueError::UnknownPreset(self.preset.clone()))?;
+        let (world, camera) = preset(self.width, self.height);
+        let mut canvas = camera.try_render_with_progress(&world, |_| {})?;
+        if let Some(white) = self.white_point {
+            canvas = canvas.adapt(white, WhitePoint::D65);
+        }
+        if self.srgb {
+            canvas = canvas.to_srgb();
+        }
+
+        let mut writer = BufWriter::new(File::create(&self.output)?);
+        PPMEncoder::new(&mut writer).write(&canvas.dither(self.dither))?;
+        if let Some(width) = self.proxy_width {
+            write_proxy(
+                &canvas.downscale(width).dither(self.dither),
+                &self.proxy_path(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Where the proxy goes: `output` with `.proxy.png` as its extension, or
+    /// `.proxy.ppm` without the `image` feature.
+    pub fn proxy_path(&self) -> PathBuf {
+        self.output.with_extension(PROXY_EXTENSION)
+    }
+}
+
+#[cfg(feature = "image")]
+fn write_proxy(canvas: &Canvas, path: &Path) -> Result<(), QueueError> {
+    image::RgbImage::from(canvas)
+        .save(path)
+        .map_err(io::Error::other)?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "image"))]
+fn write_proxy(canvas: &Canvas, path: &Path) -> Result<(), QueueError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    PPMEncoder::with_format(&mut writer, PPMFormat::Raw).write(canvas)?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueProgress {
+    pub finished: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderQueue {
+    jobs: Vec<RenderJob>,
+    parallel: bool,
+}
+
+impl RenderQueue {
+    pub fn new() -> Self {
+        RenderQueue::default()
+    }
+
+    /// Reads jobs from a YAML list of flat mappings, e.g.
+    ///
+    /// ```text
+    /// - preset: three_spheres
+    ///   width: 800
+    ///   height: 400
+    ///   output: spheres.ppm
+    ///   proxy_width: 200   # optional
+    ///   srgb: true         # optional, defaults to false
+    ///   white_point: A     # optional: A, D50, D65 or "x, y"
+    ///   dither: ordered    # optional: none, ordered or floyd_steinberg
+    /// ```
+    ///
+    /// Only this subset of YAML is understood.
+    pub fn from_yaml(text: &str) -> Result<Self, QueueError> {
+        let mut queue = RenderQueue::new();
+        let mut fields: Option<(usize, Vec<(String, String)>)> = None;
+
+        for (i, raw) in text.lines().enumerate() {
+            let line = raw.split('#').next().unwrap_or("").trim_end();
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = if let Some(rest) = line.strip_prefix("- ") {
+                if let Some((start, job)) = fields.take() {
+                    queue.push(job_from_fields(start, job)?);
+                }
+                fields = Some((i + 1, Vec::new()));
+                rest
+            } else if line.starts_with(' ') && fields.is_some() {
+                line.trim_start()
+            } else {
+                return Err(QueueError::Parse {
+                    line: i + 1,
+                    message: "expected a list item".to_string(),
+                });
+            };
+
+            let (key, value) = entry.split_once(':').ok_or_else(|| QueueError::Parse {
+                line: i + 1,
+                message: "expected 'key: value'".to_string(),
+            })?;
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            if let Some((_, job)) = fields.as_mut() {
+                job.push((key.trim().to_string(), value.to_string()));
+            }
+        }
+        if let Some((start, job)) = fields {
+            queue.push(job_from_fields(start, job)?);
+        }
+
+        Ok(queue)
+    }
+
+    pub fn push(&mut self, job: RenderJob) {
+        self.jobs.push(job);
+    }
+
+    pub fn get_jobs(&self) -> &[RenderJob] {
+        &self.jobs
+    }
+
+    /// Renders jobs on several threads at once instead of one after another.
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    /// Renders every job, calling `progress` as each one finishes. Results
+    /// are returned in queue order; a failed or panicking job does not stop
+    /// the others.
+    pub fn run<F>(&self, progress: F) -> Vec<Result<(), QueueError>>
+    where
+        F: Fn(QueueProgress, &RenderJob) + Sync,
+    {
+        let total = self.jobs.len();
+        let finished = AtomicUsize::new(0);
+        let run_job = |job: &RenderJob| {
+            let result = catch_tile(|| job.run()).unwrap_or_else(|e| Err(QueueError::Panicked(e)));
+            let finished = finished.fetch_add(1, Ordering::SeqCst) + 1;
+            progress(QueueProgress { finished, total }, job);
+            result
+        };
+
+        if !self.parallel {
+            return self.jobs.iter().map(run_job).collect();
+        }
+
+        parallel_map(total, |i| run_job(&self.jobs[i]))
+    }
+}
+
+fn job_from_fields(line: usize, fields: Vec<(String, String)>) -> Result<RenderJob, QueueError> {
+    let get = |key: &str| {
+        fields
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .ok_or_else(|| QueueError::Parse {
+                line,
+                message: format!("job is missing '{}'", key),
+            })
+    };
+    let size = |key: &str| -> Result<usize, QueueError> {
+        let value = get(key)?;
+        match value.parse() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(QueueError::Parse {
+                line,
+                message: format!("invalid {} '{}'", key, value),
+            }),
+        }
+    };
+
+    Ok(RenderJob {
+        preset: get("preset")?.to_string(),
+        width: size("width")?,
+        height: size("height")?,
+        output: PathBuf::from(get("output")?),
+        proxy_width: match get("proxy_width") {
+            Ok(_) => Some(size("proxy_width")?),
+            Err(_) => None,
+        },
+        srgb: match get("srgb") {
+            Ok("true") => true,
+            Ok("false") | Err(_) => false,
+            Ok(value) => {
+                return Err(QueueError::Parse {
+                    line,
+                    message: format!("invalid srgb '{}'", value),
+                })
+            }
+        },
+        white_point: match get("white_point") {
+            Ok(value) => Some(white_point(value).ok_or_else(|| QueueError::Parse {
+                line,
+                message: format!("invalid white_point '{}'", value),
+            })?),
+            Err(_) => None,
+        },
+        dither: match get("dither") {
+            Ok("none") | Err(_) => Dither::None,
+            Ok("ordered") => Dither::Ordered,
+            Ok("floyd_steinberg") => Dither::FloydSteinberg,
+            Ok(value) => {
+                return Err(QueueError::Parse {
+                    line,
+                    message: format!("invalid dither '{}'", value),
+                })
+            }
+        },
+    })
+}
+
+fn white_point(value: &str) -> Option<WhitePoint> {
+    match value {
+        "A" => Some(WhitePoint::A),
+        "D50" => Some(WhitePoint::D50),
+        "D65" => Some(WhitePoint::D65),
+        _ => {
+            let (x, y) = value.split_once(',')?;
+            let (x, y) = (x.trim().parse().ok()?, y.trim().parse().ok()?);
+            if x > 0.0 && y > 0.0 && x + y < 1.0 {
+                Some(WhitePoint { x, y })
+            } else {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, sync::Mutex};
+
+    use super::*;
+
+    #[test]
+    fn test_parsing_a_batch_file() {
+        let yaml = "# overnight renders\n\
+                    - preset: three_spheres\n  width: 8\n  height: 4\n  output: a.ppm\n  proxy_width: 2\n  srgb: true\n  white_point: A\n  dither: floyd_steinberg\n\
+                    \n\
+                    - preset: spotlight\n  output: \"b.ppm\"\n  width: 16\n  height: 8\n  white_point: 0.3, 0.4\n";
+
+        let queue = RenderQueue::from_yaml(yaml).unwrap();
+
+        assert_eq!(
+            queue.get_jobs(),
+            [
+                RenderJob {
+                    preset: "three_spheres".to_string(),
+                    width: 8,
+                    height: 4,
+                    output: PathBuf::from("a.ppm"),
+                    proxy_width: Some(2),
+                    srgb: true,
+                    white_point: Some(WhitePoint::A),
+                    dither: Dither::FloydSteinberg,
+                },
+                RenderJob {
+                    preset: "spotlight".to_string(),
+                    width: 16,
+                    height: 8,
+                    output: PathBuf::from("b.ppm"),
+                    proxy_width: None,
+                    srgb: false,
+                    white_point: Some(WhitePoint { x: 0.3, y: 0.4 }),
+                    dither: Dither::None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_malformed_batch_files_report_the_line() {
+        let missing = RenderQueue::from_yaml("- preset: spotlight\n  width: 8\n  height: 4\n");
+        let stray = RenderQueue::from_yaml("preset: spotlight\n");
+        let size = RenderQueue::from_yaml("- preset: a\n  width: 0\n  height: 4\n  output: a\n");
+        let white = RenderQueue::from_yaml(
+            "- preset: a\n  width: 8\n  height: 4\n  output: a\n  white_point: D99\n",
+        );
+
+        assert!(matches!(missing, Err(QueueError::Parse { line: 1, .. })));
+        assert!(matches!(stray, Err(QueueError::Parse { line: 1, .. })));
+        assert!(matches!(size, Err(QueueError::Parse { line: 1, .. })));
+        assert!(matches!(white, Err(QueueError::Parse { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_running_a_queue_in_parallel() {
+        let dir = env::temp_dir().join(format!("ray-tracer-queue-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut queue = RenderQueue::new();
+        for (i, preset) in ["three_spheres", "missing", "spotlight"].iter().enumerate() {
+            queue.push(RenderJob {
+                preset: preset.to_string(),
+                width: 4,
+                height: 2,
+                output: dir.join(format!("{}.ppm", i)),
+                proxy_width: Some(2),
+                srgb: false,
+                white_point: None,
+                dither: Dither::Ordered,
+            });
+        }
+        queue.set_parallel(true);
+        let seen = Mutex::new(Vec::new());
+
+        let results = queue.run(|progress, _| seen.lock().unwrap().push(progress.finished));
+
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(QueueError::UnknownPreset(_))));
+        assert!(results[2].is_ok());
+        assert!(fs::read_to_string(dir.join("2.ppm"))
+            .unwrap()
+            .starts_with("P3"));
+        let proxy = fs::read(queue.get_jobs()[2].proxy_path()).unwrap();
+        if cfg!(feature = "image") {
+            assert!(proxy.starts_with(b"\x89PNG"));
+        } else {
+            assert!(proxy.starts_with(b"P6\n2 1\n"));
+        }
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort();
+        assert_eq!(seen, [1, 2, 3]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_a_panicking_job_does_not_stop_the_queue() {
+        let dir = env::temp_dir().join(format!("ray-tracer-panic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut queue = RenderQueue::new();
+        for (i, width) in [usize::MAX, 4].into_iter().enumerate() {
+            queue.push(RenderJob {
+                preset: "three_spheres".to_string(),
+                width,
+                height: 2,
+                output: dir.join(format!("{}.ppm", i)),
+                proxy_width: None,
+                srgb: false,
+                white_point: None,
+                dither: Dither::None,
+            });
+        }
+        queue.set_parallel(true);
+        let seen = Mutex::new(0);
+
+        let results = queue.run(|_, _| *seen.lock().unwrap() += 1);
+
+        assert!(matches!(&results[0], Err(QueueError::Panicked(m)) if m == "capacity overflow"));
+        assert!(results[1].is_ok());
+        assert_eq!(*seen.lock().unwrap(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,5 +1,25 @@
-use crate::{matrix::Matrix4x4, tuple::Tuple4};
+use std::{error::Error, fmt};
 
+use crate::{float::Float, matrix::Matrix4x4, tuple::Tuple4};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RayError {
+    DirectionNotAVector,
+    ZeroLengthDirection,
+}
+
+impl fmt::Display for RayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RayError::DirectionNotAVector => write!(f, "ray direction must be a vector (w = 0)"),
+            RayError::ZeroLengthDirection => write!(f, "ray direction must not have zero length"),
+        }
+    }
+}
+
+impl Error for RayError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Ray {
     pub origin: Tuple4,
     pub direction: Tuple4,
@@ -10,11 +30,32 @@ impl Ray {
         Ray { origin, direction }
     }
 
-    pub fn position(&self, t: f64) -> Tuple4 {
+    pub fn try_new(origin: Tuple4, direction: Tuple4) -> Result<Ray, RayError> {
+        if !direction.is_vector() {
+            return Err(RayError::DirectionNotAVector);
+        }
+        if direction.magnitude() == 0.0 {
+            return Err(RayError::ZeroLengthDirection);
+        }
+
+        Ok(Ray::new(origin, direction))
+    }
+
+    pub fn spawn(point: Tuple4, normal: Tuple4, direction: Tuple4, epsilon: Float) -> Ray {
+        let offset = if direction.dot(&normal) >= 0.0 {
+            normal * epsilon
+        } else {
+            normal * -epsilon
+        };
+
+        Ray::new(point + offset, direction)
+    }
+
+    pub fn position(&self, t: Float) -> Tuple4 {
         self.origin + self.direction * t
     }
 
-    pub fn transform(&self, m: Matrix4x4) -> Ray {
+    pub fn transform(&self, m: &Matrix4x4) -> Ray {
         let new_origin = m * self.origin;
         let new_direction = m * self.direction;
 
@@ -44,7 +85,7 @@ mod tests {
         let r = Ray::new(Tuple4::point(1.0, 2.0, 3.0), Tuple4::vector(0.0, 1.0, 0.0));
         let m = Matrix4x4::translation(3.0, 4.0, 5.0);
 
-        let r2 = r.transform(m);
+        let r2 = r.transform(&m);
 
         assert_eq!(r2.origin, Tuple4::point(4.0, 6.0, 8.0));
         assert_eq!(r2.direction, Tuple4::vector(0.0, 1.0, 0.0));
@@ -55,9 +96,45 @@ mod tests {
         let r = Ray::new(Tuple4::point(1.0, 2.0, 3.0), Tuple4::vector(0.0, 1.0, 0.0));
         let m = Matrix4x4::scaling(2.0, 3.0, 4.0);
 
-        let r2 = r.transform(m);
+        let r2 = r.transform(&m);
 
         assert_eq!(r2.origin, Tuple4::point(2.0, 6.0, 12.0));
         assert_eq!(r2.direction, Tuple4::vector(0.0, 3.0, 0.0));
     }
+
+    #[test]
+    fn test_spawned_ray_is_offset_to_the_side_it_travels_towards() {
+        let point = Tuple4::point(0.0, 0.0, 0.0);
+        let normal = Tuple4::vector(0.0, 1.0, 0.0);
+
+        let above = Ray::spawn(point, normal, Tuple4::vector(1.0, 1.0, 0.0), 0.01);
+        let below = Ray::spawn(point, normal, Tuple4::vector(1.0, -1.0, 0.0), 0.01);
+
+        assert_eq!(above.origin, Tuple4::point(0.0, 0.01, 0.0));
+        assert_eq!(below.origin, Tuple4::point(0.0, -0.01, 0.0));
+    }
+
+    #[test]
+    fn test_checked_constructor_accepts_a_valid_direction() {
+        let origin = Tuple4::point(1.0, 2.0, 3.0);
+        let direction = Tuple4::vector(0.0, 1.0, 0.0);
+
+        let r = Ray::try_new(origin, direction);
+
+        assert_eq!(r, Ok(Ray::new(origin, direction)));
+    }
+
+    #[test]
+    fn test_checked_constructor_rejects_a_point_as_direction() {
+        let r = Ray::try_new(Tuple4::point(1.0, 2.0, 3.0), Tuple4::point(0.0, 1.0, 0.0));
+
+        assert_eq!(r, Err(RayError::DirectionNotAVector));
+    }
+
+    #[test]
+    fn test_checked_constructor_rejects_a_zero_length_direction() {
+        let r = Ray::try_new(Tuple4::point(1.0, 2.0, 3.0), Tuple4::vector(0.0, 0.0, 0.0));
+
+        assert_eq!(r, Err(RayError::ZeroLengthDirection));
+    }
 }
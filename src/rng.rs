@@ -0,0 +1,89 @@
+use crate::float::Float;
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Uniform random numbers for stochastic sampling. Samplers are generic over
+/// it, so any generator can drive them as long as it is seeded the same way
+/// every render.
+pub trait RandomSource {
+    fn next_u64(&mut self) -> u64;
+
+    /// Uniform in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `[0, 1)` at the precision of `Float`; the same as
+    /// `next_f64` in the default build.
+    fn next_float(&mut self) -> Float {
+        let bits = Float::MANTISSA_DIGITS;
+        (self.next_u64() >> (64 - bits)) as Float / (1u64 << bits) as Float
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Generator for one independent stream (a pixel, a shading point) of a
+    /// render seeded with `seed`. Seed zero leaves the stream as is.
+    pub fn for_stream(seed: u64, stream: u64) -> Self {
+        Rng::new(stream ^ seed.wrapping_mul(GOLDEN_GAMMA))
+    }
+}
+
+impl RandomSource for Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_produces_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn test_different_seeds_produce_different_sequences() {
+        let mut a = Rng::new(1);
+        let mut b = Rng::new(2);
+
+        assert_ne!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn test_streams_depend_on_the_render_seed() {
+        assert_eq!(Rng::for_stream(0, 5), Rng::new(5));
+        assert_eq!(Rng::for_stream(3, 5), Rng::for_stream(3, 5));
+        assert_ne!(Rng::for_stream(3, 5), Rng::for_stream(4, 5));
+    }
+
+    #[test]
+    fn test_next_f64_is_in_unit_interval() {
+        let mut rng = Rng::new(7);
+
+        for _ in 0..1000 {
+            let n = rng.next_f64();
+            assert!((0.0..1.0).contains(&n));
+        }
+    }
+}
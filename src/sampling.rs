@@ -0,0 +1,190 @@
+use crate::{
+    float::{consts::PI, Float},
+    tuple::Tuple4,
+};
+
+/// Orthonormal basis with `w` along a unit normal, for turning directions
+/// sampled around the z axis into world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onb {
+    pub u: Tuple4,
+    pub v: Tuple4,
+    pub w: Tuple4,
+}
+
+impl Onb {
+    pub fn from_normal(normal: Tuple4) -> Self {
+        let up = if normal.x.abs() > 0.9 {
+            Tuple4::vector(0.0, 1.0, 0.0)
+        } else {
+            Tuple4::vector(1.0, 0.0, 0.0)
+        };
+        let u = normal.cross(up).normalize();
+        let v = normal.cross(u);
+
+        Onb { u, v, w: normal }
+    }
+
+    pub fn local(&self, x: Float, y: Float, z: Float) -> Tuple4 {
+        self.u * x + self.v * y + self.w * z
+    }
+}
+
+/// Direction on the hemisphere around `normal`, distributed by the cosine of
+/// its angle to the normal.
+pub fn cosine_hemisphere(normal: Tuple4, u1: Float, u2: Float) -> Tuple4 {
+    let r = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+
+    Onb::from_normal(normal)
+        .local(r * phi.cos(), r * phi.sin(), (1.0 - u1).sqrt())
+        .normalize()
+}
+
+/// Unit vector with every direction equally likely.
+pub fn uniform_sphere(u1: Float, u2: Float) -> Tuple4 {
+    let z = 1.0 - 2.0 * u1;
+    let r = (1.0 - z * z).sqrt();
+    let phi = 2.0 * PI * u2;
+
+    Tuple4::vector(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Point spread evenly over a disk of `radius` around the origin.
+pub fn uniform_disk(radius: Float, u1: Float, u2: Float) -> (Float, Float) {
+    let r = radius * u1.sqrt();
+    let theta = 2.0 * PI * u2;
+
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// Point spread evenly over a regular polygon with `sides` corners at
+/// `radius` from the origin, the first at angle `rotation`.
+pub fn uniform_polygon(
+    sides: usize,
+    rotation: Float,
+    radius: Float,
+    u1: Float,
+    u2: Float,
+) -> (Float, Float) {
+    let sides = sides.max(3);
+    let wedge = ((u1 * sides as Float) as usize).min(sides - 1);
+    let u1 = u1 * sides as Float - wedge as Float;
+    let corner = |i: usize| {
+        let theta = rotation + 2.0 * PI * i as Float / sides as Float;
+        Tuple4::point(radius * theta.cos(), radius * theta.sin(), 0.0)
+    };
+    let p = uniform_triangle(
+        Tuple4::point(0.0, 0.0, 0.0),
+        corner(wedge),
+        corner(wedge + 1),
+        u1,
+        u2,
+    );
+
+    (p.x, p.y)
+}
+
+/// Point spread evenly over the triangle `a`, `b`, `c`.
+pub fn uniform_triangle(a: Tuple4, b: Tuple4, c: Tuple4, u1: Float, u2: Float) -> Tuple4 {
+    let s = u1.sqrt();
+
+    a + (b - a) * (s * (1.0 - u2)) + (c - a) * (s * u2)
+}
+
+/// Microfacet normal around `normal` drawn from the GGX distribution with
+/// width `alpha`, the square of the roughness used by `Brdf::Pbr`. Its
+/// density is the distribution times the cosine to the normal.
+pub fn ggx_half_vector(normal: Tuple4, alpha: Float, u1: Float, u2: Float) -> Tuple4 {
+    let alpha2 = alpha * alpha;
+    let cos_theta = ((1.0 - u1) / (1.0 + (alpha2 - 1.0) * u1)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+
+    Onb::from_normal(normal)
+        .local(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+        .normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float::EPSILON,
+        rng::{RandomSource, Rng},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_a_basis_is_orthonormal() {
+        let normal = Tuple4::vector(1.0, 2.0, -2.0).normalize();
+
+        let onb = Onb::from_normal(normal);
+
+        assert!(onb.u.dot(&onb.v).abs() < EPSILON);
+        assert!(onb.u.dot(&onb.w).abs() < EPSILON);
+        assert!(onb.v.dot(&onb.w).abs() < EPSILON);
+        assert!((onb.u.magnitude() - 1.0).abs() < EPSILON);
+        assert!((onb.v.magnitude() - 1.0).abs() < EPSILON);
+        assert_eq!(onb.local(0.0, 0.0, 1.0), normal);
+    }
+
+    #[test]
+    fn test_cosine_samples_average_two_thirds() {
+        let normal = Tuple4::vector(0.0, 1.0, 0.0);
+        let mut rng = Rng::new(7);
+        let n = 20000;
+
+        let mean = (0..n)
+            .map(|_| cosine_hemisphere(normal, rng.next_float(), rng.next_float()).dot(&normal))
+            .inspect(|&cos| assert!(cos >= 0.0))
+            .sum::<Float>()
+            / n as Float;
+
+        assert!((mean - 2.0 / 3.0).abs() < 0.01, "{}", mean);
+    }
+
+    #[test]
+    fn test_uniform_samples_stay_in_their_shapes() {
+        let (a, b, c) = (
+            Tuple4::point(0.0, 0.0, 0.0),
+            Tuple4::point(2.0, 0.0, 0.0),
+            Tuple4::point(0.0, 2.0, 0.0),
+        );
+        let mut rng = Rng::new(3);
+
+        for _ in 0..1000 {
+            let (u1, u2) = (rng.next_float(), rng.next_float());
+            let (x, y) = uniform_disk(0.5, u1, u2);
+            let p = uniform_triangle(a, b, c, u1, u2);
+
+            assert!((uniform_sphere(u1, u2).magnitude() - 1.0).abs() < 1e-6);
+            assert!(x.hypot(y) <= 0.5);
+            assert!(p.x >= 0.0 && p.y >= 0.0 && p.x + p.y <= 2.0 + EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_polygon_samples_stay_inside_the_blades() {
+        let mut rng = Rng::new(5);
+        let apothem = (PI / 6.0).cos();
+
+        for _ in 0..1000 {
+            let (x, y) = uniform_polygon(6, 0.0, 1.0, rng.next_float(), rng.next_float());
+
+            let theta = y.atan2(x).rem_euclid(PI / 3.0) - PI / 6.0;
+            assert!(x.hypot(y) * theta.cos() <= apothem + 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_ggx_half_vectors_narrow_with_the_roughness() {
+        let normal = Tuple4::vector(0.0, 0.0, 1.0);
+
+        let smooth = ggx_half_vector(normal, 0.01, 0.5, 0.25);
+        let rough = ggx_half_vector(normal, 1.0, 0.5, 0.25);
+
+        assert!(smooth.dot(&normal) > 0.999);
+        assert!((rough.dot(&normal) - Float::sqrt(0.5)).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{
+    color::Color,
+    float::Float,
+    lights::Light,
+    materials::{Brdf, Material, ShadowCatcher},
+    matrix::Matrix4x4,
+    patterns::Pattern,
+    rng::Rng,
+    settings::RenderSettings,
+    sphere::Sphere,
+    world::{Background, World},
+};
+
+/// Fluent construction of a [`World`]. Transforms on a sphere apply in the
+/// order they are written, so `.scale(..).translate(..)` scales first.
+#[derive(Default)]
+pub struct SceneBuilder {
+    world: World,
+}
+
+impl SceneBuilder {
+    pub fn new() -> Self {
+        SceneBuilder::default()
+    }
+
+    pub fn sphere<F: FnOnce(SphereBuilder) -> SphereBuilder>(mut self, f: F) -> Self {
+        self.world.add_object(f(SphereBuilder::new()).build());
+        self
+    }
+
+    /// Adds `count` spheres shaped by `f`, drawing any randomness from a
+    /// generator seeded with `seed` so the same seed always yields the same scene.
+    pub fn scatter<F>(mut self, seed: u64, count: usize, mut f: F) -> Self
+    where
+        F: FnMut(&mut Rng, SphereBuilder) -> SphereBuilder,
+    {
+        let mut rng = Rng::new(seed);
+        for _ in 0..count {
+            self.world
+                .add_object(f(&mut rng, SphereBuilder::new()).build());
+        }
+        self
+    }
+
+    pub fn light<L: Into<Light>>(mut self, light: L) -> Self {
+        self.world.add_light(light);
+        self
+    }
+
+    pub fn background(mut self, background: Background) -> Self {
+        self.world.set_background(background);
+        self
+    }
+
+    pub fn settings(mut self, settings: RenderSettings) -> Self {
+        self.world.set_settings(settings);
+        self
+    }
+
+    pub fn build(self) -> World {
+        self.world
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SphereBuilder {
+    transform: Matrix4x4,
+    material: Material,
+}
+
+impl SphereBuilder {
+    pub fn new() -> Self {
+        SphereBuilder {
+            transform: Matrix4x4::identity(),
+            material: Material::default(),
+        }
+    }
+
+    pub fn transform(mut self, m: Matrix4x4) -> Self {
+        self.transform = m * self.transform;
+        self
+    }
+
+    pub fn translate(self, x: Float, y: Float, z: Float) -> Self {
+        self.transform(Matrix4x4::translation(x, y, z))
+    }
+
+    pub fn scale(self, x: Float, y: Float, z: Float) -> Self {
+        self.transform(Matrix4x4::scaling(x, y, z))
+    }
+
+    pub fn uniform_scale(self, s: Float) -> Self {
+        self.scale(s, s, s)
+    }
+
+    pub fn rotate_x(self, r: Float) -> Self {
+        self.transform(Matrix4x4::rotation_x(r))
+    }
+
+    pub fn rotate_y(self, r: Float) -> Self {
+        self.transform(Matrix4x4::rotation_y(r))
+    }
+
+    pub fn rotate_z(self, r: Float) -> Self {
+        self.transform(Matrix4x4::rotation_z(r))
+    }
+
+    pub fn material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn pattern<P: Pattern + 'static>(mut self, pattern: P) -> Self {
+        self.material.pattern = Some(Box::new(pattern));
+        self
+    }
+
+    pub fn color(mut self, r: Float, g: Float, b: Float) -> Self {
+        self.material.color = Color::new(r, g, b);
+        self
+    }
+
+    pub fn ambient(mut self, ambient: Float) -> Self {
+        self.material.ambient = ambient;
+        self
+    }
+
+    pub fn diffuse(mut self, diffuse: Float) -> Self {
+        self.material.diffuse = diffuse;
+        self
+    }
+
+    pub fn specular(mut self, specular: Float) -> Self {
+        self.material.specular = specular;
+        self
+    }
+
+    pub fn shininess(mut self, shininess: Float) -> Self {
+        self.material.shininess = shininess;
+        self
+    }
+
+    pub fn reflective(mut self, reflective: Float) -> Self {
+        self.material.reflective = reflective;
+        self
+    }
+
+    pub fn roughness(mut self, roughness: Float) -> Self {
+        self.material.roughness = roughness;
+        self
+    }
+
+    pub fn pbr(mut self, metallic: Float, roughness: Float) -> Self {
+        self.material.brdf = Brdf::Pbr {
+            metallic,
+            roughness,
+        };
+        self
+    }
+
+    pub fn shadow_catcher(mut self, ao_distance: Float) -> Self {
+        self.material.shadow_catcher = Some(ShadowCatcher { ao_distance });
+        self
+    }
+
+    pub fn build(self) -> Sphere {
+        let mut sphere = Sphere::new();
+        sphere.set_transform(self.transform);
+        sphere.set_material(self.material);
+
+        sphere
+    }
+}
+
+impl Default for SphereBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lights::PointLight, rng::RandomSource, tuple::Tuple4};
+
+    use super::*;
+
+    #[test]
+    fn test_building_an_empty_scene() {
+        let world = SceneBuilder::new().build();
+
+        assert!(world.get_objects().is_empty());
+        assert!(world.get_lights().is_empty());
+    }
+
+    #[test]
+    fn test_sphere_transforms_apply_in_written_order() {
+        let sphere = SphereBuilder::new()
+            .scale(2.0, 2.0, 2.0)
+            .translate(1.0, 0.0, 0.0)
+            .build();
+
+        assert_eq!(
+            *sphere.get_transform(),
+            Matrix4x4::translation(1.0, 0.0, 0.0) * Matrix4x4::scaling(2.0, 2.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn test_sphere_material_shorthands() {
+        let sphere = SphereBuilder::new()
+            .color(0.8, 1.0, 0.6)
+            .diffuse(0.7)
+            .specular(0.2)
+            .build();
+
+        assert_eq!(
+            *sphere.get_material(),
+            Material {
+                color: Color::new(0.8, 1.0, 0.6),
+                diffuse: 0.7,
+                specular: 0.2,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_building_a_scene_with_spheres_and_lights() {
+        let world = SceneBuilder::new()
+            .sphere(|s| s.color(1.0, 0.0, 0.0))
+            .sphere(|s| s.uniform_scale(0.5))
+            .light(PointLight::new(
+                Tuple4::point(-10.0, 10.0, -10.0),
+                Color::new(1.0, 1.0, 1.0),
+            ))
+            .build();
+
+        assert_eq!(world.get_objects().len(), 2);
+        assert_eq!(world.get_lights().len(), 1);
+        assert_eq!(
+            *world.get_objects()[1].get_transform(),
+            Matrix4x4::scaling(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn test_scatter_is_reproducible_for_a_seed() {
+        let scatter = |seed| {
+            SceneBuilder::new()
+                .scatter(seed, 5, |rng, s| {
+                    s.uniform_scale(0.2)
+                        .translate(rng.next_float(), 0.0, rng.next_float())
+                })
+                .build()
+        };
+
+        let a = scatter(3);
+        let b = scatter(3);
+        let c = scatter(4);
+
+        assert_eq!(a.get_objects().len(), 5);
+        assert_eq!(a.get_objects(), b.get_objects());
+        assert_ne!(a.get_objects(), c.get_objects());
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    capabilities::capabilities,
+    float::Float,
+    ray::Ray,
+    rng::{RandomSource, Rng},
+    sampling::uniform_sphere,
+    tuple::Tuple4,
+    world::{Background, World},
+};
+
+/// Rays timed by `RenderSettings::auto` to measure the cost of one
+/// intersection test in the scene.
+const AUTO_PROBES: usize = 256;
+/// Shadow samples, reflection depth, glossy samples and environment
+/// samples `RenderSettings::auto` picks from, best first.
+const AUTO_LEVELS: [(usize, usize, usize, usize); 5] = [
+    (16, 5, 16, 16),
+    (8, 4, 8, 8),
+    (4, 3, 4, 8),
+    (2, 2, 2, 4),
+    (1, 1, 1, 4),
+];
+
+/// How `World::color_at` turns a ray into a color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Integrator {
+    /// Direct lighting plus recursive mirror reflections.
+    Whitted,
+    /// Monte Carlo path tracing, averaging `samples` paths per camera ray
+    /// with up to `max_bounces` indirect bounces each.
+    PathTracer { samples: usize, max_bounces: usize },
+    /// Ambient, diffuse and specular light with one hard shadow ray per
+    /// light and a single perfect mirror bounce. Nothing is sampled, so the
+    /// image is free of noise and quick to refresh.
+    Preview,
+    /// Stochastic progressive photon mapping for caustics, which point
+    /// lights seen through mirrors never produce in the other integrators.
+    /// Every pass, each light emits `photons` photons that bounce up to
+    /// `reflection_depth` times; each camera ray gathers those within a
+    /// radius that starts at `radius` and shrinks as photons are found.
+    PhotonMapping {
+        passes: usize,
+        photons: usize,
+        radius: Float,
+    },
+}
+
+/// Quality knobs for secondary effects. Lowering them trades accuracy for
+/// speed, which keeps draft renders of heavy scenes interactive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    /// Shadow rays cast towards each light with a non-zero radius.
+    pub shadow_samples: usize,
+    /// When non-zero and smaller than the number of lights, direct lighting
+    /// weighs this many randomly drawn lights and shades only one of them,
+    /// instead of tracing shadow rays to every light.
+    pub light_candidates: usize,
+    /// How many times a reflected ray may bounce before it is cut off.
+    pub reflection_depth: usize,
+    /// Reflected rays that travel further than this see the background.
+    pub reflection_distance: Float,
+    /// Rays averaged for the first reflection off a rough surface.
+    pub glossy_samples: usize,
+    /// Directions sampled from an environment map background to light
+    /// diffuse surfaces; zero turns image-based lighting off.
+    pub environment_samples: usize,
+    pub integrator: Integrator,
+    /// Bounces a path tracer path always survives before Russian roulette
+    /// may end it.
+    pub roulette_depth: usize,
+    /// Largest channel value a single Monte Carlo sample may contribute.
+    /// Lowering it trades a little energy for fewer bright specks.
+    pub firefly_clamp: Float,
+    /// Mixed into every sampling seed. Renders with the same seed are
+    /// identical; changing it gives a different noise pattern.
+    pub seed: u64,
+}
+
+impl RenderSettings {
+    pub fn draft() -> Self {
+        RenderSettings {
+            shadow_samples: 1,
+            light_candidates: 0,
+            reflection_depth: 1,
+            reflection_distance: 20.0,
+            glossy_samples: 1,
+            environment_samples: 4,
+            integrator: Integrator::Whitted,
+            roulette_depth: 1,
+            firefly_clamp: 10.0,
+            seed: 0,
+        }
+    }
+
+    /// `draft` with the noise-free `Integrator::Preview`, for images that
+    /// are redrawn while a scene is being edited.
+    pub fn preview() -> Self {
+        RenderSettings {
+            reflection_depth: 1,
+            integrator: Integrator::Preview,
+            ..RenderSettings::draft()
+        }
+    }
+
+    /// Whitted settings expected to shade `camera_rays` camera rays, e.g.
+    /// a frame's pixels times its samples per pixel, within `target_time`.
+    /// The cost of a ray through `world` is timed on a few probe rays,
+    /// shared out over the worker threads, and scaled by what the settings
+    /// imply for its lights, reflective and rough surfaces and environment
+    /// lighting. The best quality level that fits is chosen, down to
+    /// roughly `draft`. Keeps the world's seed.
+    pub fn auto(world: &World, camera_rays: usize, target_time: Duration) -> Self {
+        let mut rng = Rng::new(0);
+        let bounds = world.bounds();
+        let (center, reach) = if bounds.is_empty() {
+            (Tuple4::point(0.0, 0.0, 0.0), 1.0)
+        } else {
+            let reach = (bounds.get_max() - bounds.get_min()).magnitude();
+            (bounds.center(), reach.max(1.0))
+        };
+        let probes: Vec<Ray> = (0..AUTO_PROBES)
+            .map(|_| {
+                let from = uniform_sphere(rng.next_float(), rng.next_float());
+                Ray::new(center + from * reach, from * -1.0)
+            })
+            .collect();
+
+        // The first intersection builds the hierarchy, which is not part of
+        // the per-ray cost.
+        black_box(world.intersect(&probes[0]).len());
+        let start = Instant::now();
+        for ray in &probes {
+            black_box(world.intersect(ray).len());
+        }
+        let per_test =
+            start.elapsed().as_secs_f64() / AUTO_PROBES as f64 / capabilities().threads as f64;
+
+        RenderSettings::auto_for(world, per_test, camera_rays, target_time)
+    }
+
+    fn auto_for(world: &World, per_test: f64, camera_rays: usize, target_time: Duration) -> Self {
+        let lights = world.get_lights().len();
+        let levels = AUTO_LEVELS.map(|(shadow, depth, glossy, environment)| RenderSettings {
+            shadow_samples: shadow,
+            light_candidates: if lights > 8 { 4 } else { 0 },
+            reflection_depth: depth,
+            glossy_samples: glossy,
+            environment_samples: environment,
+            seed: world.get_settings().seed,
+            ..RenderSettings::default()
+        });
+        let budget = target_time.as_secs_f64() / camera_rays.max(1) as f64;
+
+        levels
+            .into_iter()
+            .find(|settings| settings.tests_per_ray(world) * per_test <= budget)
+            .unwrap_or(levels[levels.len() - 1])
+    }
+
+    /// Rough number of intersection tests one camera ray costs in `world`.
+    fn tests_per_ray(&self, world: &World) -> f64 {
+        let lights = world.get_lights();
+        let shadow_rays = |radius: Float| {
+            if radius > 0.0 {
+                self.shadow_samples as f64
+            } else {
+                1.0
+            }
+        };
+        let all: f64 = lights.iter().map(|l| shadow_rays(l.radius())).sum();
+        let shadows = if self.light_candidates > 0 && self.light_candidates < lights.len() {
+            all / lights.len() as f64
+        } else {
+            all
+        };
+        let environment = match world.get_background() {
+            Background::Environment(_) => self.environment_samples as f64,
+            _ => 0.0,
+        };
+
+        let objects = world.get_objects();
+        let (mut reflective, mut rough) = (0.0, 0.0);
+        for material in objects.iter().map(|o| o.get_material()) {
+            if material.reflective > 0.0 {
+                reflective += 1.0 / objects.len() as f64;
+                if material.roughness > 0.0 {
+                    rough += 1.0 / objects.len() as f64;
+                }
+            }
+        }
+        let deeper: f64 = (1..self.reflection_depth)
+            .map(|k| reflective.powi(k as i32))
+            .sum();
+        let first = reflective + rough * (self.glossy_samples as f64 - 1.0);
+        let hits = 1.0 + first * (1.0 + deeper);
+
+        hits * (1.0 + shadows + environment)
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            shadow_samples: 16,
+            light_candidates: 0,
+            reflection_depth: 5,
+            reflection_distance: Float::INFINITY,
+            glossy_samples: 16,
+            environment_samples: 16,
+            integrator: Integrator::Whitted,
+            roulette_depth: 3,
+            firefly_clamp: Float::INFINITY,
+            seed: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{color::Color, lights::PointLight, scene::SceneBuilder};
+
+    use super::*;
+
+    fn world(reflective: Float, roughness: Float) -> World {
+        let mut light = PointLight::new(Tuple4::point(0.0, 5.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        light.set_radius(1.0);
+
+        SceneBuilder::new()
+            .sphere(|s| s.reflective(reflective).roughness(roughness))
+            .sphere(|s| {
+                s.translate(3.0, 0.0, 0.0)
+                    .reflective(reflective)
+                    .roughness(roughness)
+            })
+            .light(light)
+            .build()
+    }
+
+    #[test]
+    fn test_auto_settings_follow_the_budget() {
+        let world = world(0.0, 0.0);
+
+        let generous = RenderSettings::auto(&world, 1000, Duration::from_secs(3600));
+        let none = RenderSettings::auto(&world, 1000, Duration::ZERO);
+
+        assert_eq!(generous.shadow_samples, 16);
+        assert_eq!(generous.reflection_depth, 5);
+        assert_eq!(none.shadow_samples, 1);
+        assert_eq!(none.reflection_depth, 1);
+    }
+
+    #[test]
+    fn test_auto_settings_cut_back_for_glossy_mirrors() {
+        let budget = Duration::from_millis(20);
+
+        let matte = RenderSettings::auto_for(&world(0.0, 0.0), 1e-9, 1_000_000, budget);
+        let mirrors = RenderSettings::auto_for(&world(1.0, 0.5), 1e-9, 1_000_000, budget);
+
+        assert_eq!((matte.shadow_samples, matte.reflection_depth), (16, 5));
+        assert_eq!((mirrors.shadow_samples, mirrors.reflection_depth), (2, 2));
+        assert_eq!(mirrors.glossy_samples, 2);
+    }
+
+    #[test]
+    fn test_auto_settings_scale_with_the_camera_rays() {
+        let budget = Duration::from_millis(20);
+        let mirrors = world(1.0, 0.5);
+
+        let small = RenderSettings::auto_for(&mirrors, 1e-9, 10_000, budget);
+        let large = RenderSettings::auto_for(&mirrors, 1e-9, 100_000_000, budget);
+
+        assert_eq!((small.shadow_samples, small.reflection_depth), (16, 5));
+        assert_eq!((large.shadow_samples, large.reflection_depth), (1, 1));
+    }
+}
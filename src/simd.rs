@@ -0,0 +1,221 @@
+//! Tuple and matrix-vector kernels. With the `simd` feature on x86_64 they
+//! run on SSE2, which every x86_64 CPU has: two `f64` lanes at a time, or a
+//! whole tuple at once in `f32` builds. Otherwise they are plain scalar code.
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", not(feature = "f32")))]
+pub const WIDTH: usize = 2;
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", feature = "f32"))]
+pub const WIDTH: usize = 4;
+
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+pub const WIDTH: usize = 1;
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", not(feature = "f32")))]
+mod sse2 {
+    //! SAFETY: SSE2 is part of the x86_64 baseline, so its intrinsics are
+    //! always available on this target.
+
+    use std::arch::x86_64::{
+        __m128d, _mm_add_pd, _mm_mul_pd, _mm_set1_pd, _mm_set_pd, _mm_storeu_pd, _mm_sub_pd,
+    };
+
+    use crate::tuple::Tuple4;
+
+    fn halves(t: &Tuple4) -> (__m128d, __m128d) {
+        unsafe { (_mm_set_pd(t.y, t.x), _mm_set_pd(t.w, t.z)) }
+    }
+
+    fn join(lo: __m128d, hi: __m128d) -> Tuple4 {
+        let mut data = [0.0; 4];
+        unsafe {
+            _mm_storeu_pd(data.as_mut_ptr(), lo);
+            _mm_storeu_pd(data[2..].as_mut_ptr(), hi);
+        }
+
+        Tuple4::new(data[0], data[1], data[2], data[3])
+    }
+
+    pub fn add(a: &Tuple4, b: &Tuple4) -> Tuple4 {
+        let ((a0, a1), (b0, b1)) = (halves(a), halves(b));
+        unsafe { join(_mm_add_pd(a0, b0), _mm_add_pd(a1, b1)) }
+    }
+
+    pub fn sub(a: &Tuple4, b: &Tuple4) -> Tuple4 {
+        let ((a0, a1), (b0, b1)) = (halves(a), halves(b));
+        unsafe { join(_mm_sub_pd(a0, b0), _mm_sub_pd(a1, b1)) }
+    }
+
+    pub fn dot(a: &Tuple4, b: &Tuple4) -> f64 {
+        let ((a0, a1), (b0, b1)) = (halves(a), halves(b));
+        let mut lanes = [0.0; 2];
+        unsafe {
+            let sum = _mm_add_pd(_mm_mul_pd(a0, b0), _mm_mul_pd(a1, b1));
+            _mm_storeu_pd(lanes.as_mut_ptr(), sum);
+        }
+
+        lanes[0] + lanes[1]
+    }
+
+    pub fn cross(a: &Tuple4, b: &Tuple4) -> Tuple4 {
+        unsafe {
+            let yz = _mm_mul_pd(_mm_set_pd(a.z, a.y), _mm_set_pd(b.x, b.z));
+            let zx = _mm_mul_pd(_mm_set_pd(a.x, a.z), _mm_set_pd(b.z, b.y));
+            let xy = _mm_set_pd(0.0, a.x * b.y - a.y * b.x);
+
+            join(_mm_sub_pd(yz, zx), xy)
+        }
+    }
+
+    /// Sums the matrix columns scaled by the tuple's components, filling
+    /// rows 0-1 and 2-3 of the result in parallel.
+    pub fn transform(m: &[f64; 16], t: &Tuple4) -> Tuple4 {
+        unsafe {
+            let mut lo = _mm_set1_pd(0.0);
+            let mut hi = _mm_set1_pd(0.0);
+            for (col, n) in [t.x, t.y, t.z, t.w].into_iter().enumerate() {
+                let n = _mm_set1_pd(n);
+                lo = _mm_add_pd(lo, _mm_mul_pd(_mm_set_pd(m[4 + col], m[col]), n));
+                hi = _mm_add_pd(hi, _mm_mul_pd(_mm_set_pd(m[12 + col], m[8 + col]), n));
+            }
+
+            join(lo, hi)
+        }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", not(feature = "f32")))]
+pub use sse2::{add, cross, dot, sub, transform};
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", feature = "f32"))]
+mod sse {
+    //! SAFETY: SSE is part of the x86_64 baseline, so its intrinsics are
+    //! always available on this target.
+
+    use std::arch::x86_64::{
+        __m128, _mm_add_ps, _mm_mul_ps, _mm_set1_ps, _mm_set_ps, _mm_setzero_ps, _mm_storeu_ps,
+        _mm_sub_ps,
+    };
+
+    use crate::tuple::Tuple4;
+
+    fn load(t: &Tuple4) -> __m128 {
+        unsafe { _mm_set_ps(t.w, t.z, t.y, t.x) }
+    }
+
+    fn store(v: __m128) -> Tuple4 {
+        let mut data = [0.0; 4];
+        unsafe { _mm_storeu_ps(data.as_mut_ptr(), v) };
+
+        Tuple4::new(data[0], data[1], data[2], data[3])
+    }
+
+    pub fn add(a: &Tuple4, b: &Tuple4) -> Tuple4 {
+        unsafe { store(_mm_add_ps(load(a), load(b))) }
+    }
+
+    pub fn sub(a: &Tuple4, b: &Tuple4) -> Tuple4 {
+        unsafe { store(_mm_sub_ps(load(a), load(b))) }
+    }
+
+    pub fn dot(a: &Tuple4, b: &Tuple4) -> f32 {
+        let mut lanes = [0.0; 4];
+        unsafe { _mm_storeu_ps(lanes.as_mut_ptr(), _mm_mul_ps(load(a), load(b))) };
+
+        (lanes[0] + lanes[2]) + (lanes[1] + lanes[3])
+    }
+
+    pub fn cross(a: &Tuple4, b: &Tuple4) -> Tuple4 {
+        unsafe {
+            let (a_yzx, a_zxy) = (
+                _mm_set_ps(0.0, a.x, a.z, a.y),
+                _mm_set_ps(0.0, a.y, a.x, a.z),
+            );
+            let (b_yzx, b_zxy) = (
+                _mm_set_ps(0.0, b.x, b.z, b.y),
+                _mm_set_ps(0.0, b.y, b.x, b.z),
+            );
+
+            store(_mm_sub_ps(
+                _mm_mul_ps(a_yzx, b_zxy),
+                _mm_mul_ps(a_zxy, b_yzx),
+            ))
+        }
+    }
+
+    /// Sums the matrix columns scaled by the tuple's components, filling all
+    /// four rows of the result in parallel.
+    pub fn transform(m: &[f32; 16], t: &Tuple4) -> Tuple4 {
+        unsafe {
+            let mut rows = _mm_setzero_ps();
+            for (col, n) in [t.x, t.y, t.z, t.w].into_iter().enumerate() {
+                let column = _mm_set_ps(m[12 + col], m[8 + col], m[4 + col], m[col]);
+                rows = _mm_add_ps(rows, _mm_mul_ps(column, _mm_set1_ps(n)));
+            }
+
+            store(rows)
+        }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", feature = "f32"))]
+pub use sse::{add, cross, dot, sub, transform};
+
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+mod scalar {
+    use crate::{float::Float, tuple::Tuple4};
+
+    pub fn add(a: &Tuple4, b: &Tuple4) -> Tuple4 {
+        Tuple4::new(a.x + b.x, a.y + b.y, a.z + b.z, a.w + b.w)
+    }
+
+    pub fn sub(a: &Tuple4, b: &Tuple4) -> Tuple4 {
+        Tuple4::new(a.x - b.x, a.y - b.y, a.z - b.z, a.w - b.w)
+    }
+
+    pub fn dot(a: &Tuple4, b: &Tuple4) -> Float {
+        a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w
+    }
+
+    pub fn cross(a: &Tuple4, b: &Tuple4) -> Tuple4 {
+        Tuple4::vector(
+            a.y * b.z - a.z * b.y,
+            a.z * b.x - a.x * b.z,
+            a.x * b.y - a.y * b.x,
+        )
+    }
+
+    pub fn transform(m: &[Float; 16], t: &Tuple4) -> Tuple4 {
+        let row = |i: usize| m[i] * t.x + m[i + 1] * t.y + m[i + 2] * t.z + m[i + 3] * t.w;
+
+        Tuple4::new(row(0), row(4), row(8), row(12))
+    }
+}
+
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+pub use scalar::{add, cross, dot, sub, transform};
+
+#[cfg(test)]
+mod tests {
+    use crate::tuple::Tuple4;
+
+    use super::*;
+
+    #[test]
+    fn test_kernels_match_the_scalar_formulas() {
+        let a = Tuple4::new(1.0, -2.0, 3.5, 0.0);
+        let b = Tuple4::new(4.0, 0.5, -1.0, 1.0);
+        let m = [
+            1.0, 2.0, 3.0, 4.0, 2.0, 4.0, 4.0, 2.0, 8.0, 6.0, 4.0, 1.0, 0.0, 0.0, 0.0, 1.0,
+        ];
+
+        assert_eq!(add(&a, &b), Tuple4::new(5.0, -1.5, 2.5, 1.0));
+        assert_eq!(sub(&a, &b), Tuple4::new(-3.0, -2.5, 4.5, -1.0));
+        assert_eq!(dot(&a, &b), -0.5);
+        assert_eq!(cross(&a, &b), Tuple4::vector(0.25, 15.0, 8.5));
+        assert_eq!(
+            transform(&m, &Tuple4::new(1.0, 2.0, 3.0, 1.0)),
+            Tuple4::new(18.0, 24.0, 33.0, 1.0)
+        );
+    }
+}
@@ -1,17 +1,32 @@
 use std::ops::Index;
 
+use crate::bounds::BoundingBox;
+use crate::float::Float;
 use crate::materials::Material;
 use crate::matrix::Matrix4x4;
 use crate::ray::Ray;
 use crate::tuple::Tuple4;
 
+#[cfg(not(feature = "f32"))]
+pub const EPSILON: Float = 1e-5;
+
+/// Thin, stretched spheres like the preset walls put `f32` hit points off
+/// by more than the `f64` offset, which shows up as shadow acne.
+#[cfg(feature = "f32")]
+pub const EPSILON: Float = 1e-4;
+
+const CLOSEST_POINT_STEPS: usize = 64;
+
 #[allow(dead_code)]
-#[derive(PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Sphere {
     origin: Tuple4,
-    radius: f64,
+    radius: Float,
     transform: Matrix4x4,
+    inverse: Matrix4x4,
+    normal_matrix: Matrix4x4,
     material: Material,
+    epsilon: Option<Float>,
 }
 
 impl Sphere {
@@ -19,22 +34,46 @@ impl Sphere {
         let origin = Tuple4::point(0.0, 0.0, 0.0);
         let radius = 1.0;
         let transform = Matrix4x4::identity();
+        let inverse = Matrix4x4::identity();
         let material = Material::default();
 
         Sphere {
             origin,
             radius,
             transform,
+            inverse,
+            normal_matrix: Matrix4x4::identity(),
             material,
+            epsilon: None,
+        }
+    }
+
+    pub fn intersect(&self, ray: &Ray) -> SphereIntersections<'_> {
+        let mut xs = SphereIntersections::default();
+        self.intersect_into(ray, &mut xs);
+
+        xs
+    }
+
+    /// Same as `intersect`, but refills `xs` in place so a loop casting many
+    /// rays reuses one allocation.
+    pub fn intersect_into<'a>(&'a self, ray: &Ray, xs: &mut SphereIntersections<'a>) {
+        xs.clear();
+        self.append_intersections(ray, xs);
+    }
+
+    /// Adds the ray's intersections to the ones already in `xs`.
+    pub(crate) fn append_intersections<'a>(&'a self, ray: &Ray, xs: &mut SphereIntersections<'a>) {
+        if let Some((t1, t2)) = self.intersection_distances(ray) {
+            xs.intersections.push(SphereIntersection::new(t1, self));
+            xs.intersections.push(SphereIntersection::new(t2, self));
         }
     }
 
-    pub fn intersect(&self, ray: &Ray) -> SphereIntersections {
-        let ray_transformation_matrix = self
-            .transform
-            .inverse()
-            .expect("Can't inverse singular matrix");
-        let transformed_ray = ray.transform(ray_transformation_matrix);
+    /// Distances along `ray` to where it enters and leaves the sphere, for
+    /// queries that do not need an intersection list.
+    pub fn intersection_distances(&self, ray: &Ray) -> Option<(Float, Float)> {
+        let transformed_ray = ray.transform(&self.inverse);
 
         let sphere_to_ray = transformed_ray.origin - self.origin;
         let a = transformed_ray.direction.dot(&transformed_ray.direction);
@@ -42,31 +81,81 @@ impl Sphere {
         let c = sphere_to_ray.dot(&sphere_to_ray) - 1.0;
         let discriminant = b * b - 4.0 * a * c;
 
-        let intersections = if discriminant < 0.0 {
-            Vec::new()
-        } else {
-            let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
-            let i1 = SphereIntersection::new(t1, self);
-            let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
-            let i2 = SphereIntersection::new(t2, self);
-            vec![i1, i2]
-        };
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
+        let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
 
-        SphereIntersections::new(intersections)
+        Some((t1, t2))
     }
 
     pub fn set_transform(&mut self, m: Matrix4x4) {
+        self.inverse = m.inverse().expect("Can't inverse singular matrix");
+        self.normal_matrix = m.normal_matrix().expect("Can't inverse singular matrix");
         self.transform = m;
     }
 
+    pub fn get_transform(&self) -> &Matrix4x4 {
+        &self.transform
+    }
+
+    pub fn get_inverse_transform(&self) -> &Matrix4x4 {
+        &self.inverse
+    }
+
+    pub fn get_normal_matrix(&self) -> &Matrix4x4 {
+        &self.normal_matrix
+    }
+
+    /// Tight world-space box around the sphere. Transforming the unit box
+    /// would also work but leaves slack around rotated ellipsoids.
+    pub fn bounds(&self) -> BoundingBox {
+        let m = &self.transform;
+        let center = m * Tuple4::point(0.0, 0.0, 0.0);
+        let half = |row: usize| (0..3).map(|x| m.get(row, x).powi(2)).sum::<Float>().sqrt();
+        let half = Tuple4::vector(half(0), half(1), half(2));
+
+        BoundingBox::new(center - half, center + half)
+    }
+
     pub fn normal_at(&self, p: Tuple4) -> Tuple4 {
-        let object_point = self.transform.inverse().unwrap() * p;
+        let object_point = self.inverse * p;
         let object_normal = object_point - Tuple4::point(0.0, 0.0, 0.0);
-        let mut world_normal = self.transform.inverse().unwrap().transpose() * object_normal;
-        world_normal.w = 0.0;
+        let world_normal = self.normal_matrix * object_normal;
         world_normal.normalize()
     }
 
+    /// Point on the surface nearest to `p`. Exact for spheres scaled the
+    /// same on every axis; stretched ones start from the same guess and are
+    /// refined by projected gradient steps.
+    pub fn closest_point(&self, p: Tuple4) -> Tuple4 {
+        let local = self.inverse * p;
+        let mut u = Tuple4::vector(local.x, local.y, local.z);
+        u = if u.magnitude() < EPSILON {
+            Tuple4::vector(0.0, 1.0, 0.0)
+        } else {
+            u.normalize()
+        };
+
+        let m = &self.transform;
+        let scale: Float = (0..3)
+            .flat_map(|y| (0..3).map(move |x| m.get(y, x).powi(2)))
+            .sum();
+        for _ in 0..CLOSEST_POINT_STEPS {
+            let offset = m * Tuple4::point(u.x, u.y, u.z) - p;
+            let gradient = m.transpose() * offset;
+            let next = u - Tuple4::vector(gradient.x, gradient.y, gradient.z) * (1.0 / scale);
+            if next.magnitude() < EPSILON {
+                break;
+            }
+            u = next.normalize();
+        }
+
+        m * Tuple4::point(u.x, u.y, u.z)
+    }
+
     pub fn set_material(&mut self, m: Material) {
         self.material = m;
     }
@@ -74,6 +163,25 @@ impl Sphere {
     pub fn get_material(&self) -> &Material {
         &self.material
     }
+
+    pub fn set_epsilon(&mut self, epsilon: Float) {
+        self.epsilon = Some(epsilon);
+    }
+
+    pub fn get_epsilon(&self) -> Float {
+        self.epsilon.unwrap_or_else(|| EPSILON * self.max_scale())
+    }
+
+    fn max_scale(&self) -> Float {
+        (0..3)
+            .map(|x| {
+                (0..3)
+                    .map(|y| self.transform.get(y, x).powi(2))
+                    .sum::<Float>()
+                    .sqrt()
+            })
+            .fold(0.0, Float::max)
+    }
 }
 
 impl Default for Sphere {
@@ -83,22 +191,80 @@ impl Default for Sphere {
 }
 
 pub struct SphereIntersection<'a> {
-    pub t: f64,
+    pub t: Float,
     pub sphere: &'a Sphere,
 }
 
 impl SphereIntersection<'_> {
-    pub fn new(t: f64, sphere: &Sphere) -> SphereIntersection {
+    pub fn new(t: Float, sphere: &Sphere) -> SphereIntersection<'_> {
         SphereIntersection { t, sphere }
     }
 }
 
+pub struct PreparedComputations<'a> {
+    pub t: Float,
+    pub object: &'a Sphere,
+    pub point: Tuple4,
+    pub over_point: Tuple4,
+    pub eyev: Tuple4,
+    pub normalv: Tuple4,
+    pub geometric_normalv: Tuple4,
+    pub inside: bool,
+}
+
+impl<'a> SphereIntersection<'a> {
+    pub fn prepare_computations(&self, ray: &Ray) -> PreparedComputations<'a> {
+        let point = ray.position(self.t);
+        let eyev = -1.0 * ray.direction;
+        let mut geometric_normalv = self.sphere.normal_at(point);
+        let mut normalv = match &self.sphere.get_material().normal_map {
+            Some(map) => map.perturb(self.sphere, point, geometric_normalv),
+            None => geometric_normalv,
+        };
+        let inside = geometric_normalv.dot(&eyev) < 0.0;
+        if inside {
+            normalv = -1.0 * normalv;
+            geometric_normalv = -1.0 * geometric_normalv;
+        }
+        let over_point = point + geometric_normalv * self.sphere.get_epsilon();
+
+        PreparedComputations {
+            t: self.t,
+            object: self.sphere,
+            point,
+            over_point,
+            eyev,
+            normalv,
+            geometric_normalv,
+            inside,
+        }
+    }
+}
+
+impl PreparedComputations<'_> {
+    /// Secondary ray from the hit, nudged off the surface on the side
+    /// `direction` heads towards so it does not hit the same spot again.
+    pub fn spawn_ray(&self, direction: Tuple4) -> Ray {
+        Ray::spawn(
+            self.point,
+            self.geometric_normalv,
+            direction,
+            self.object.get_epsilon(),
+        )
+    }
+
+    pub fn reflected_ray(&self) -> Ray {
+        self.spawn_ray((-1.0 * self.eyev).reflect(self.normalv))
+    }
+}
+
+#[derive(Default)]
 pub struct SphereIntersections<'a> {
     intersections: Vec<SphereIntersection<'a>>,
 }
 
 impl SphereIntersections<'_> {
-    pub fn new(intersections: Vec<SphereIntersection<'_>>) -> SphereIntersections {
+    pub fn new(intersections: Vec<SphereIntersection<'_>>) -> SphereIntersections<'_> {
         SphereIntersections { intersections }
     }
 
@@ -110,7 +276,17 @@ impl SphereIntersections<'_> {
         self.intersections.len() == 0
     }
 
-    pub fn hit(&self) -> Option<&SphereIntersection> {
+    /// Empties the buffer but keeps its allocation.
+    pub fn clear(&mut self) {
+        self.intersections.clear();
+    }
+
+    pub(crate) fn sort(&mut self) {
+        self.intersections
+            .sort_by(|a, b| a.t.partial_cmp(&b.t).expect("Tried to compare to NaN"));
+    }
+
+    pub fn hit(&self) -> Option<&SphereIntersection<'_>> {
         self.intersections
             .iter()
             .filter(|x| x.t >= 0.0)
@@ -118,6 +294,15 @@ impl SphereIntersections<'_> {
     }
 }
 
+impl<'a> IntoIterator for SphereIntersections<'a> {
+    type Item = SphereIntersection<'a>;
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.intersections.into_iter()
+    }
+}
+
 impl<'a> Index<usize> for SphereIntersections<'a> {
     type Output = SphereIntersection<'a>;
 
@@ -128,14 +313,25 @@ impl<'a> Index<usize> for SphereIntersections<'a> {
 
 #[cfg(test)]
 mod tests {
-    use std::f64::consts::PI;
     use std::ptr;
+    use std::sync::Arc;
+
+    use crate::{
+        canvas::Canvas,
+        color::Color,
+        float::{
+            consts::{FRAC_1_SQRT_2, PI},
+            Float,
+        },
+        normal_map::NormalMap,
+        texture::{Texture, UvMapping},
+    };
 
     use super::*;
 
-    const EPSILON: f64 = 1e-6;
+    const EPSILON: Float = 1e-6;
 
-    fn equal(a: f64, b: f64) -> bool {
+    fn equal(a: Float, b: Float) -> bool {
         (a - b).abs() < EPSILON
     }
 
@@ -275,6 +471,37 @@ mod tests {
         assert_eq!(s.transform, t);
     }
 
+    #[test]
+    fn test_changing_spheres_transformation_updates_cached_inverse() {
+        let mut s = Sphere::new();
+        let t = Matrix4x4::translation(2.0, 3.0, 4.0);
+
+        s.set_transform(t);
+
+        assert_eq!(s.inverse, t.inverse().unwrap());
+    }
+
+    #[test]
+    fn test_changing_spheres_transformation_updates_cached_normal_matrix() {
+        let mut s = Sphere::new();
+        let t = Matrix4x4::translation(2.0, 3.0, 4.0) * Matrix4x4::scaling(1.0, 0.5, 1.0);
+
+        s.set_transform(t);
+
+        assert_eq!(*s.get_normal_matrix(), t.normal_matrix().unwrap());
+    }
+
+    #[test]
+    fn test_bounds_of_a_transformed_sphere() {
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4x4::translation(1.0, -3.0, 5.0) * Matrix4x4::scaling(0.5, 2.0, 4.0));
+
+        let bounds = s.bounds();
+
+        assert_eq!(bounds.get_min(), Tuple4::point(0.5, -5.0, 1.0));
+        assert_eq!(bounds.get_max(), Tuple4::point(1.5, -1.0, 9.0));
+    }
+
     #[test]
     fn test_intersecting_a_scaled_sphere_with_a_ray() {
         let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
@@ -330,19 +557,15 @@ mod tests {
         let s = Sphere::new();
 
         let n = s.normal_at(Tuple4::point(
-            3.0_f64.sqrt() / 3.0,
-            3.0_f64.sqrt() / 3.0,
-            3.0_f64.sqrt() / 3.0,
+            Float::sqrt(3.0) / 3.0,
+            Float::sqrt(3.0) / 3.0,
+            Float::sqrt(3.0) / 3.0,
         ));
 
-        assert_eq!(
-            n,
-            Tuple4::vector(
-                3.0_f64.sqrt() / 3.0,
-                3.0_f64.sqrt() / 3.0,
-                3.0_f64.sqrt() / 3.0,
-            )
-        );
+        assert!(equal(n.x, Float::sqrt(3.0) / 3.0));
+        assert!(equal(n.y, Float::sqrt(3.0) / 3.0));
+        assert!(equal(n.z, Float::sqrt(3.0) / 3.0));
+        assert!(n.is_vector());
     }
 
     #[test]
@@ -350,17 +573,17 @@ mod tests {
         let s = Sphere::new();
 
         let n = s.normal_at(Tuple4::point(
-            3.0_f64.sqrt() / 3.0,
-            3.0_f64.sqrt() / 3.0,
-            3.0_f64.sqrt() / 3.0,
+            Float::sqrt(3.0) / 3.0,
+            Float::sqrt(3.0) / 3.0,
+            Float::sqrt(3.0) / 3.0,
         ));
 
         assert_eq!(
             n,
             Tuple4::vector(
-                3.0_f64.sqrt() / 3.0,
-                3.0_f64.sqrt() / 3.0,
-                3.0_f64.sqrt() / 3.0,
+                Float::sqrt(3.0) / 3.0,
+                Float::sqrt(3.0) / 3.0,
+                Float::sqrt(3.0) / 3.0,
             )
             .normalize()
         );
@@ -371,11 +594,11 @@ mod tests {
         let mut s = Sphere::new();
         s.set_transform(Matrix4x4::translation(0.0, 1.0, 0.0));
 
-        let n = s.normal_at(Tuple4::point(0.0, 1.70711, -0.70711));
+        let n = s.normal_at(Tuple4::point(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
 
         assert_eq!(n.x, 0.0);
-        assert!(equal(n.y, 0.707106));
-        assert!(equal(n.z, -0.707106));
+        assert!(equal(n.y, FRAC_1_SQRT_2));
+        assert!(equal(n.z, -FRAC_1_SQRT_2));
         assert!(n.is_vector());
     }
 
@@ -387,11 +610,11 @@ mod tests {
 
         let n = s.normal_at(Tuple4::point(
             0.0,
-            2.0_f64.sqrt() / 2.0,
-            -(2.0_f64.sqrt()) / 2.0,
+            Float::sqrt(2.0) / 2.0,
+            -(Float::sqrt(2.0)) / 2.0,
         ));
 
-        assert_eq!(n.x, 0.0);
+        assert!(equal(n.x, 0.0));
         assert!(equal(n.y, 0.970142));
         assert!(equal(n.z, -0.242535));
         assert!(n.is_vector());
@@ -407,11 +630,174 @@ mod tests {
     #[test]
     fn test_sphere_may_be_assigned_a_material() {
         let mut s = Sphere::new();
-        let mut m = Material::default();
-        m.ambient = 1.0;
+        let m = Material {
+            ambient: 1.0,
+            ..Default::default()
+        };
 
         s.set_material(m.clone());
 
         assert_eq!(s.material, m);
     }
+
+    #[test]
+    fn test_unit_sphere_uses_the_base_epsilon() {
+        let s = Sphere::new();
+
+        assert_eq!(s.get_epsilon(), super::EPSILON);
+    }
+
+    #[test]
+    fn test_epsilon_follows_the_largest_scale_factor() {
+        let mut tiny = Sphere::new();
+        tiny.set_transform(Matrix4x4::scaling(0.001, 0.001, 0.001));
+        let mut huge = Sphere::new();
+        huge.set_transform(
+            Matrix4x4::rotation_y(PI / 3.0) * Matrix4x4::scaling(10.0, 10000.0, 1.0),
+        );
+
+        assert!((tiny.get_epsilon() - super::EPSILON * 0.001).abs() < 1e-15);
+        assert!((huge.get_epsilon() - super::EPSILON * 10000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_epsilon_can_be_overridden() {
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4x4::scaling(100.0, 100.0, 100.0));
+
+        s.set_epsilon(0.5);
+
+        assert_eq!(s.get_epsilon(), 0.5);
+    }
+
+    #[test]
+    fn test_precomputing_the_state_of_an_intersection() {
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let shape = Sphere::new();
+        let i = SphereIntersection::new(4.0, &shape);
+
+        let comps = i.prepare_computations(&r);
+
+        assert_eq!(comps.t, i.t);
+        assert!(ptr::eq(comps.object, &shape));
+        assert_eq!(comps.point, Tuple4::point(0.0, 0.0, -1.0));
+        assert_eq!(comps.eyev, Tuple4::vector(0.0, 0.0, -1.0));
+        assert_eq!(comps.normalv, Tuple4::vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_the_hit_when_an_intersection_occurs_on_the_outside() {
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let shape = Sphere::new();
+        let i = SphereIntersection::new(4.0, &shape);
+
+        let comps = i.prepare_computations(&r);
+
+        assert!(!comps.inside);
+    }
+
+    #[test]
+    fn test_the_hit_when_an_intersection_occurs_on_the_inside() {
+        let r = Ray::new(Tuple4::point(0.0, 0.0, 0.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let shape = Sphere::new();
+        let i = SphereIntersection::new(1.0, &shape);
+
+        let comps = i.prepare_computations(&r);
+
+        assert_eq!(comps.point, Tuple4::point(0.0, 0.0, 1.0));
+        assert_eq!(comps.eyev, Tuple4::vector(0.0, 0.0, -1.0));
+        assert!(comps.inside);
+        assert_eq!(comps.normalv, Tuple4::vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_the_hit_should_offset_the_point() {
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let mut shape = Sphere::new();
+        shape.set_transform(Matrix4x4::translation(0.0, 0.0, 1.0));
+        let i = SphereIntersection::new(5.0, &shape);
+
+        let comps = i.prepare_computations(&r);
+
+        assert!(comps.over_point.z < -super::EPSILON / 2.0);
+        assert!(comps.point.z > comps.over_point.z);
+    }
+
+    #[test]
+    fn test_reflected_ray_starts_at_the_over_point() {
+        let r = Ray::new(
+            Tuple4::point(0.0, 2.0, -1.0),
+            Tuple4::vector(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+        );
+        let shape = Sphere::new();
+        let xs = shape.intersect(&r);
+
+        let comps = xs.hit().unwrap().prepare_computations(&r);
+        let reflected = comps.reflected_ray();
+
+        assert_eq!(reflected.origin, comps.over_point);
+        assert!(equal(reflected.direction.x, 0.0));
+        assert!(equal(reflected.direction.y, FRAC_1_SQRT_2));
+        assert!(equal(reflected.direction.z, FRAC_1_SQRT_2));
+    }
+
+    #[test]
+    fn test_spawned_rays_leave_from_the_side_they_travel_towards() {
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let shape = Sphere::new();
+        let xs = shape.intersect(&r);
+
+        let comps = xs.hit().unwrap().prepare_computations(&r);
+        let outward = comps.spawn_ray(Tuple4::vector(0.0, 1.0, -1.0));
+        let inward = comps.spawn_ray(Tuple4::vector(0.0, 1.0, 1.0));
+
+        assert_eq!(outward.origin, comps.over_point);
+        assert!(inward.origin.z > comps.point.z);
+        assert!(equal(inward.direction.z, 1.0));
+    }
+
+    #[test]
+    fn test_normal_maps_change_the_shading_normal_but_not_the_over_point() {
+        let mut canvas = Canvas::new(1, 1);
+        canvas.put_pixel(Color::new(1.0, 0.5, 0.5), (0, 0));
+        let mut shape = Sphere::new();
+        shape.set_material(Material {
+            normal_map: Some(NormalMap::Tangent {
+                texture: Arc::new(Texture::from_canvas(&canvas)),
+                mapping: UvMapping::Spherical,
+            }),
+            ..Default::default()
+        });
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let xs = shape.intersect(&r);
+
+        let comps = xs.hit().unwrap().prepare_computations(&r);
+
+        assert!(equal(comps.normalv.x, 1.0));
+        assert!(equal(comps.over_point.x, 0.0));
+        assert!(comps.over_point.z < -1.0);
+    }
+
+    #[test]
+    fn test_closest_point_on_a_sphere() {
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4x4::translation(1.0, 0.0, 0.0) * Matrix4x4::scaling(2.0, 2.0, 2.0));
+
+        let p = s.closest_point(Tuple4::point(1.0, 5.0, 0.0));
+
+        assert!((p - Tuple4::point(1.0, 2.0, 0.0)).magnitude() < EPSILON);
+    }
+
+    #[test]
+    fn test_closest_point_on_a_stretched_sphere() {
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4x4::rotation_z(PI / 6.0) * Matrix4x4::scaling(3.0, 1.0, 1.0));
+        let target = Tuple4::point(2.0, 3.0, 0.5);
+
+        let p = s.closest_point(target);
+
+        let along_normal = (target - p).normalize().dot(&s.normal_at(p));
+        assert!((along_normal - 1.0).abs() < EPSILON);
+        assert!(((s.inverse * p - Tuple4::point(0.0, 0.0, 0.0)).magnitude() - 1.0).abs() < EPSILON);
+    }
 }
@@ -0,0 +1,694 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read},
+    sync::Arc,
+};
+
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    float::{consts::PI, Float},
+    matrix::Matrix4x4,
+    patterns::Pattern,
+    ppm::{PPMDecoder, PPMError},
+    tuple::Tuple4,
+};
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(io::Error),
+    Format(String),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Io(e) => write!(f, "could not read texture: {}", e),
+            TextureError::Format(msg) => write!(f, "invalid texture image: {}", msg),
+        }
+    }
+}
+
+impl Error for TextureError {}
+
+impl From<io::Error> for TextureError {
+    fn from(e: io::Error) -> Self {
+        TextureError::Io(e)
+    }
+}
+
+impl From<PPMError> for TextureError {
+    fn from(e: PPMError) -> Self {
+        match e {
+            PPMError::Io(e) => TextureError::Io(e),
+            PPMError::Format(msg) => TextureError::Format(msg),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Texture {
+    pub fn from_canvas(canvas: &Canvas) -> Self {
+        let (width, height) = (canvas.get_width(), canvas.get_height());
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(*canvas.get_pixel((x, y)));
+            }
+        }
+
+        Texture {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Loads a plain (P3) or raw (P6) PPM image, see `PPMDecoder`.
+    pub fn load_ppm<R: Read>(mut reader: R) -> Result<Self, TextureError> {
+        let canvas = PPMDecoder::new(&mut reader).read()?;
+        if canvas.get_width() == 0 || canvas.get_height() == 0 {
+            return Err(TextureError::Format("empty image".to_string()));
+        }
+
+        Ok(Texture::from_canvas(&canvas))
+    }
+
+    /// Loads a Radiance RGBE (`.hdr`) image, flat or run-length encoded.
+    pub fn load_hdr<R: Read>(mut reader: R) -> Result<Self, TextureError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let format = |msg: &str| TextureError::Format(msg.to_string());
+
+        let mut lines = data.split(|&b| b == b'\n');
+        let mut consumed = 0;
+        let mut next_line = || -> Result<&[u8], TextureError> {
+            let line = lines
+                .next()
+                .ok_or_else(|| format("unexpected end of data"))?;
+            consumed += line.len() + 1;
+            Ok(line)
+        };
+
+        if !next_line()?.starts_with(b"#?") {
+            return Err(format("expected a Radiance header"));
+        }
+        loop {
+            let line = next_line()?;
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with(b"FORMAT=") && line != b"FORMAT=32-bit_rle_rgbe" {
+                return Err(format("only 32-bit_rle_rgbe images are supported"));
+            }
+        }
+        let resolution = String::from_utf8_lossy(next_line()?).into_owned();
+        let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", h, "+X", w] => (
+                h.parse::<usize>().map_err(|_| format("invalid height"))?,
+                w.parse::<usize>().map_err(|_| format("invalid width"))?,
+            ),
+            _ => return Err(format("only -Y +X scanline order is supported")),
+        };
+        if width == 0 || height == 0 {
+            return Err(format("empty image"));
+        }
+
+        let mut bytes = data[consumed.min(data.len())..].iter().copied();
+        let mut next = || bytes.next().ok_or_else(|| format("unexpected end of data"));
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut scanline = vec![[0u8; 4]; width];
+        for _ in 0..height {
+            let header = [next()?, next()?, next()?, next()?];
+            let encoded = (8..0x8000).contains(&width) && header[0] == 2 && header[1] == 2;
+            if encoded {
+                if ((header[2] as usize) << 8 | header[3] as usize) != width {
+                    return Err(format("scanline width mismatch"));
+                }
+                for channel in 0..4 {
+                    let mut x = 0;
+                    while x < width {
+                        let count = next()? as usize;
+                        let (run, count) = if count > 128 {
+                            (Some(next()?), count - 128)
+                        } else {
+                            (None, count)
+                        };
+                        if count == 0 || x + count > width {
+                            return Err(format("bad run length"));
+                        }
+                        for pixel in &mut scanline[x..x + count] {
+                            pixel[channel] = match run {
+                                Some(value) => value,
+                                None => next()?,
+                            };
+                        }
+                        x += count;
+                    }
+                }
+            } else {
+                scanline[0] = header;
+                for pixel in &mut scanline[1..] {
+                    *pixel = [next()?, next()?, next()?, next()?];
+                }
+            }
+            pixels.extend(scanline.iter().map(|&rgbe| rgbe_to_color(rgbe)));
+        }
+
+        Ok(Texture {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_pixel(&self, (x, y): (usize, usize)) -> &Color {
+        &self.pixels[y * self.width + x]
+    }
+
+    /// Nearest-pixel lookup; `v` runs from the bottom row up.
+    pub fn color_at(&self, u: Float, v: Float) -> Color {
+        self.region_color_at(&AtlasRegion::full(self), u, v)
+    }
+
+    /// Like `color_at`, with `u` and `v` spanning only the given region.
+    pub fn region_color_at(&self, region: &AtlasRegion, u: Float, v: Float) -> Color {
+        let (last_x, last_y) = (
+            region.width.saturating_sub(1),
+            region.height.saturating_sub(1),
+        );
+        let x = ((u * last_x as Float).round() as usize).min(last_x) + region.x;
+        let y = (((1.0 - v) * last_y as Float).round() as usize).min(last_y) + region.y;
+        let (x, y) = (x.min(self.width - 1), y.min(self.height - 1));
+
+        self.pixels[y * self.width + x]
+    }
+}
+
+fn rgbe_to_color([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let scale = Float::powi(2.0, e as i32 - 136);
+
+    Color::new(r as Float * scale, g as Float * scale, b as Float * scale)
+}
+
+/// A rectangle of pixels inside a shared texture, with `(x, y)` the top-left
+/// pixel as in `Canvas`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl AtlasRegion {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        AtlasRegion {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn full(texture: &Texture) -> Self {
+        AtlasRegion::new(0, 0, texture.get_width(), texture.get_height())
+    }
+}
+
+/// Scales, then rotates counter-clockwise about the UV origin, then offsets
+/// texture coordinates. Results outside `[0, 1]` wrap around.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvTransform {
+    pub offset: (Float, Float),
+    pub scale: (Float, Float),
+    pub rotation: Float,
+}
+
+impl UvTransform {
+    pub fn apply(&self, u: Float, v: Float) -> (Float, Float) {
+        let (su, sv) = (u * self.scale.0, v * self.scale.1);
+        let (sin, cos) = self.rotation.sin_cos();
+        let wrap = |a: Float| {
+            if (0.0..=1.0).contains(&a) {
+                a
+            } else {
+                a.rem_euclid(1.0)
+            }
+        };
+
+        (
+            wrap(su * cos - sv * sin + self.offset.0),
+            wrap(su * sin + sv * cos + self.offset.1),
+        )
+    }
+}
+
+impl Default for UvTransform {
+    fn default() -> Self {
+        UvTransform {
+            offset: (0.0, 0.0),
+            scale: (1.0, 1.0),
+            rotation: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UvMapping {
+    Spherical,
+    Planar,
+    Cylindrical,
+    Cubic,
+}
+
+impl UvMapping {
+    pub fn map(&self, point: Tuple4) -> (Float, Float) {
+        match self {
+            UvMapping::Spherical => {
+                let theta = point.x.atan2(point.z);
+                let radius = Tuple4::vector(point.x, point.y, point.z).magnitude();
+                let phi = (point.y / radius).acos();
+                let raw_u = theta / (2.0 * PI);
+
+                (1.0 - (raw_u + 0.5), 1.0 - phi / PI)
+            }
+            UvMapping::Planar => (point.x.rem_euclid(1.0), point.z.rem_euclid(1.0)),
+            UvMapping::Cylindrical => {
+                let theta = point.x.atan2(point.z);
+                let raw_u = theta / (2.0 * PI);
+
+                (1.0 - (raw_u + 0.5), point.y.rem_euclid(1.0))
+            }
+            UvMapping::Cubic => cube_uv(point),
+        }
+    }
+
+    /// Object-space directions in which u and v grow at `point`, not
+    /// normalized. The spherical u direction vanishes at the poles.
+    pub fn tangents(&self, point: Tuple4) -> (Tuple4, Tuple4) {
+        let (x, y, z) = (point.x, point.y, point.z);
+        match self {
+            UvMapping::Spherical => (
+                Tuple4::vector(-z, 0.0, x),
+                Tuple4::vector(-x * y, x * x + z * z, -z * y),
+            ),
+            UvMapping::Planar => (Tuple4::vector(1.0, 0.0, 0.0), Tuple4::vector(0.0, 0.0, 1.0)),
+            UvMapping::Cylindrical => (Tuple4::vector(-z, 0.0, x), Tuple4::vector(0.0, 1.0, 0.0)),
+            UvMapping::Cubic => cube_tangents(point),
+        }
+    }
+}
+
+fn cube_uv(point: Tuple4) -> (Float, Float) {
+    let (x, y, z) = (point.x, point.y, point.z);
+    let wrap = |a: Float| a.rem_euclid(2.0) / 2.0;
+    let coord = x.abs().max(y.abs()).max(z.abs());
+
+    if coord == x {
+        (wrap(1.0 - z), wrap(y + 1.0))
+    } else if coord == -x {
+        (wrap(z + 1.0), wrap(y + 1.0))
+    } else if coord == y {
+        (wrap(x + 1.0), wrap(1.0 - z))
+    } else if coord == -y {
+        (wrap(x + 1.0), wrap(z + 1.0))
+    } else if coord == z {
+        (wrap(x + 1.0), wrap(y + 1.0))
+    } else {
+        (wrap(1.0 - x), wrap(y + 1.0))
+    }
+}
+
+fn cube_tangents(point: Tuple4) -> (Tuple4, Tuple4) {
+    let (x, y, z) = (point.x, point.y, point.z);
+    let coord = x.abs().max(y.abs()).max(z.abs());
+    let up = Tuple4::vector(0.0, 1.0, 0.0);
+
+    if coord == x {
+        (Tuple4::vector(0.0, 0.0, -1.0), up)
+    } else if coord == -x {
+        (Tuple4::vector(0.0, 0.0, 1.0), up)
+    } else if coord == y {
+        (
+            Tuple4::vector(1.0, 0.0, 0.0),
+            Tuple4::vector(0.0, 0.0, -1.0),
+        )
+    } else if coord == -y {
+        (Tuple4::vector(1.0, 0.0, 0.0), Tuple4::vector(0.0, 0.0, 1.0))
+    } else if coord == z {
+        (Tuple4::vector(1.0, 0.0, 0.0), up)
+    } else {
+        (Tuple4::vector(-1.0, 0.0, 0.0), up)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TexturePattern {
+    texture: Arc<Texture>,
+    mapping: UvMapping,
+    uv_transform: UvTransform,
+    region: AtlasRegion,
+    transform: Matrix4x4,
+    inverse: Matrix4x4,
+}
+
+impl TexturePattern {
+    pub fn new(texture: Arc<Texture>, mapping: UvMapping) -> Self {
+        TexturePattern {
+            region: AtlasRegion::full(&texture),
+            texture,
+            mapping,
+            uv_transform: UvTransform::default(),
+            transform: Matrix4x4::identity(),
+            inverse: Matrix4x4::identity(),
+        }
+    }
+
+    pub fn set_transform(&mut self, m: Matrix4x4) {
+        self.inverse = m.inverse().expect("Can't inverse singular matrix");
+        self.transform = m;
+    }
+
+    pub fn set_uv_transform(&mut self, uv_transform: UvTransform) {
+        self.uv_transform = uv_transform;
+    }
+
+    /// Restricts lookups to part of the texture, so several patterns can
+    /// share one atlas image.
+    pub fn set_region(&mut self, region: AtlasRegion) {
+        self.region = region;
+    }
+}
+
+impl Pattern for TexturePattern {
+    fn get_transform(&self) -> &Matrix4x4 {
+        &self.transform
+    }
+
+    fn get_inverse_transform(&self) -> &Matrix4x4 {
+        &self.inverse
+    }
+
+    fn pattern_at(&self, point: Tuple4) -> Color {
+        let (u, v) = self.mapping.map(point);
+        let (u, v) = self.uv_transform.apply(u, v);
+
+        self.texture.region_color_at(&self.region, u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float::{
+        consts::{FRAC_1_SQRT_2, FRAC_PI_2},
+        Float,
+    };
+
+    use super::*;
+
+    const EPSILON: Float = 1e-6;
+
+    fn equal(a: Float, b: Float) -> bool {
+        (a - b).abs() < EPSILON
+    }
+
+    fn checkers() -> Texture {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.put_pixel(Color::new(1.0, 1.0, 1.0), (0, 0));
+        canvas.put_pixel(Color::new(1.0, 1.0, 1.0), (1, 1));
+
+        Texture::from_canvas(&canvas)
+    }
+
+    #[test]
+    fn test_loading_a_ppm_texture() {
+        let ppm = "P3\n# a comment\n2 1\n255\n255 0 0  0 0 255\n";
+
+        let texture = Texture::load_ppm(ppm.as_bytes()).unwrap();
+
+        assert_eq!(texture.get_width(), 2);
+        assert_eq!(texture.get_height(), 1);
+        assert_eq!(texture.color_at(0.0, 0.0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.color_at(1.0, 0.0), Color::new(0.0, 0.0, 1.0));
+
+        let mut raw = b"P6\n2 1\n255\n".to_vec();
+        raw.extend([255, 0, 0, 0, 0, 255]);
+        assert_eq!(Texture::load_ppm(&raw[..]).unwrap(), texture);
+    }
+
+    #[test]
+    fn test_loading_a_malformed_texture_fails() {
+        assert!(matches!(
+            Texture::load_ppm("P6\n1 1\n255\n".as_bytes()),
+            Err(TextureError::Format(_))
+        ));
+        assert!(matches!(
+            Texture::load_ppm("P3\n2 2\n255\n0 0 0".as_bytes()),
+            Err(TextureError::Format(_))
+        ));
+        assert!(matches!(
+            Texture::load_ppm("P3\n0 0\n255\n".as_bytes()),
+            Err(TextureError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn test_loading_a_flat_hdr_texture() {
+        let mut hdr = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        hdr.extend([128, 64, 0, 129, 0, 0, 0, 0]);
+
+        let texture = Texture::load_hdr(&hdr[..]).unwrap();
+
+        assert_eq!(texture.get_width(), 2);
+        assert_eq!(texture.get_height(), 1);
+        assert_eq!(*texture.get_pixel((0, 0)), Color::new(1.0, 0.5, 0.0));
+        assert_eq!(*texture.get_pixel((1, 0)), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_loading_a_run_length_encoded_hdr_texture() {
+        let mut hdr = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+        hdr.extend([2, 2, 0, 8]);
+        hdr.extend([128 + 8, 128]);
+        hdr.extend([4, 1, 2, 3, 4, 128 + 4, 0]);
+        hdr.extend([128 + 8, 0]);
+        hdr.extend([128 + 8, 130]);
+
+        let texture = Texture::load_hdr(&hdr[..]).unwrap();
+
+        assert_eq!(*texture.get_pixel((0, 0)), Color::new(2.0, 0.015625, 0.0));
+        assert_eq!(*texture.get_pixel((7, 0)), Color::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_loading_a_malformed_hdr_texture_fails() {
+        assert!(matches!(
+            Texture::load_hdr("P3\n1 1\n255\n".as_bytes()),
+            Err(TextureError::Format(_))
+        ));
+        assert!(matches!(
+            Texture::load_hdr("#?RADIANCE\n\n+Y 1 +X 1\n".as_bytes()),
+            Err(TextureError::Format(_))
+        ));
+        assert!(matches!(
+            Texture::load_hdr(&b"#?RADIANCE\n\n-Y 2 +X 1\n\x80\x80\x80\x81"[..]),
+            Err(TextureError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn test_texture_lookup_puts_v_zero_at_the_bottom() {
+        let texture = checkers();
+
+        assert_eq!(texture.color_at(0.0, 1.0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(texture.color_at(0.0, 0.0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(texture.color_at(1.0, 0.0), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_spherical_mapping_on_a_3d_point() {
+        let cases = [
+            (Tuple4::point(0.0, 0.0, -1.0), (0.0, 0.5)),
+            (Tuple4::point(1.0, 0.0, 0.0), (0.25, 0.5)),
+            (Tuple4::point(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (Tuple4::point(-1.0, 0.0, 0.0), (0.75, 0.5)),
+            (Tuple4::point(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (Tuple4::point(0.0, -1.0, 0.0), (0.5, 0.0)),
+            (
+                Tuple4::point(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0),
+                (0.25, 0.75),
+            ),
+        ];
+
+        for (point, (u, v)) in cases {
+            let (mu, mv) = UvMapping::Spherical.map(point);
+            assert!(equal(mu, u) && equal(mv, v), "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn test_planar_mapping_on_a_3d_point() {
+        let cases = [
+            (Tuple4::point(0.25, 0.0, 0.5), (0.25, 0.5)),
+            (Tuple4::point(0.25, 0.0, -0.25), (0.25, 0.75)),
+            (Tuple4::point(0.25, 0.5, -0.25), (0.25, 0.75)),
+            (Tuple4::point(1.25, 0.0, 0.5), (0.25, 0.5)),
+            (Tuple4::point(0.25, 0.0, -1.75), (0.25, 0.25)),
+            (Tuple4::point(1.0, 0.0, -1.0), (0.0, 0.0)),
+        ];
+
+        for (point, (u, v)) in cases {
+            let (mu, mv) = UvMapping::Planar.map(point);
+            assert!(equal(mu, u) && equal(mv, v), "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn test_cylindrical_mapping_on_a_3d_point() {
+        let cases = [
+            (Tuple4::point(0.0, 0.0, -1.0), (0.0, 0.0)),
+            (Tuple4::point(0.0, 0.5, -1.0), (0.0, 0.5)),
+            (Tuple4::point(0.0, 1.0, -1.0), (0.0, 0.0)),
+            (
+                Tuple4::point(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2),
+                (0.125, 0.5),
+            ),
+            (Tuple4::point(1.0, 0.5, 0.0), (0.25, 0.5)),
+            (Tuple4::point(-1.0, 1.25, 0.0), (0.75, 0.25)),
+        ];
+
+        for (point, (u, v)) in cases {
+            let (mu, mv) = UvMapping::Cylindrical.map(point);
+            assert!(equal(mu, u) && equal(mv, v), "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn test_cubic_mapping_on_each_face() {
+        let cases = [
+            (Tuple4::point(-0.5, 0.5, 1.0), (0.25, 0.75)),
+            (Tuple4::point(0.5, -0.5, -1.0), (0.25, 0.25)),
+            (Tuple4::point(-1.0, 0.5, -0.5), (0.25, 0.75)),
+            (Tuple4::point(1.0, -0.5, 0.5), (0.25, 0.25)),
+            (Tuple4::point(-0.5, 1.0, -0.5), (0.25, 0.75)),
+            (Tuple4::point(0.5, -1.0, 0.5), (0.75, 0.75)),
+        ];
+
+        for (point, (u, v)) in cases {
+            let (mu, mv) = UvMapping::Cubic.map(point);
+            assert!(equal(mu, u) && equal(mv, v), "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn test_mapping_tangents_point_where_u_and_v_grow() {
+        let h = 1e-3;
+        let cases = [
+            (UvMapping::Spherical, Tuple4::point(0.6, 0.48, -0.64)),
+            (UvMapping::Spherical, Tuple4::point(-0.6, -0.8, 0.0)),
+            (UvMapping::Planar, Tuple4::point(0.25, 0.0, 0.5)),
+            (UvMapping::Cylindrical, Tuple4::point(1.0, 0.5, 0.0)),
+            (UvMapping::Cubic, Tuple4::point(-0.5, 0.5, 1.0)),
+            (UvMapping::Cubic, Tuple4::point(0.5, -0.5, -1.0)),
+            (UvMapping::Cubic, Tuple4::point(-1.0, 0.5, -0.5)),
+            (UvMapping::Cubic, Tuple4::point(1.0, -0.5, 0.5)),
+            (UvMapping::Cubic, Tuple4::point(-0.5, 1.0, -0.5)),
+            (UvMapping::Cubic, Tuple4::point(0.5, -1.0, 0.5)),
+        ];
+
+        for (mapping, point) in cases {
+            let (du, dv) = mapping.tangents(point);
+            let (u, v) = mapping.map(point);
+            let (u1, v1) = mapping.map(point + du.normalize() * h);
+            let (u2, v2) = mapping.map(point + dv.normalize() * h);
+
+            assert!(
+                u1 > u && (v1 - v).abs() < h / 10.0,
+                "{:?} {:?}",
+                mapping,
+                point
+            );
+            assert!(
+                v2 > v && (u2 - u).abs() < h / 10.0,
+                "{:?} {:?}",
+                mapping,
+                point
+            );
+        }
+    }
+
+    #[test]
+    fn test_texture_pattern_samples_through_the_mapping() {
+        let pattern = TexturePattern::new(Arc::new(checkers()), UvMapping::Planar);
+
+        assert_eq!(
+            pattern.color_at(Tuple4::point(0.0, 0.0, 0.9)),
+            Color::new(1.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            pattern.color_at(Tuple4::point(0.9, 0.0, 0.9)),
+            Color::new(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_uv_transforms_scale_rotate_and_wrap() {
+        let identity = UvTransform::default();
+        let tiled = UvTransform {
+            scale: (2.0, 2.0),
+            ..Default::default()
+        };
+        let turned = UvTransform {
+            offset: (1.0, 0.0),
+            rotation: FRAC_PI_2,
+            ..Default::default()
+        };
+
+        assert_eq!(identity.apply(1.0, 0.25), (1.0, 0.25));
+        let (u, v) = tiled.apply(0.75, 0.25);
+        assert!(equal(u, 0.5) && equal(v, 0.5));
+        let (u, v) = turned.apply(0.25, 0.5);
+        assert!(equal(u, 0.5) && equal(v, 0.25));
+    }
+
+    #[test]
+    fn test_patterns_can_share_regions_of_an_atlas() {
+        let mut canvas = Canvas::new(4, 2);
+        for y in 0..2 {
+            canvas.put_pixel(Color::new(1.0, 0.0, 0.0), (0, y));
+            canvas.put_pixel(Color::new(1.0, 0.0, 0.0), (1, y));
+            canvas.put_pixel(Color::new(0.0, 0.0, 1.0), (2, y));
+            canvas.put_pixel(Color::new(0.0, 0.0, 1.0), (3, y));
+        }
+        let atlas = Arc::new(Texture::from_canvas(&canvas));
+        let mut left = TexturePattern::new(atlas.clone(), UvMapping::Planar);
+        left.set_region(AtlasRegion::new(0, 0, 2, 2));
+        let mut right = TexturePattern::new(atlas, UvMapping::Planar);
+        right.set_region(AtlasRegion::new(2, 0, 2, 2));
+
+        for x in [0.0, 0.4, 0.9] {
+            let p = Tuple4::point(x, 0.0, 0.5);
+            assert_eq!(left.color_at(p), Color::new(1.0, 0.0, 0.0));
+            assert_eq!(right.color_at(p), Color::new(0.0, 0.0, 1.0));
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use crate::{float::Float, matrix::Matrix4x4, tuple::Tuple4};
+
+/// Unit quaternion for rotations that interpolate without gimbal lock.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: Float,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Quaternion {
+    pub fn identity() -> Self {
+        Quaternion {
+            w: 1.0,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+
+    /// Rotation by `angle` radians about `axis`, counter-clockwise when
+    /// looking down the axis like `Matrix4x4::rotation_*`.
+    pub fn from_axis_angle(axis: Tuple4, angle: Float) -> Self {
+        let axis = axis.normalize();
+        let (sin, cos) = (angle / 2.0).sin_cos();
+
+        Quaternion {
+            w: cos,
+            x: axis.x * sin,
+            y: axis.y * sin,
+            z: axis.z * sin,
+        }
+    }
+
+    fn dot(&self, other: &Quaternion) -> Float {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    fn scaled(&self, s: Float) -> Self {
+        Quaternion {
+            w: self.w * s,
+            x: self.x * s,
+            y: self.y * s,
+            z: self.z * s,
+        }
+    }
+
+    fn plus(&self, other: &Quaternion) -> Self {
+        Quaternion {
+            w: self.w + other.w,
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+
+    fn normalize(&self) -> Self {
+        self.scaled(1.0 / self.dot(self).sqrt())
+    }
+
+    /// Spherical interpolation along the shorter arc.
+    pub fn slerp(&self, other: &Quaternion, t: Float) -> Self {
+        let mut cos = self.dot(other);
+        let mut other = *other;
+        if cos < 0.0 {
+            cos = -cos;
+            other = other.scaled(-1.0);
+        }
+        if cos > 0.9995 {
+            return self.scaled(1.0 - t).plus(&other.scaled(t)).normalize();
+        }
+
+        let theta = cos.acos();
+        let sin = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin;
+        let b = (t * theta).sin() / sin;
+
+        self.scaled(a).plus(&other.scaled(b))
+    }
+
+    pub fn to_matrix(&self) -> Matrix4x4 {
+        let Quaternion { w, x, y, z } = *self;
+
+        Matrix4x4::new([
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y - w * z),
+            2.0 * (x * z + w * y),
+            0.0,
+            2.0 * (x * y + w * z),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z - w * x),
+            0.0,
+            2.0 * (x * z - w * y),
+            2.0 * (y * z + w * x),
+            1.0 - 2.0 * (x * x + y * y),
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        ])
+    }
+}
+
+/// Pose at one moment: scale, then rotate, then translate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    pub time: Float,
+    pub translation: Tuple4,
+    pub rotation: Quaternion,
+    pub scale: Tuple4,
+}
+
+impl Keyframe {
+    pub fn to_matrix(&self) -> Matrix4x4 {
+        let t = self.translation;
+        let s = self.scale;
+
+        Matrix4x4::translation(t.x, t.y, t.z)
+            * self.rotation.to_matrix()
+            * Matrix4x4::scaling(s.x, s.y, s.z)
+    }
+}
+
+/// A transform that may change over time. Every time-dependent feature
+/// should go through `sample` so they all agree on how poses blend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    keyframes: Vec<Keyframe>,
+}
+
+impl Transform {
+    /// A single pose at time zero.
+    pub fn new(translation: Tuple4, rotation: Quaternion, scale: Tuple4) -> Self {
+        Transform {
+            keyframes: vec![Keyframe {
+                time: 0.0,
+                translation,
+                rotation,
+                scale,
+            }],
+        }
+    }
+
+    pub fn identity() -> Self {
+        Transform::new(
+            Tuple4::vector(0.0, 0.0, 0.0),
+            Quaternion::identity(),
+            Tuple4::vector(1.0, 1.0, 1.0),
+        )
+    }
+
+    /// Adds a pose at `time`, replacing any pose already there.
+    pub fn keyframe(mut self, keyframe: Keyframe) -> Self {
+        self.keyframes.retain(|k| k.time != keyframe.time);
+        let i = self.keyframes.partition_point(|k| k.time < keyframe.time);
+        self.keyframes.insert(i, keyframe);
+        self
+    }
+
+    pub fn get_keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.keyframes.len() > 1
+    }
+
+    /// Matrix at time `t`, holding the first and last poses outside the
+    /// keyframed range. Translation and scale are interpolated linearly and
+    /// rotation spherically.
+    pub fn sample(&self, t: Float) -> Matrix4x4 {
+        let first = &self.keyframes[0];
+        let last = &self.keyframes[self.keyframes.len() - 1];
+        if t <= first.time {
+            return first.to_matrix();
+        }
+        if t >= last.time {
+            return last.to_matrix();
+        }
+
+        let i = self.keyframes.partition_point(|k| k.time <= t);
+        let (a, b) = (&self.keyframes[i - 1], &self.keyframes[i]);
+        let s = (t - a.time) / (b.time - a.time);
+        let lerp = |p: Tuple4, q: Tuple4| p * (1.0 - s) + q * s;
+
+        Keyframe {
+            time: t,
+            translation: lerp(a.translation, b.translation),
+            rotation: a.rotation.slerp(&b.rotation, s),
+            scale: lerp(a.scale, b.scale),
+        }
+        .to_matrix()
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float::{consts::PI, Float, EPSILON};
+
+    use super::*;
+
+    fn matrices_equal(a: &Matrix4x4, b: &Matrix4x4) -> bool {
+        (0..4).all(|y| (0..4).all(|x| (a.get(y, x) - b.get(y, x)).abs() < EPSILON))
+    }
+
+    fn spin(angle: Float, time: Float, x: Float) -> Keyframe {
+        Keyframe {
+            time,
+            translation: Tuple4::vector(x, 0.0, 0.0),
+            rotation: Quaternion::from_axis_angle(Tuple4::vector(0.0, 1.0, 0.0), angle),
+            scale: Tuple4::vector(1.0, 1.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn test_quaternions_match_rotation_matrices() {
+        let cases = [
+            (
+                Tuple4::vector(1.0, 0.0, 0.0),
+                Matrix4x4::rotation_x(PI / 3.0),
+            ),
+            (
+                Tuple4::vector(0.0, 1.0, 0.0),
+                Matrix4x4::rotation_y(PI / 3.0),
+            ),
+            (
+                Tuple4::vector(0.0, 0.0, 1.0),
+                Matrix4x4::rotation_z(PI / 3.0),
+            ),
+        ];
+
+        for (axis, expected) in cases {
+            let q = Quaternion::from_axis_angle(axis, PI / 3.0);
+            assert!(matrices_equal(&q.to_matrix(), &expected));
+        }
+    }
+
+    #[test]
+    fn test_a_static_transform_ignores_time() {
+        let transform = Transform::new(
+            Tuple4::vector(1.0, 2.0, 3.0),
+            Quaternion::identity(),
+            Tuple4::vector(2.0, 2.0, 2.0),
+        );
+        let expected = Matrix4x4::translation(1.0, 2.0, 3.0) * Matrix4x4::scaling(2.0, 2.0, 2.0);
+
+        assert!(!transform.is_animated());
+        assert!(matrices_equal(&transform.sample(-1.0), &expected));
+        assert!(matrices_equal(&transform.sample(5.0), &expected));
+    }
+
+    #[test]
+    fn test_sampling_between_keyframes() {
+        let transform = Transform::identity()
+            .keyframe(spin(PI / 2.0, 1.0, 4.0))
+            .keyframe(spin(0.0, 0.0, 0.0));
+
+        let halfway = transform.sample(0.5);
+
+        let expected = Matrix4x4::translation(2.0, 0.0, 0.0) * Matrix4x4::rotation_y(PI / 4.0);
+        assert!(transform.is_animated());
+        assert!(matrices_equal(&halfway, &expected));
+        assert!(matrices_equal(
+            &transform.sample(2.0),
+            &spin(PI / 2.0, 1.0, 4.0).to_matrix()
+        ));
+    }
+
+    #[test]
+    fn test_slerp_takes_the_shorter_arc() {
+        let a = Quaternion::from_axis_angle(Tuple4::vector(0.0, 0.0, 1.0), 0.1);
+        let b = Quaternion::from_axis_angle(Tuple4::vector(0.0, 0.0, 1.0), 2.0 * PI - 0.1);
+
+        let mid = a.slerp(&b, 0.5);
+
+        assert!(matrices_equal(&mid.to_matrix(), &Matrix4x4::identity()));
+    }
+}
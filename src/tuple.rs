@@ -1,6 +1,30 @@
-use std::ops::{Add, Div, Mul, Sub};
+use std::{
+    error::Error,
+    fmt,
+    ops::{Add, Div, Mul, Sub},
+};
 
-type Elem = f64;
+use crate::float::Float;
+use crate::simd;
+
+type Elem = Float;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TupleError {
+    WrongLength { found: usize },
+}
+
+impl fmt::Display for TupleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TupleError::WrongLength { found } => {
+                write!(f, "expected 4 tuple elements, found {}", found)
+            }
+        }
+    }
+}
+
+impl Error for TupleError {}
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Tuple4 {
@@ -28,6 +52,16 @@ impl Tuple4 {
         Tuple4::new(x.into(), y.into(), z.into(), 0.0)
     }
 
+    /// Reads `[x, y, z, w]`.
+    pub fn from_slice(elements: &[Elem]) -> Result<Self, TupleError> {
+        match *elements {
+            [x, y, z, w] => Ok(Tuple4::new(x, y, z, w)),
+            _ => Err(TupleError::WrongLength {
+                found: elements.len(),
+            }),
+        }
+    }
+
     pub fn is_point(&self) -> bool {
         self.w == 1.0
     }
@@ -50,15 +84,11 @@ impl Tuple4 {
     }
 
     pub fn dot(self, other: &Tuple4) -> Elem {
-        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+        simd::dot(&self, other)
     }
 
     pub fn cross(self, other: Tuple4) -> Self {
-        Self::vector(
-            self.y * other.z - self.z * other.y,
-            self.z * other.x - self.x * other.z,
-            self.x * other.y - self.y * other.x,
-        )
+        simd::cross(&self, &other)
     }
 
     pub fn reflect(self, normal: Tuple4) -> Self {
@@ -66,16 +96,35 @@ impl Tuple4 {
     }
 }
 
+impl Add<&Tuple4> for &Tuple4 {
+    type Output = Tuple4;
+
+    fn add(self, other: &Tuple4) -> Self::Output {
+        simd::add(self, other)
+    }
+}
+
 impl Add for Tuple4 {
     type Output = Self;
 
     fn add(self, other: Self) -> Self::Output {
-        Tuple4::new(
-            self.x + other.x,
-            self.y + other.y,
-            self.z + other.z,
-            self.w + other.w,
-        )
+        Add::add(&self, &other)
+    }
+}
+
+impl Add<&Tuple4> for Tuple4 {
+    type Output = Self;
+
+    fn add(self, other: &Tuple4) -> Self::Output {
+        Add::add(&self, other)
+    }
+}
+
+impl Sub<&Tuple4> for &Tuple4 {
+    type Output = Tuple4;
+
+    fn sub(self, other: &Tuple4) -> Self::Output {
+        simd::sub(self, other)
     }
 }
 
@@ -83,18 +132,21 @@ impl Sub for Tuple4 {
     type Output = Self;
 
     fn sub(self, other: Self) -> Self::Output {
-        Tuple4::new(
-            self.x - other.x,
-            self.y - other.y,
-            self.z - other.z,
-            self.w - other.w,
-        )
+        Sub::sub(&self, &other)
     }
 }
 
-impl Mul<Elem> for Tuple4 {
+impl Sub<&Tuple4> for Tuple4 {
     type Output = Self;
 
+    fn sub(self, other: &Tuple4) -> Self::Output {
+        Sub::sub(&self, other)
+    }
+}
+
+impl Mul<Elem> for &Tuple4 {
+    type Output = Tuple4;
+
     fn mul(self, other: Elem) -> Self::Output {
         Tuple4::new(
             self.x * other,
@@ -105,6 +157,14 @@ impl Mul<Elem> for Tuple4 {
     }
 }
 
+impl Mul<Elem> for Tuple4 {
+    type Output = Self;
+
+    fn mul(self, other: Elem) -> Self::Output {
+        Mul::mul(&self, other)
+    }
+}
+
 impl Mul<Tuple4> for Elem {
     type Output = Tuple4;
 
@@ -113,6 +173,14 @@ impl Mul<Tuple4> for Elem {
     }
 }
 
+impl Mul<&Tuple4> for Elem {
+    type Output = Tuple4;
+
+    fn mul(self, other: &Tuple4) -> Self::Output {
+        other * self
+    }
+}
+
 impl Div<Elem> for Tuple4 {
     type Output = Self;
 
@@ -121,16 +189,52 @@ impl Div<Elem> for Tuple4 {
     }
 }
 
+impl Div<Elem> for &Tuple4 {
+    type Output = Tuple4;
+
+    fn div(self, other: Elem) -> Self::Output {
+        self * (1.0 / other)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::float::Float;
 
-    const EPSILON: f64 = 1e-6;
+    const EPSILON: Float = 1e-6;
 
-    fn equal(a: f64, b: f64) -> bool {
+    fn equal(a: Float, b: Float) -> bool {
         (a - b).abs() < EPSILON
     }
 
+    #[test]
+    #[allow(clippy::op_ref)]
+    fn test_tuple_operators_accept_references() {
+        let a = Tuple4::point(3.0, -2.0, 5.0);
+        let b = Tuple4::vector(-2.0, 3.0, 1.0);
+
+        assert_eq!(&a + &b, a + b);
+        assert_eq!(a + &b, a + b);
+        assert_eq!(&a - &b, a - b);
+        assert_eq!(a - &b, a - b);
+        assert_eq!(&b * 2.0, b * 2.0);
+        assert_eq!(2.0 * &b, b * 2.0);
+        assert_eq!(&b / 2.0, b / 2.0);
+    }
+
+    #[test]
+    fn test_tuple_from_a_slice() {
+        assert_eq!(
+            Tuple4::from_slice(&[1.0, 2.0, 3.0, 1.0]),
+            Ok(Tuple4::point(1.0, 2.0, 3.0))
+        );
+        assert_eq!(
+            Tuple4::from_slice(&[1.0, 2.0, 3.0]),
+            Err(TupleError::WrongLength { found: 3 })
+        );
+    }
+
     #[test]
     fn test_tuple_with_w_as_one_should_be_a_point() {
         let tuple = Tuple4::new(4.3, -4.2, 3.1, 1.0);
@@ -139,8 +243,8 @@ mod tests {
         assert_eq!(tuple.y, -4.2);
         assert_eq!(tuple.z, 3.1);
         assert_eq!(tuple.w, 1.0);
-        assert_eq!(tuple.is_point(), true);
-        assert_eq!(tuple.is_vector(), false);
+        assert!(tuple.is_point());
+        assert!(!tuple.is_vector());
     }
 
     #[test]
@@ -151,8 +255,8 @@ mod tests {
         assert_eq!(tuple.y, -4.2);
         assert_eq!(tuple.z, 3.1);
         assert_eq!(tuple.w, 0.0);
-        assert_eq!(tuple.is_point(), false);
-        assert_eq!(tuple.is_vector(), true);
+        assert!(!tuple.is_point());
+        assert!(tuple.is_vector());
     }
 
     #[test]
@@ -234,7 +338,7 @@ mod tests {
 
         let mag = v.magnitude();
 
-        assert_eq!(mag * mag, 14.0);
+        assert_eq!(mag, Float::sqrt(14.0));
     }
 
     #[test]
@@ -243,9 +347,9 @@ mod tests {
 
         let normalized_v = v.normalize();
 
-        assert_eq!(equal(normalized_v.x, 0.267261), true);
-        assert_eq!(equal(normalized_v.y, 0.534522), true);
-        assert_eq!(equal(normalized_v.z, 0.801783), true);
+        assert!(equal(normalized_v.x, 0.267261));
+        assert!(equal(normalized_v.y, 0.534522));
+        assert!(equal(normalized_v.z, 0.801783));
     }
 
     #[test]
@@ -281,7 +385,7 @@ mod tests {
     #[test]
     fn test_reflecting_vector_off_slanted_surface() {
         let v = Tuple4::vector(0.0, -1.0, 0.0);
-        let n = Tuple4::vector(2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0, 0.0);
+        let n = Tuple4::vector(Float::sqrt(2.0) / 2.0, Float::sqrt(2.0) / 2.0, 0.0);
 
         let r = v.reflect(n);
 
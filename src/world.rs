@@ -0,0 +1,2235 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use crate::{
+    bounds::BoundingBox,
+    bvh::{Bvh, BvhError},
+    canvas::Canvas,
+    capabilities::parallel_map,
+    color::{Color, ColorSum},
+    environment::EnvironmentMap,
+    float::{consts::PI, Float},
+    lights::Light,
+    materials::{Material, ShadowCatcher},
+    matrix::Matrix4x4,
+    palette::Palette,
+    photons::{Photon, PhotonGather, PhotonMap},
+    ray::Ray,
+    rng::{RandomSource, Rng},
+    sampling::{cosine_hemisphere, uniform_sphere},
+    settings::{Integrator, RenderSettings},
+    sphere::{PreparedComputations, Sphere, SphereIntersection, SphereIntersections},
+    tuple::Tuple4,
+};
+
+/// Photons one worker traces at a time while building a photon map.
+const PHOTON_BATCH: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldSummary {
+    pub spheres: usize,
+    pub lights: usize,
+    pub extent: Option<(Tuple4, Tuple4)>,
+}
+
+impl fmt::Display for WorldSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "spheres: {}", self.spheres)?;
+        writeln!(f, "lights: {}", self.lights)?;
+        match self.extent {
+            Some((min, max)) => write!(
+                f,
+                "extent: ({:.3}, {:.3}, {:.3}) to ({:.3}, {:.3}, {:.3})",
+                min.x, min.y, min.z, max.x, max.y, max.z
+            ),
+            None => write!(f, "extent: empty"),
+        }
+    }
+}
+
+/// First surface found by `World::raycast`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit<'a> {
+    pub distance: Float,
+    pub point: Tuple4,
+    pub normal: Tuple4,
+    pub object: &'a Sphere,
+}
+
+#[derive(Clone)]
+pub enum Background {
+    Color(Color),
+    /// Blends from `horizon` straight ahead to `zenith` straight up; rays
+    /// pointing below the horizon see the horizon color.
+    Sky {
+        horizon: Color,
+        zenith: Color,
+    },
+    /// Equirectangular (latitude-longitude) image looked up by ray direction.
+    Environment(EnvironmentMap),
+    Shader(Arc<dyn Fn(&Ray) -> Color + Send + Sync>),
+}
+
+impl Background {
+    pub fn shader<F: Fn(&Ray) -> Color + Send + Sync + 'static>(f: F) -> Self {
+        Background::Shader(Arc::new(f))
+    }
+
+    pub fn color_at(&self, ray: &Ray) -> Color {
+        match self {
+            Background::Color(color) => *color,
+            Background::Sky { horizon, zenith } => {
+                let t = ray.direction.normalize().y.max(0.0);
+                *horizon * (1.0 - t) + *zenith * t
+            }
+            Background::Environment(map) => map.color_at(ray.direction),
+            Background::Shader(shader) => shader(ray),
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Color(Color::new(0.0, 0.0, 0.0))
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct World {
+    objects: Vec<Sphere>,
+    lights: Vec<Light>,
+    background: Background,
+    settings: RenderSettings,
+    bvh: OnceLock<Bvh>,
+    photon_maps: OnceLock<Vec<PhotonMap>>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        World {
+            objects: Vec::new(),
+            lights: Vec::new(),
+            background: Background::default(),
+            settings: RenderSettings::default(),
+            bvh: OnceLock::new(),
+            photon_maps: OnceLock::new(),
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: RenderSettings) {
+        self.settings = settings;
+        self.photon_maps = OnceLock::new();
+    }
+
+    pub fn get_settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+    }
+
+    pub fn get_background(&self) -> &Background {
+        &self.background
+    }
+
+    pub fn add_object(&mut self, object: Sphere) {
+        self.objects.push(object);
+        self.bvh = OnceLock::new();
+        self.photon_maps = OnceLock::new();
+    }
+
+    /// Swaps the object at `index` for `object` and returns the old one,
+    /// whose bounds together with the new ones tell `Camera::render_dirty`
+    /// what to redraw.
+    pub fn replace_object(&mut self, index: usize, object: Sphere) -> Sphere {
+        let old = std::mem::replace(&mut self.objects[index], object);
+        self.bvh = OnceLock::new();
+        self.photon_maps = OnceLock::new();
+
+        old
+    }
+
+    pub fn get_objects(&self) -> &[Sphere] {
+        &self.objects
+    }
+
+    pub fn add_light<L: Into<Light>>(&mut self, light: L) {
+        self.lights.push(light.into());
+        self.photon_maps = OnceLock::new();
+    }
+
+    pub fn get_lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn summary(&self) -> WorldSummary {
+        let bounds = self.bounds();
+
+        WorldSummary {
+            spheres: self.objects.len(),
+            lights: self.lights.len(),
+            extent: (!bounds.is_empty()).then(|| (bounds.get_min(), bounds.get_max())),
+        }
+    }
+
+    /// Box around every object, empty for an empty world.
+    pub fn bounds(&self) -> BoundingBox {
+        self.objects
+            .iter()
+            .fold(BoundingBox::empty(), |bounds, object| {
+                bounds.merge(&object.bounds())
+            })
+    }
+
+    /// Every intersection with objects whose bounds the ray passes through,
+    /// sorted by distance. Objects entirely behind the ray's origin are
+    /// skipped, so some negative distances may be missing.
+    pub fn intersect(&self, ray: &Ray) -> SphereIntersections<'_> {
+        let mut xs = SphereIntersections::default();
+        self.intersect_into(ray, &mut xs);
+
+        xs
+    }
+
+    /// Loads the object hierarchy from `path` if it was saved there for
+    /// these exact objects, and otherwise builds it and saves it there for
+    /// the next render. Returns whether the saved copy was used.
+    pub fn cache_bvh<P: AsRef<Path>>(&mut self, path: P) -> Result<bool, BvhError> {
+        let bounds: Vec<_> = self.objects.iter().map(Sphere::bounds).collect();
+        if let Ok(file) = File::open(&path) {
+            match Bvh::read(&mut BufReader::new(file), &bounds) {
+                Ok(bvh) => {
+                    self.bvh = OnceLock::from(bvh);
+                    return Ok(true);
+                }
+                Err(BvhError::Io(e)) => return Err(BvhError::Io(e)),
+                Err(_) => {}
+            }
+        }
+
+        let bvh = self.bvh.get_or_init(|| Bvh::new(&bounds));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        bvh.write(&mut writer, &bounds)?;
+        writer.flush()?;
+
+        Ok(false)
+    }
+
+    /// Same as `intersect`, but refills `xs` in place so a loop casting many
+    /// rays reuses one allocation.
+    pub fn intersect_into<'a>(&'a self, ray: &Ray, xs: &mut SphereIntersections<'a>) {
+        xs.clear();
+        self.bvh()
+            .traverse(ray, |i| self.objects[i].append_intersections(ray, xs));
+        xs.sort();
+    }
+
+    /// The intersection `intersect(ray).hit()` would return, found without
+    /// collecting the others.
+    pub fn hit(&self, ray: &Ray) -> Option<SphereIntersection<'_>> {
+        let mut nearest: Option<SphereIntersection> = None;
+        self.bvh().traverse(ray, |i| {
+            if let Some((t1, t2)) = self.objects[i].intersection_distances(ray) {
+                for t in [t1, t2] {
+                    if t >= 0.0 && nearest.as_ref().is_none_or(|hit| t < hit.t) {
+                        nearest = Some(SphereIntersection::new(t, &self.objects[i]));
+                    }
+                }
+            }
+        });
+
+        nearest
+    }
+
+    fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| {
+            let bounds: Vec<_> = self.objects.iter().map(Sphere::bounds).collect();
+            Bvh::new(&bounds)
+        })
+    }
+
+    /// Nearest surface within `max_distance` of `origin` along `direction`,
+    /// for geometry queries that have nothing to do with rendering. A zero
+    /// `direction` points nowhere and hits nothing.
+    pub fn raycast(
+        &self,
+        origin: Tuple4,
+        direction: Tuple4,
+        max_distance: Float,
+    ) -> Option<RaycastHit<'_>> {
+        let ray = Ray::try_new(origin, direction).ok()?;
+        let ray = Ray::new(origin, ray.direction.normalize());
+        let hit = self.hit(&ray).filter(|hit| hit.t <= max_distance)?;
+        let point = ray.position(hit.t);
+
+        Some(RaycastHit {
+            distance: hit.t,
+            point,
+            normal: hit.sphere.normal_at(point),
+            object: hit.sphere,
+        })
+    }
+
+    /// True when no surface lies on the segment from `a` to `b`.
+    pub fn line_of_sight(&self, a: Tuple4, b: Tuple4) -> bool {
+        !self.is_occluded(a, b)
+    }
+
+    /// Copy of the world with the box of every node in its object hierarchy
+    /// outlined by twelve thin spheres, `thickness` across, stretched along
+    /// the edges. The outlines are unlit and colored from `palette` by depth,
+    /// so lopsided or heavily overlapping splits stand out in a render.
+    pub fn with_bvh_bounds(&self, thickness: Float, palette: Palette) -> World {
+        let nodes = self.bvh().node_bounds();
+        let deepest = nodes.iter().map(|(depth, _)| *depth).max().unwrap_or(0);
+        let mut world = self.clone();
+        for (depth, bounds) in nodes {
+            let material = Material {
+                color: palette.color(depth as Float / deepest.max(1) as Float),
+                ambient: 1.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                ..Default::default()
+            };
+            for edge in box_edges(&bounds, thickness) {
+                let mut sphere = Sphere::new();
+                sphere.set_transform(edge);
+                sphere.set_material(material.clone());
+                world.add_object(sphere);
+            }
+        }
+
+        world
+    }
+
+    /// Nearest point on any surface to `point`, with the object it lies on.
+    pub fn closest_point(&self, point: Tuple4) -> Option<(Tuple4, &Sphere)> {
+        self.objects
+            .iter()
+            .map(|object| (object.closest_point(point), object))
+            .min_by(|(a, _), (b, _)| {
+                (*a - point)
+                    .magnitude()
+                    .total_cmp(&(*b - point).magnitude())
+            })
+    }
+
+    pub fn is_shadowed(&self, light: &Light, point: Tuple4) -> bool {
+        self.is_occluded(point, *light.position())
+    }
+
+    /// Fraction of a light visible from `point`. Lights with a radius are
+    /// sampled with `shadow_samples` rays, seeded from the point so the
+    /// estimate does not change between renders.
+    pub fn light_visibility(&self, light: &Light, point: Tuple4) -> Float {
+        let samples = self.settings.shadow_samples;
+        if light.radius() <= 0.0 || samples <= 1 {
+            return if self.is_shadowed(light, point) {
+                0.0
+            } else {
+                1.0
+            };
+        }
+
+        let mut rng = self.rng_at(point);
+        let visible = (0..samples)
+            .filter(|_| {
+                let offset = uniform_sphere(rng.next_float(), rng.next_float()) * light.radius();
+                !self.is_occluded(point, *light.position() + offset)
+            })
+            .count();
+
+        visible as Float / samples as Float
+    }
+
+    /// Sampler for a shading point, seeded from the point and the render seed
+    /// so noise stays put between renders.
+    fn rng_at(&self, point: Tuple4) -> Rng {
+        Rng::for_stream(self.settings.seed, point_seed(point))
+    }
+
+    fn is_occluded(&self, point: Tuple4, target: Tuple4) -> bool {
+        let v = target - point;
+        let distance = v.magnitude();
+        if distance == 0.0 {
+            return false;
+        }
+        let ray = match Ray::try_new(point, v.normalize()) {
+            Ok(ray) => ray,
+            Err(_) => return false,
+        };
+
+        self.bvh().any(&ray, |i| {
+            self.objects[i]
+                .intersection_distances(&ray)
+                .is_some_and(|(t1, t2)| {
+                    (0.0..distance).contains(&t1) || (0.0..distance).contains(&t2)
+                })
+        })
+    }
+
+    /// Color seen at a prepared hit. `remaining` is how many more mirror
+    /// bounces may follow: each reflected ray is shaded with `remaining - 1`,
+    /// and at zero the surface is shaded without its reflection. Glossy
+    /// reflections are supersampled only when `remaining` equals
+    /// `reflection_depth`, so start custom integrators there.
+    pub fn shade_hit(&self, comps: &PreparedComputations, remaining: usize) -> Color {
+        let material = comps.object.get_material();
+        if let Some(catcher) = &material.shadow_catcher {
+            let behind = Ray::new(comps.point, -1.0 * comps.eyev);
+            return self.background.color_at(&behind) * self.caught_light(comps, catcher);
+        }
+
+        let surface = self.direct_light(comps, true);
+
+        surface + self.environment_light(comps) + self.reflected_color(comps, remaining)
+    }
+
+    /// Light reaching the eye from every light source, including the
+    /// material's ambient term only when `ambient` is set.
+    fn direct_light(&self, comps: &PreparedComputations, ambient: bool) -> Color {
+        let candidates = self.settings.light_candidates;
+        if candidates > 0 && candidates < self.lights.len() {
+            return self.resampled_direct_light(comps, ambient, candidates);
+        }
+        let material = comps.object.get_material();
+
+        self.lights
+            .iter()
+            .map(|light| {
+                let visibility = if light.falloff(comps.point) > 0.0 {
+                    self.light_visibility(light, comps.over_point)
+                } else {
+                    1.0
+                };
+                let lighting = |in_shadow| {
+                    material.lighting(
+                        comps.object,
+                        *light,
+                        comps.point,
+                        comps.eyev,
+                        comps.normalv,
+                        in_shadow,
+                    )
+                };
+                if !ambient {
+                    (lighting(false) - lighting(true)) * visibility
+                } else if visibility >= 1.0 {
+                    lighting(false)
+                } else if visibility <= 0.0 {
+                    lighting(true)
+                } else {
+                    let shadowed = lighting(true);
+                    shadowed + (lighting(false) - shadowed) * visibility
+                }
+            })
+            .sum()
+    }
+
+    /// `direct_light` for worlds with many lights, using the resampled
+    /// importance sampling at the heart of ReSTIR. `candidates` lights drawn
+    /// at random are streamed through a one-item reservoir weighted by their
+    /// unshadowed contribution, and only the survivor gets shadow rays. The
+    /// estimate stays unbiased; ambient terms are still summed over every
+    /// light since they need no shadow rays.
+    fn resampled_direct_light(
+        &self,
+        comps: &PreparedComputations,
+        ambient: bool,
+        candidates: usize,
+    ) -> Color {
+        let material = comps.object.get_material();
+        let lighting = |light: &Light, in_shadow| {
+            material.lighting(
+                comps.object,
+                *light,
+                comps.point,
+                comps.eyev,
+                comps.normalv,
+                in_shadow,
+            )
+        };
+        let base: Color = if ambient {
+            self.lights.iter().map(|light| lighting(light, true)).sum()
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        };
+
+        let mut rng = Rng::for_stream(self.settings.seed, !point_seed(comps.over_point));
+        let count = self.lights.len();
+        let mut chosen = None;
+        let mut weight_sum = 0.0;
+        for _ in 0..candidates {
+            let i = ((rng.next_float() * count as Float) as usize).min(count - 1);
+            let light = &self.lights[i];
+            let unshadowed = lighting(light, false) - lighting(light, true);
+            let target = unshadowed.luminance();
+            // Candidates are drawn with probability 1 / count.
+            let weight = target * count as Float;
+            weight_sum += weight;
+            if rng.next_float() * weight_sum < weight {
+                chosen = Some((light, unshadowed, target));
+            }
+        }
+
+        match chosen {
+            Some((light, unshadowed, target)) if target > 0.0 => {
+                let visibility = self.light_visibility(light, comps.over_point);
+                base + unshadowed * (visibility * weight_sum / (candidates as Float * target))
+            }
+            _ => base,
+        }
+    }
+
+    /// Fraction of light a shadow catcher receives: the average visibility of
+    /// the lights times the share of `shadow_samples` hemisphere rays that
+    /// escape within the catcher's occlusion distance.
+    fn caught_light(&self, comps: &PreparedComputations, catcher: &ShadowCatcher) -> Float {
+        let lit = if self.lights.is_empty() {
+            1.0
+        } else {
+            self.lights
+                .iter()
+                .map(|light| self.light_visibility(light, comps.over_point))
+                .sum::<Float>()
+                / self.lights.len() as Float
+        };
+
+        let samples = self.settings.shadow_samples.max(1);
+        let mut rng = self.rng_at(comps.over_point);
+        let open = (0..samples)
+            .filter(|_| {
+                let direction =
+                    cosine_hemisphere(comps.normalv, rng.next_float(), rng.next_float());
+                let ray = comps.spawn_ray(direction);
+                match self.hit(&ray) {
+                    Some(hit) => hit.t > catcher.ao_distance,
+                    None => true,
+                }
+            })
+            .count();
+
+        lit * open as Float / samples as Float
+    }
+
+    /// Coverage for compositing: 1 where an ordinary object is hit, 0 where
+    /// the ray sees the background, and the shadow strength on a catcher.
+    pub fn alpha_at(&self, ray: &Ray) -> Float {
+        let hit = match self.hit(ray) {
+            Some(hit) => hit,
+            None => return 0.0,
+        };
+
+        match &hit.sphere.get_material().shadow_catcher {
+            Some(catcher) => {
+                let comps = hit.prepare_computations(ray);
+                1.0 - self.caught_light(&comps, catcher)
+            }
+            None => 1.0,
+        }
+    }
+
+    /// Diffuse light arriving from an environment map background, estimated
+    /// from `environment_samples` directions drawn in proportion to the
+    /// map's brightness. It comes on top of the material's ambient term, so
+    /// scenes lit by an environment usually set `ambient` to zero.
+    fn environment_light(&self, comps: &PreparedComputations) -> Color {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let material = comps.object.get_material();
+        let samples = self.settings.environment_samples;
+        let map = match &self.background {
+            Background::Environment(map) if samples > 0 && material.diffuse > 0.0 => map,
+            _ => return black,
+        };
+
+        let mut rng = self.rng_at(comps.over_point);
+        let mut sum = ColorSum::new();
+        for _ in 0..samples {
+            let (direction, pdf) = map.sample(rng.next_float(), rng.next_float());
+            let cos = direction.dot(&comps.normalv);
+            if cos <= 0.0 || pdf <= 0.0 {
+                continue;
+            }
+            if self.hit(&comps.spawn_ray(direction)).is_none() {
+                sum.add(map.color_at(direction) * (cos / (PI * pdf)));
+            }
+        }
+
+        material.color_at(comps.object, comps.point)
+            * sum.total()
+            * (material.diffuse / samples as Float)
+    }
+
+    fn reflected_color(&self, comps: &PreparedComputations, remaining: usize) -> Color {
+        let reflective = comps.object.get_material().reflective;
+        if reflective == 0.0 || remaining == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        let mirror = comps.reflected_ray();
+        let roughness = comps.object.get_material().roughness;
+        // Only the first bounce is supersampled, otherwise the ray count
+        // would grow exponentially with the reflection depth.
+        let samples = if roughness > 0.0 && remaining == self.settings.reflection_depth {
+            self.settings.glossy_samples.max(1)
+        } else {
+            1
+        };
+        let mut rng = self.rng_at(comps.over_point);
+        let color: Color = (0..samples)
+            .map(|_| {
+                let ray = glossy_ray(&mirror, comps.normalv, roughness, &mut rng);
+                let color = match self.hit(&ray) {
+                    Some(hit) if hit.t <= self.settings.reflection_distance => {
+                        self.shade_hit(&hit.prepare_computations(&ray), remaining - 1)
+                    }
+                    _ => self.background.color_at(&ray),
+                };
+                if samples > 1 {
+                    clamp_radiance(color, self.settings.firefly_clamp)
+                } else {
+                    color
+                }
+            })
+            .sum();
+
+        color * (reflective / samples as Float)
+    }
+
+    pub fn color_at(&self, ray: &Ray) -> Color {
+        self.color_at_depth(ray, self.settings.reflection_depth)
+    }
+
+    /// `color_at` with `remaining` mirror bounces left, following the same
+    /// contract as `shade_hit`. The path tracer ignores `remaining` and
+    /// bounces up to its own `max_bounces`.
+    pub fn color_at_depth(&self, ray: &Ray, remaining: usize) -> Color {
+        if let Integrator::PathTracer {
+            samples,
+            max_bounces,
+        } = self.settings.integrator
+        {
+            let samples = samples.max(1);
+            let mut rng = Rng::for_stream(
+                self.settings.seed,
+                point_seed(ray.origin) ^ point_seed(ray.direction).rotate_left(7),
+            );
+            let sum: Color = (0..samples)
+                .map(|_| {
+                    clamp_radiance(
+                        self.trace_path(ray, max_bounces, &mut rng),
+                        self.settings.firefly_clamp,
+                    )
+                })
+                .sum();
+            return sum * (1.0 / samples as Float);
+        }
+        if self.settings.integrator == Integrator::Preview {
+            return self.preview_color(ray, 1);
+        }
+        if let Integrator::PhotonMapping { radius, .. } = self.settings.integrator {
+            return self.progressive_photons(ray, radius);
+        }
+
+        match self.hit(ray) {
+            Some(hit) => {
+                let comps = hit.prepare_computations(ray);
+                self.shade_hit(&comps, remaining)
+            }
+            None => self.background.color_at(ray),
+        }
+    }
+
+    /// `color_at` for `Integrator::Preview`, following at most
+    /// `mirror_bounces` reflections.
+    fn preview_color(&self, ray: &Ray, mirror_bounces: usize) -> Color {
+        let hit = match self.hit(ray) {
+            Some(hit) => hit,
+            None => return self.background.color_at(ray),
+        };
+        let comps = hit.prepare_computations(ray);
+        let material = comps.object.get_material();
+        let shadowed = |light: &Light| {
+            light.falloff(comps.point) > 0.0
+                && self.is_occluded(comps.over_point, *light.position())
+        };
+        if material.shadow_catcher.is_some() {
+            let behind = Ray::new(comps.point, ray.direction);
+            let lit = self.lights.iter().filter(|light| !shadowed(light)).count();
+            let lit = if self.lights.is_empty() {
+                1.0
+            } else {
+                lit as Float / self.lights.len() as Float
+            };
+            return self.background.color_at(&behind) * lit;
+        }
+
+        let surface: Color = self
+            .lights
+            .iter()
+            .map(|light| {
+                material.lighting(
+                    comps.object,
+                    *light,
+                    comps.point,
+                    comps.eyev,
+                    comps.normalv,
+                    shadowed(light),
+                )
+            })
+            .sum();
+        if material.reflective > 0.0 && mirror_bounces > 0 {
+            let reflected = self.preview_color(&comps.reflected_ray(), mirror_bounces - 1);
+            surface + reflected * material.reflective
+        } else {
+            surface
+        }
+    }
+
+    /// One camera ray under `Integrator::PhotonMapping`, gathering from the
+    /// maps of every pass. These are traced on the first call and kept until
+    /// the scene or settings change, unlike `Camera::render`, which holds one
+    /// pass's photons at a time.
+    fn progressive_photons(&self, ray: &Ray, radius: Float) -> Color {
+        let mut rng = Rng::for_stream(
+            self.settings.seed,
+            point_seed(ray.origin) ^ point_seed(ray.direction).rotate_left(7),
+        );
+        let mut gather = PhotonGather::new(radius);
+        for map in self.photon_maps() {
+            self.gather_photons(ray, map, &mut gather, &mut rng);
+        }
+
+        gather.estimate()
+    }
+
+    fn photon_maps(&self) -> &[PhotonMap] {
+        self.photon_maps
+            .get_or_init(|| match self.settings.integrator {
+                Integrator::PhotonMapping {
+                    passes,
+                    photons,
+                    radius,
+                } => (0..passes.max(1))
+                    .map(|pass| self.trace_photons(pass, photons, radius))
+                    .collect(),
+                _ => Vec::new(),
+            })
+    }
+
+    /// One photon mapping pass for a camera ray. Follows the ray through
+    /// mirrors to a diffuse surface, adding the direct light met on the way
+    /// to `gather` along with the photons of `map` around the surface.
+    pub fn gather_photons<R: RandomSource>(
+        &self,
+        ray: &Ray,
+        map: &PhotonMap,
+        gather: &mut PhotonGather,
+        rng: &mut R,
+    ) {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let mut direct = black;
+        let mut ray = *ray;
+
+        for bounce in 0..=self.settings.reflection_depth {
+            let hit = match self.hit(&ray) {
+                Some(hit) => hit,
+                None => {
+                    direct = direct + self.background.color_at(&ray);
+                    break;
+                }
+            };
+            let comps = hit.prepare_computations(&ray);
+            let material = comps.object.get_material();
+            if let Some(catcher) = &material.shadow_catcher {
+                let behind = Ray::new(comps.point, ray.direction);
+                direct =
+                    direct + self.background.color_at(&behind) * self.caught_light(&comps, catcher);
+                break;
+            }
+
+            direct = direct + self.direct_light(&comps, false) + self.environment_light(&comps);
+            let last = bounce == self.settings.reflection_depth;
+            if !last && rng.next_float() < material.reflective {
+                ray = glossy_ray(
+                    &comps.reflected_ray(),
+                    comps.normalv,
+                    material.roughness,
+                    rng,
+                );
+                continue;
+            }
+
+            let weight = if last {
+                1.0
+            } else {
+                1.0 / (1.0 - material.reflective)
+            };
+            let albedo = material.color_at(comps.object, comps.point) * (material.diffuse * weight);
+            let mut count = 0;
+            let mut power = black;
+            map.for_each_within(comps.point, gather.radius(), |photon| {
+                if photon.direction.dot(&comps.normalv) < 0.0 {
+                    count += 1;
+                    power = power + photon.power;
+                }
+            });
+            gather.add_pass(direct, count, albedo * power);
+            return;
+        }
+
+        gather.add_pass(direct, 0, black);
+    }
+
+    /// Photons for pass `pass` of `Integrator::PhotonMapping`: each light
+    /// emits `photons` photons, traced in batches spread over the worker
+    /// threads. Batches draw from their own random streams, so the map does
+    /// not depend on the number of threads.
+    pub fn trace_photons(&self, pass: usize, photons: usize, radius: Float) -> PhotonMap {
+        let batches = photons.div_ceil(PHOTON_BATCH);
+        let jobs = self.lights.len() * batches;
+        let stored = parallel_map(jobs, |job| {
+            let (light, batch) = (&self.lights[job / batches], job % batches);
+            let mut rng = Rng::for_stream(self.settings.seed, !((pass * jobs + job) as u64));
+            let count = PHOTON_BATCH.min(photons - batch * PHOTON_BATCH);
+            let mut stored = Vec::new();
+            for _ in 0..count {
+                self.trace_photon(light, photons, &mut rng, &mut stored);
+            }
+
+            stored
+        });
+
+        PhotonMap::new(stored.into_iter().flatten().collect(), radius)
+    }
+
+    /// Follows one of the `photons` photons emitted by `light`, keeping it
+    /// wherever it lands after at least one bounce, as `direct_light`
+    /// already covers the first hit. Point lights here do not dim with
+    /// distance, so the photon's power is scaled by the square of its first
+    /// flight to stay consistent with them.
+    fn trace_photon<R: RandomSource>(
+        &self,
+        light: &Light,
+        photons: usize,
+        rng: &mut R,
+        stored: &mut Vec<Photon>,
+    ) {
+        let direction = uniform_sphere(rng.next_float(), rng.next_float());
+        let mut ray = Ray::new(*light.position(), direction);
+        let mut power = Color::new(0.0, 0.0, 0.0);
+
+        for bounce in 0..=self.settings.reflection_depth {
+            let hit = match self.hit(&ray) {
+                Some(hit) => hit,
+                None => break,
+            };
+            let comps = hit.prepare_computations(&ray);
+            let material = comps.object.get_material();
+            if bounce == 0 {
+                let scale = 4.0 * PI * hit.t * hit.t / photons as Float;
+                power = *light.intensity() * (scale * light.falloff(comps.point));
+            }
+            if material.shadow_catcher.is_some() {
+                break;
+            }
+            if rng.next_float() < material.reflective {
+                ray = glossy_ray(
+                    &comps.reflected_ray(),
+                    comps.normalv,
+                    material.roughness,
+                    rng,
+                );
+                continue;
+            }
+
+            if bounce > 0 {
+                stored.push(Photon {
+                    position: comps.point,
+                    direction: ray.direction,
+                    power,
+                });
+            }
+            let albedo = material.color_at(comps.object, comps.point) * material.diffuse;
+            power = power * albedo * (1.0 / (1.0 - material.reflective));
+            let direction = cosine_hemisphere(comps.normalv, rng.next_float(), rng.next_float());
+            ray = comps.spawn_ray(direction);
+        }
+    }
+
+    /// Bakes the light falling on `object` into a `width` by `height` image
+    /// laid out like `UvMapping::Spherical`, so it can be applied back with a
+    /// `TexturePattern`. Each texel is shaded as if seen head-on, with direct
+    /// and environment light but no reflections, and row 0 holds `v = 1`.
+    pub fn bake_lightmap(&self, object: &Sphere, width: usize, height: usize) -> Canvas {
+        let mut canvas = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let u = (x as Float + 0.5) / width as Float;
+                let v = 1.0 - (y as Float + 0.5) / height as Float;
+                let theta = (0.5 - u) * 2.0 * PI;
+                let phi = (1.0 - v) * PI;
+                let local =
+                    Tuple4::point(phi.sin() * theta.sin(), phi.cos(), phi.sin() * theta.cos());
+                let point = object.get_transform() * local;
+                let normal = object.normal_at(point);
+                let ray = Ray::new(point + normal, -1.0 * normal);
+                let hit = SphereIntersection {
+                    t: 1.0,
+                    sphere: object,
+                };
+                let comps = hit.prepare_computations(&ray);
+                let color = self.direct_light(&comps, true) + self.environment_light(&comps);
+                canvas.put_pixel(color, (x, y));
+            }
+        }
+
+        canvas
+    }
+
+    /// One Monte Carlo estimate of the light arriving along `ray`. Point
+    /// lights and an environment map are sampled directly at every vertex;
+    /// the path then continues in a cosine-weighted diffuse direction, or
+    /// along the mirror direction with probability `reflective`. Ambient
+    /// terms are left out, as indirect bounces take their place.
+    fn trace_path<R: RandomSource>(&self, ray: &Ray, max_bounces: usize, rng: &mut R) -> Color {
+        let environment = match &self.background {
+            Background::Environment(map) => Some(map),
+            _ => None,
+        };
+        let mut radiance = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+        let mut diffuse_pdf = None;
+
+        for bounce in 0..=max_bounces {
+            let hit = match self.hit(&ray) {
+                Some(hit) => hit,
+                None => {
+                    let weight = match (environment, diffuse_pdf) {
+                        (Some(map), Some(pdf)) => power_heuristic(pdf, map.pdf(ray.direction)),
+                        _ => 1.0,
+                    };
+                    radiance = radiance + throughput * self.background.color_at(&ray) * weight;
+                    break;
+                }
+            };
+            let comps = hit.prepare_computations(&ray);
+            let material = comps.object.get_material();
+            if let Some(catcher) = &material.shadow_catcher {
+                let behind = Ray::new(comps.point, ray.direction);
+                radiance = radiance
+                    + throughput
+                        * self.background.color_at(&behind)
+                        * self.caught_light(&comps, catcher);
+                break;
+            }
+
+            let albedo = material.color_at(comps.object, comps.point) * material.diffuse;
+            radiance = radiance + throughput * self.direct_light(&comps, false);
+            if let Some(map) = environment {
+                let (direction, pdf) = map.sample(rng.next_float(), rng.next_float());
+                let cos = direction.dot(&comps.normalv);
+                if cos > 0.0 && pdf > 0.0 && self.hit(&comps.spawn_ray(direction)).is_none() {
+                    let weight = if bounce == max_bounces {
+                        1.0
+                    } else {
+                        power_heuristic(pdf, cos / PI)
+                    };
+                    radiance = radiance
+                        + throughput
+                            * albedo
+                            * map.color_at(direction)
+                            * (cos / (PI * pdf) * weight);
+                }
+            }
+
+            if bounce == max_bounces {
+                break;
+            }
+            if rng.next_float() < material.reflective {
+                ray = glossy_ray(
+                    &comps.reflected_ray(),
+                    comps.normalv,
+                    material.roughness,
+                    rng,
+                );
+                diffuse_pdf = None;
+            } else {
+                let direction =
+                    cosine_hemisphere(comps.normalv, rng.next_float(), rng.next_float());
+                throughput = throughput * albedo * (1.0 / (1.0 - material.reflective));
+                diffuse_pdf = Some(direction.dot(&comps.normalv) / PI);
+                ray = comps.spawn_ray(direction);
+            }
+
+            // Past the roulette depth, dim paths are ended at random and the
+            // survivors boosted to keep the estimate unbiased.
+            if bounce + 1 >= self.settings.roulette_depth {
+                let survival = throughput
+                    .r
+                    .max(throughput.g)
+                    .max(throughput.b)
+                    .clamp(0.05, 1.0);
+                if rng.next_float() >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+
+        radiance
+    }
+}
+
+/// Jitters a mirror ray by a random offset of up to `roughness`, keeping it
+/// above the surface. A roughness of zero returns the mirror ray unchanged.
+fn glossy_ray<R: RandomSource>(mirror: &Ray, normal: Tuple4, roughness: Float, rng: &mut R) -> Ray {
+    if roughness <= 0.0 {
+        return *mirror;
+    }
+
+    let direction = uniform_sphere(rng.next_float(), rng.next_float());
+    let offset = direction * (roughness * rng.next_float().cbrt());
+    let mut direction = (mirror.direction + offset).normalize();
+    let below = direction.dot(&normal);
+    if below < 0.0 {
+        direction = (direction - normal * (2.0 * below)).normalize();
+    }
+
+    Ray::new(mirror.origin, direction)
+}
+
+/// Scales `color` down so no channel exceeds `max`, keeping its hue.
+fn clamp_radiance(color: Color, max: Float) -> Color {
+    let peak = color.r.max(color.g).max(color.b);
+    if peak > max {
+        color * (max / peak)
+    } else {
+        color
+    }
+}
+
+/// Multiple importance sampling weight for a sample drawn with density `a`
+/// when another strategy could have drawn it with density `b`.
+fn power_heuristic(a: Float, b: Float) -> Float {
+    let (a2, b2) = (a * a, b * b);
+    if a2 + b2 == 0.0 {
+        0.0
+    } else {
+        a2 / (a2 + b2)
+    }
+}
+
+/// Stream for per-point sampling. Coordinates are hashed as `f64` so
+/// `f32` builds mix the same 64 bits.
+#[allow(clippy::unnecessary_cast)]
+fn point_seed(point: Tuple4) -> u64 {
+    let bits = |n: Float| (n as f64).to_bits();
+    bits(point.x) ^ bits(point.y).rotate_left(21) ^ bits(point.z).rotate_left(42)
+}
+
+/// Transforms turning the unit sphere into a rod `thickness` across along
+/// each edge of `bounds`. Flat boxes get rods at least as long as thick.
+fn box_edges(bounds: &BoundingBox, thickness: Float) -> Vec<Matrix4x4> {
+    let (min, max) = (bounds.get_min(), bounds.get_max());
+    let (lo, hi) = ([min.x, min.y, min.z], [max.x, max.y, max.z]);
+    let half = thickness / 2.0;
+    let mut edges = Vec::with_capacity(12);
+    for axis in 0..3 {
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        for (at_a, at_b) in [
+            (lo[a], lo[b]),
+            (lo[a], hi[b]),
+            (hi[a], lo[b]),
+            (hi[a], hi[b]),
+        ] {
+            let mut center = [0.0; 3];
+            let mut scale = [half; 3];
+            center[axis] = (lo[axis] + hi[axis]) / 2.0;
+            center[a] = at_a;
+            center[b] = at_b;
+            scale[axis] = ((hi[axis] - lo[axis]) / 2.0).max(half);
+            edges.push(
+                Matrix4x4::translation(center[0], center[1], center[2])
+                    * Matrix4x4::scaling(scale[0], scale[1], scale[2]),
+            );
+        }
+    }
+
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, sync::Arc};
+
+    use crate::{
+        canvas::Canvas,
+        float::{consts::FRAC_1_SQRT_2, Float},
+        lights::{PointLight, SpotLight},
+        materials::Material,
+        matrix::Matrix4x4,
+        settings::Integrator,
+        sphere::SphereIntersection,
+        texture::Texture,
+    };
+
+    use super::*;
+
+    const EPSILON: Float = 1e-5;
+
+    fn colors_equal(a: &Color, b: &Color) -> bool {
+        (a.r - b.r).abs() < EPSILON && (a.g - b.g).abs() < EPSILON && (a.b - b.b).abs() < EPSILON
+    }
+
+    fn default_world() -> World {
+        let mut world = World::new();
+        world.add_light(PointLight::new(
+            Tuple4::point(-10.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let mut s1 = Sphere::new();
+        s1.set_material(Material {
+            color: Color::new(0.8, 1.0, 0.6),
+            diffuse: 0.7,
+            specular: 0.2,
+            ..Default::default()
+        });
+        let mut s2 = Sphere::new();
+        s2.set_transform(Matrix4x4::scaling(0.5, 0.5, 0.5));
+        world.add_object(s1);
+        world.add_object(s2);
+
+        world
+    }
+
+    #[test]
+    fn test_creating_a_world() {
+        let world = World::new();
+
+        assert!(world.get_objects().is_empty());
+        assert!(world.get_lights().is_empty());
+    }
+
+    #[test]
+    fn test_intersect_a_world_with_a_ray() {
+        let world = default_world();
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+
+        let xs = world.intersect(&r);
+
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs[0].t, 4.0);
+        assert_eq!(xs[1].t, 4.5);
+        assert_eq!(xs[2].t, 5.5);
+        assert_eq!(xs[3].t, 6.0);
+    }
+
+    #[test]
+    fn test_intersecting_into_a_reused_buffer() {
+        let world = default_world();
+        let hit = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let miss = Ray::new(Tuple4::point(0.0, 5.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let mut xs = SphereIntersections::default();
+
+        world.intersect_into(&hit, &mut xs);
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs[0].t, 4.0);
+
+        world.intersect_into(&miss, &mut xs);
+        assert!(xs.is_empty());
+
+        world.get_objects()[0].intersect_into(&hit, &mut xs);
+        world.get_objects()[0].intersect_into(&hit, &mut xs);
+        assert_eq!(xs.len(), 2);
+    }
+
+    #[test]
+    fn test_outlining_the_hierarchy_adds_twelve_edges_per_node() {
+        let mut world = World::new();
+        for i in 0..10 {
+            let mut s = Sphere::new();
+            s.set_transform(Matrix4x4::translation(3.0 * i as Float, 0.0, 0.0));
+            world.add_object(s);
+        }
+
+        let outlined = world.with_bvh_bounds(0.1, Palette::Viridis);
+
+        let nodes = world.bvh().node_bounds();
+        assert_eq!(outlined.get_objects().len(), 10 + 12 * nodes.len());
+        assert_eq!(world.get_objects().len(), 10);
+        let root = outlined.get_objects()[10].get_material().color;
+        let leaf = outlined.get_objects().last().unwrap().get_material().color;
+        assert_eq!(root, Palette::Viridis.color(0.0));
+        assert_eq!(leaf, Palette::Viridis.color(1.0));
+    }
+
+    #[test]
+    fn test_an_outline_edge_runs_along_the_box() {
+        let mut world = World::new();
+        world.add_object(Sphere::new());
+        let outlined = world.with_bvh_bounds(0.1, Palette::Grayscale);
+
+        let along = Ray::new(
+            Tuple4::point(-5.0, 1.0, -1.0),
+            Tuple4::vector(1.0, 0.0, 0.0),
+        );
+        let beside = Ray::new(
+            Tuple4::point(-5.0, 0.8, -0.8),
+            Tuple4::vector(1.0, 0.0, 0.0),
+        );
+
+        assert!((outlined.hit(&along).unwrap().t - 4.0).abs() < EPSILON);
+        assert!(outlined.hit(&beside).is_none());
+    }
+
+    #[test]
+    fn test_the_hit_matches_the_full_intersection_list() {
+        let world = default_world();
+        let rays = [
+            Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0)),
+            Ray::new(Tuple4::point(0.0, 0.0, 0.0), Tuple4::vector(0.0, 0.0, 1.0)),
+            Ray::new(Tuple4::point(0.0, 0.0, 0.75), Tuple4::vector(0.0, 1.0, 0.0)),
+            Ray::new(Tuple4::point(0.0, 5.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0)),
+        ];
+
+        for ray in rays {
+            let expected = world.intersect(&ray).hit().map(|hit| hit.t);
+            assert_eq!(world.hit(&ray).map(|hit| hit.t), expected);
+        }
+    }
+
+    #[test]
+    fn test_caching_the_hierarchy_on_disk() {
+        let path = env::temp_dir().join(format!("ray-tracer-bvh-{}.bin", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let mut world = default_world();
+
+        let built = world.cache_bvh(&path).unwrap();
+        let mut reloaded = default_world();
+        let cached = reloaded.cache_bvh(&path).unwrap();
+        reloaded.add_object(Sphere::new());
+        let rebuilt = reloaded.cache_bvh(&path).unwrap();
+
+        assert!(!built && cached && !rebuilt);
+        assert_eq!(reloaded.intersect(&r).len(), 6);
+        let mut fresh = default_world();
+        assert!(!fresh.cache_bvh(&path).unwrap());
+        assert_eq!(fresh.intersect(&r).len(), world.intersect(&r).len());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_shading_an_intersection() {
+        let world = default_world();
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let shape = &world.get_objects()[0];
+        let i = SphereIntersection::new(4.0, shape);
+
+        let comps = i.prepare_computations(&r);
+        let c = world.shade_hit(&comps, 1);
+
+        assert!(colors_equal(&c, &Color::new(0.38066, 0.47583, 0.2855)));
+    }
+
+    #[test]
+    fn test_shading_an_intersection_from_the_inside() {
+        let mut world = default_world();
+        world.lights =
+            vec![PointLight::new(Tuple4::point(0.0, 0.25, 0.0), Color::new(1.0, 1.0, 1.0)).into()];
+        let r = Ray::new(Tuple4::point(0.0, 0.0, 0.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let shape = &world.get_objects()[1];
+        let i = SphereIntersection::new(0.5, shape);
+
+        let comps = i.prepare_computations(&r);
+        let c = world.shade_hit(&comps, 1);
+
+        assert!(colors_equal(&c, &Color::new(0.90498, 0.90498, 0.90498)));
+    }
+
+    #[test]
+    fn test_shade_hit_is_given_an_intersection_in_shadow() {
+        let mut world = World::new();
+        world.add_light(PointLight::new(
+            Tuple4::point(0.0, 0.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        world.add_object(Sphere::new());
+        let mut s2 = Sphere::new();
+        s2.set_transform(Matrix4x4::translation(0.0, 0.0, 10.0));
+        world.add_object(s2);
+        let r = Ray::new(Tuple4::point(0.0, 0.0, 5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let i = SphereIntersection::new(4.0, &world.get_objects()[1]);
+
+        let comps = i.prepare_computations(&r);
+        let c = world.shade_hit(&comps, 1);
+
+        assert_eq!(c, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn test_the_color_when_a_ray_misses() {
+        let world = default_world();
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 1.0, 0.0));
+
+        let c = world.color_at(&r);
+
+        assert_eq!(c, Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_the_color_when_a_ray_hits() {
+        let world = default_world();
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+
+        let c = world.color_at(&r);
+
+        assert!(colors_equal(&c, &Color::new(0.38066, 0.47583, 0.2855)));
+    }
+
+    #[test]
+    fn test_the_color_with_an_intersection_behind_the_ray() {
+        let mut world = default_world();
+        let ambient = |color| Material {
+            color,
+            ambient: 1.0,
+            ..Default::default()
+        };
+        world.objects[0].set_material(ambient(Color::new(0.8, 1.0, 0.6)));
+        world.objects[1].set_material(ambient(Color::new(1.0, 1.0, 1.0)));
+        let r = Ray::new(
+            Tuple4::point(0.0, 0.0, 0.75),
+            Tuple4::vector(0.0, 0.0, -1.0),
+        );
+
+        let c = world.color_at(&r);
+
+        assert_eq!(c, world.objects[1].get_material().color);
+    }
+
+    #[test]
+    fn test_there_is_no_shadow_when_nothing_is_collinear_with_point_and_light() {
+        let world = default_world();
+        let p = Tuple4::point(0.0, 10.0, 0.0);
+
+        assert!(!world.is_shadowed(&world.get_lights()[0], p));
+    }
+
+    #[test]
+    fn test_the_shadow_when_an_object_is_between_the_point_and_the_light() {
+        let world = default_world();
+        let p = Tuple4::point(10.0, -10.0, 10.0);
+
+        assert!(world.is_shadowed(&world.get_lights()[0], p));
+    }
+
+    #[test]
+    fn test_there_is_no_shadow_when_an_object_is_behind_the_light() {
+        let world = default_world();
+        let p = Tuple4::point(-20.0, 20.0, -20.0);
+
+        assert!(!world.is_shadowed(&world.get_lights()[0], p));
+    }
+
+    #[test]
+    fn test_there_is_no_shadow_when_an_object_is_behind_the_point() {
+        let world = default_world();
+        let p = Tuple4::point(-2.0, 2.0, -2.0);
+
+        assert!(!world.is_shadowed(&world.get_lights()[0], p));
+    }
+
+    #[test]
+    fn test_summary_of_an_empty_world() {
+        let world = World::new();
+
+        let summary = world.summary();
+
+        assert_eq!(summary.spheres, 0);
+        assert_eq!(summary.lights, 0);
+        assert_eq!(summary.extent, None);
+    }
+
+    #[test]
+    fn test_summary_counts_objects_and_lights_and_measures_the_extent() {
+        let mut world = default_world();
+        let mut s3 = Sphere::new();
+        s3.set_transform(Matrix4x4::translation(5.0, 0.0, 0.0) * Matrix4x4::scaling(2.0, 1.0, 1.0));
+        world.add_object(s3);
+
+        let summary = world.summary();
+
+        assert_eq!(summary.spheres, 3);
+        assert_eq!(summary.lights, 1);
+        assert_eq!(
+            summary.extent,
+            Some((
+                Tuple4::point(-1.0, -1.0, -1.0),
+                Tuple4::point(7.0, 1.0, 1.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_intersecting_many_objects_through_the_hierarchy() {
+        let mut world = World::new();
+        for i in 0..200 {
+            let mut s = Sphere::new();
+            let (x, z) = ((i % 20) as Float * 3.0, (i / 20) as Float * 3.0);
+            s.set_transform(Matrix4x4::translation(x, 0.0, z));
+            world.add_object(s);
+        }
+        let r = Ray::new(
+            Tuple4::point(-5.0, 0.0, -5.0),
+            Tuple4::vector(1.0, 0.0, 1.0).normalize(),
+        );
+
+        let xs = world.intersect(&r);
+
+        let expected: usize = world
+            .get_objects()
+            .iter()
+            .map(|s| s.intersect(&r).into_iter().filter(|x| x.t >= 0.0).count())
+            .sum();
+        assert_eq!(xs.len(), expected);
+        assert_eq!(xs.len(), 20);
+    }
+
+    #[test]
+    fn test_extent_of_a_rotated_sphere_is_tight() {
+        let mut world = World::new();
+        let mut s = Sphere::new();
+        s.set_transform(
+            Matrix4x4::rotation_z(crate::float::consts::PI / 2.0)
+                * Matrix4x4::scaling(3.0, 1.0, 1.0),
+        );
+        world.add_object(s);
+
+        let (min, max) = world.summary().extent.unwrap();
+
+        assert!((min.x + 1.0).abs() < EPSILON && (max.x - 1.0).abs() < EPSILON);
+        assert!((min.y + 3.0).abs() < EPSILON && (max.y - 3.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_spot_light_casts_shadows() {
+        let mut world = World::new();
+        world.add_light(SpotLight::new(
+            Tuple4::point(0.0, 0.0, -10.0),
+            Tuple4::vector(0.0, 0.0, 1.0),
+            Color::new(1.0, 1.0, 1.0),
+            crate::float::consts::PI / 8.0,
+            crate::float::consts::PI / 4.0,
+        ));
+        world.add_object(Sphere::new());
+        let mut s2 = Sphere::new();
+        s2.set_transform(Matrix4x4::translation(0.0, 0.0, 10.0));
+        world.add_object(s2);
+        let r = Ray::new(Tuple4::point(0.0, 0.0, 5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let i = SphereIntersection::new(4.0, &world.get_objects()[1]);
+
+        let comps = i.prepare_computations(&r);
+        let c = world.shade_hit(&comps, 1);
+
+        assert_eq!(c, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn test_the_color_when_a_ray_misses_uses_the_background_color() {
+        let mut world = default_world();
+        world.set_background(Background::Color(Color::new(0.2, 0.4, 0.6)));
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 1.0, 0.0));
+
+        let c = world.color_at(&r);
+
+        assert_eq!(c, Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn test_the_color_when_a_ray_misses_uses_the_miss_shader() {
+        let mut world = default_world();
+        world.set_background(Background::shader(|ray| {
+            let d = ray.direction;
+            Color::new(d.x.abs(), d.y.abs(), d.z.abs())
+        }));
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 1.0, 0.0));
+
+        let c = world.color_at(&r);
+
+        assert_eq!(c, Color::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_the_sky_background_blends_towards_the_zenith() {
+        let mut world = World::new();
+        world.set_background(Background::Sky {
+            horizon: Color::new(1.0, 1.0, 1.0),
+            zenith: Color::new(0.0, 0.0, 1.0),
+        });
+        let color = |x, y, z| {
+            world.color_at(&Ray::new(
+                Tuple4::point(0.0, 0.0, 0.0),
+                Tuple4::vector(x, y, z),
+            ))
+        };
+
+        assert_eq!(color(0.0, 0.0, 1.0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(color(0.0, 2.0, 0.0), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(color(0.0, -1.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        assert!((color(0.0, 1.0, 1.0).r - (1.0 - FRAC_1_SQRT_2)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_the_environment_background_is_looked_up_by_direction() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.put_pixel(Color::new(0.0, 0.0, 1.0), (0, 0));
+        canvas.put_pixel(Color::new(0.0, 0.0, 1.0), (1, 0));
+        canvas.put_pixel(Color::new(0.0, 1.0, 0.0), (0, 1));
+        canvas.put_pixel(Color::new(0.0, 1.0, 0.0), (1, 1));
+        let mut world = World::new();
+        world.set_background(Background::Environment(EnvironmentMap::new(Arc::new(
+            Texture::from_canvas(&canvas),
+        ))));
+        let color = |y| {
+            world.color_at(&Ray::new(
+                Tuple4::point(0.0, 0.0, 0.0),
+                Tuple4::vector(0.3, y, 0.2),
+            ))
+        };
+
+        assert_eq!(color(1.0), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(color(-1.0), Color::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_a_uniform_environment_lights_an_unoccluded_surface() {
+        let mut canvas = Canvas::new(8, 4);
+        for y in 0..4 {
+            for x in 0..8 {
+                canvas.put_pixel(Color::new(1.0, 1.0, 1.0), (x, y));
+            }
+        }
+        let mut world = World::new();
+        world.set_background(Background::Environment(EnvironmentMap::new(Arc::new(
+            Texture::from_canvas(&canvas),
+        ))));
+        world.set_settings(RenderSettings {
+            environment_samples: 4096,
+            ..Default::default()
+        });
+        let mut s = Sphere::new();
+        s.set_material(Material {
+            ambient: 0.0,
+            diffuse: 0.5,
+            specular: 0.0,
+            ..Default::default()
+        });
+        world.add_object(s);
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+
+        let c = world.color_at(&r);
+
+        assert!((c.r - 0.5).abs() < 0.03, "{:?}", c);
+    }
+
+    #[test]
+    fn test_environment_light_is_off_without_samples() {
+        let mut world = default_world();
+        world.set_background(Background::Environment(EnvironmentMap::new(Arc::new(
+            Texture::from_canvas(&Canvas::new(1, 1)),
+        ))));
+        world.set_settings(RenderSettings {
+            environment_samples: 0,
+            ..Default::default()
+        });
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+
+        let c = world.color_at(&r);
+
+        assert!(colors_equal(&c, &Color::new(0.38066, 0.47583, 0.2855)));
+    }
+
+    fn furnace(samples: usize, max_bounces: usize) -> World {
+        let mut canvas = Canvas::new(8, 4);
+        for y in 0..4 {
+            for x in 0..8 {
+                canvas.put_pixel(Color::new(1.0, 1.0, 1.0), (x, y));
+            }
+        }
+        let mut world = World::new();
+        world.set_background(Background::Environment(EnvironmentMap::new(Arc::new(
+            Texture::from_canvas(&canvas),
+        ))));
+        world.set_settings(RenderSettings {
+            integrator: Integrator::PathTracer {
+                samples,
+                max_bounces,
+            },
+            ..Default::default()
+        });
+        let mut s = Sphere::new();
+        s.set_material(Material {
+            color: Color::new(0.5, 0.5, 0.5),
+            diffuse: 1.0,
+            specular: 0.0,
+            ..Default::default()
+        });
+        world.add_object(s);
+        world
+    }
+
+    #[test]
+    fn test_a_path_traced_sphere_in_a_white_furnace_shows_its_albedo() {
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+
+        for max_bounces in [0, 3] {
+            let c = furnace(4096, max_bounces).color_at(&r);
+            assert!((c.r - 0.5).abs() < 0.03, "{} {:?}", max_bounces, c);
+        }
+    }
+
+    #[test]
+    fn test_russian_roulette_keeps_the_estimate_unbiased() {
+        let mut world = furnace(4096, 16);
+        world.set_settings(RenderSettings {
+            roulette_depth: 0,
+            ..*world.get_settings()
+        });
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+
+        let c = world.color_at(&r);
+
+        assert!((c.r - 0.5).abs() < 0.03, "{:?}", c);
+    }
+
+    #[test]
+    fn test_photon_mapping_matches_a_path_traced_reference() {
+        let mut world = World::new();
+        world.add_light(PointLight::new(
+            Tuple4::point(-1.0, 2.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let matte = |color: Color| Material {
+            color,
+            ambient: 0.0,
+            diffuse: 0.8,
+            specular: 0.0,
+            ..Default::default()
+        };
+        let mut floor = Sphere::new();
+        floor.set_transform(Matrix4x4::scaling(10.0, 0.01, 10.0));
+        floor.set_material(matte(Color::new(1.0, 1.0, 1.0)));
+        world.add_object(floor);
+        let mut wall = Sphere::new();
+        wall.set_transform(
+            Matrix4x4::translation(1.0, 0.0, 0.0) * Matrix4x4::scaling(0.01, 10.0, 10.0),
+        );
+        wall.set_material(matte(Color::new(1.0, 0.2, 0.2)));
+        world.add_object(wall);
+        let r = Ray::new(
+            Tuple4::point(0.0, 1.0, -1.0),
+            Tuple4::vector(0.0, -1.0, 1.0).normalize(),
+        );
+        let render = |world: &mut World, integrator| {
+            world.set_settings(RenderSettings {
+                reflection_depth: 1,
+                integrator,
+                ..Default::default()
+            });
+            world.color_at(&r)
+        };
+
+        let direct = render(&mut world, Integrator::Whitted);
+        let reference = render(
+            &mut world,
+            Integrator::PathTracer {
+                samples: 20000,
+                max_bounces: 1,
+            },
+        );
+        let photons = render(
+            &mut world,
+            Integrator::PhotonMapping {
+                passes: 16,
+                photons: 50000,
+                radius: 0.1,
+            },
+        );
+
+        // The red wall adds about 0.2 of indirect light to the floor.
+        assert!(reference.r - direct.r > 0.15, "{:?}", reference);
+        assert!((photons.r - reference.r).abs() < 0.02, "{:?}", photons);
+        assert!((photons.g - reference.g).abs() < 0.02, "{:?}", photons);
+        assert_eq!(photons.g, photons.b);
+    }
+
+    #[test]
+    fn test_photon_maps_are_traced_once_per_scene() {
+        let mut world = World::new();
+        world.add_light(PointLight::new(
+            Tuple4::point(0.0, 5.0, -5.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        world.add_object(Sphere::new());
+        world.set_settings(RenderSettings {
+            integrator: Integrator::PhotonMapping {
+                passes: 3,
+                photons: 100,
+                radius: 0.1,
+            },
+            ..Default::default()
+        });
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+
+        let first = world.color_at(&r);
+        let maps = world.photon_maps().as_ptr();
+
+        assert_eq!(world.color_at(&r), first);
+        assert_eq!(world.photon_maps().as_ptr(), maps);
+        assert_eq!(world.photon_maps().len(), 3);
+
+        world.add_light(PointLight::new(
+            Tuple4::point(0.0, -5.0, -5.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+
+        assert!(world.photon_maps.get().is_none());
+    }
+
+    #[test]
+    fn test_resampled_lights_average_to_the_full_sum() {
+        let mut world = World::new();
+        for i in 0..64 {
+            let angle = i as Float * 0.3;
+            world.add_light(PointLight::new(
+                Tuple4::point(6.0 * angle.cos(), 6.0 * angle.sin(), -8.0),
+                Color::new(0.05, 0.04, 0.03) * (1.0 + (i % 5) as Float),
+            ));
+        }
+        world.add_object(Sphere::new());
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let full = world.color_at(&r);
+
+        let seeds = 256;
+        let mut sum = ColorSum::new();
+        for seed in 0..seeds {
+            world.set_settings(RenderSettings {
+                light_candidates: 8,
+                seed,
+                ..Default::default()
+            });
+            sum.add(world.color_at(&r));
+        }
+        let mean = sum.total() * (1.0 / seeds as Float);
+
+        assert!(
+            (mean.r - full.r).abs() < 0.03 * full.r,
+            "{:?} {:?}",
+            mean,
+            full
+        );
+        assert!(
+            (mean.b - full.b).abs() < 0.03 * full.b,
+            "{:?} {:?}",
+            mean,
+            full
+        );
+    }
+
+    #[test]
+    fn test_the_firefly_clamp_limits_each_sample() {
+        let mut world = furnace(16, 2);
+        world.set_background(Background::Color(Color::new(8.0, 4.0, 2.0)));
+        world.set_settings(RenderSettings {
+            firefly_clamp: 2.0,
+            ..*world.get_settings()
+        });
+        let miss = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 1.0, 0.0));
+
+        assert_eq!(world.color_at(&miss), Color::new(2.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn test_path_tracing_is_deterministic() {
+        let world = furnace(4, 2);
+        let r = Ray::new(Tuple4::point(0.1, 0.2, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+
+        assert_eq!(world.color_at(&r), world.color_at(&r));
+    }
+
+    #[test]
+    fn test_path_traced_misses_see_the_background() {
+        let mut world = furnace(4, 2);
+        world.set_background(Background::Color(Color::new(0.2, 0.4, 0.6)));
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 1.0, 0.0));
+
+        assert_eq!(world.color_at(&r), Color::new(0.2, 0.4, 0.6));
+    }
+
+    fn catcher_world() -> World {
+        let mut world = World::new();
+        world.set_background(Background::Color(Color::new(0.2, 0.4, 0.6)));
+        let mut floor = Sphere::new();
+        floor.set_transform(Matrix4x4::scaling(10.0, 0.01, 10.0));
+        floor.set_material(Material {
+            shadow_catcher: Some(ShadowCatcher { ao_distance: 1.0 }),
+            ..Default::default()
+        });
+        world.add_object(floor);
+        world.add_light(PointLight::new(
+            Tuple4::point(0.0, 10.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        world
+    }
+
+    #[test]
+    fn test_an_unshadowed_catcher_shows_the_background() {
+        let world = catcher_world();
+        let r = Ray::new(Tuple4::point(3.0, 5.0, 0.0), Tuple4::vector(0.0, -1.0, 0.0));
+
+        assert_eq!(world.color_at(&r), Color::new(0.2, 0.4, 0.6));
+        assert_eq!(world.alpha_at(&r), 0.0);
+    }
+
+    #[test]
+    fn test_a_catcher_darkens_where_it_is_shadowed() {
+        let mut world = catcher_world();
+        let mut ball = Sphere::new();
+        ball.set_transform(Matrix4x4::translation(0.0, 3.0, 0.0));
+        world.add_object(ball);
+        let shadowed = Ray::new(
+            Tuple4::point(0.0, 0.5, -5.0),
+            Tuple4::vector(0.0, -0.49, 5.0),
+        );
+        let ball_ray = Ray::new(Tuple4::point(0.0, 3.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+
+        assert_eq!(world.color_at(&shadowed), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(world.alpha_at(&shadowed), 1.0);
+        assert_eq!(world.alpha_at(&ball_ray), 1.0);
+    }
+
+    #[test]
+    fn test_nearby_occluders_darken_a_catcher() {
+        let mut world = catcher_world();
+        world.lights.clear();
+        let mut wall = Sphere::new();
+        wall.set_transform(
+            Matrix4x4::translation(0.0, 0.5, 0.0) * Matrix4x4::scaling(0.3, 0.3, 0.3),
+        );
+        world.add_object(wall);
+        let near = Ray::new(
+            Tuple4::point(0.35, 5.0, 0.0),
+            Tuple4::vector(0.0, -1.0, 0.0),
+        );
+        let far = Ray::new(Tuple4::point(3.0, 5.0, 0.0), Tuple4::vector(0.0, -1.0, 0.0));
+
+        let alpha = world.alpha_at(&near);
+
+        assert!(alpha > 0.0 && alpha < 1.0, "{}", alpha);
+        assert_eq!(world.alpha_at(&far), 0.0);
+    }
+
+    fn glossy_world(roughness: Float, glossy_samples: usize) -> World {
+        let mut world = World::new();
+        world.set_settings(RenderSettings {
+            glossy_samples,
+            ..Default::default()
+        });
+        world.set_background(Background::shader(|ray| {
+            if ray.direction.x > 0.0 {
+                Color::new(1.0, 1.0, 1.0)
+            } else {
+                Color::new(0.0, 0.0, 0.0)
+            }
+        }));
+        let mut floor = mirror(0.0);
+        floor.set_material(Material {
+            color: Color::new(0.0, 0.0, 0.0),
+            ambient: 0.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            reflective: 1.0,
+            roughness,
+            ..Default::default()
+        });
+        world.add_object(floor);
+        world
+    }
+
+    #[test]
+    fn test_rough_reflections_blur_the_reflected_scene() {
+        let r = Ray::new(
+            Tuple4::point(0.0, 1.0, -1.0),
+            Tuple4::vector(0.0, -1.0, 1.0),
+        );
+
+        let sharp = glossy_world(0.0, 64).color_at(&r);
+        let blurry = glossy_world(0.8, 64).color_at(&r);
+
+        assert_eq!(sharp, Color::new(0.0, 0.0, 0.0));
+        assert!(blurry.r > 0.2 && blurry.r < 0.8, "{:?}", blurry);
+    }
+
+    #[test]
+    fn test_glossy_samples_are_deterministic() {
+        let world = glossy_world(0.5, 8);
+        let r = Ray::new(
+            Tuple4::point(0.0, 1.0, -1.0),
+            Tuple4::vector(0.0, -1.0, 1.0),
+        );
+
+        assert_eq!(world.color_at(&r), world.color_at(&r));
+    }
+
+    #[test]
+    fn test_the_miss_shader_is_not_used_for_hits() {
+        let mut world = default_world();
+        world.set_background(Background::shader(|_| Color::new(1.0, 0.0, 1.0)));
+        let r = Ray::new(Tuple4::point(0.0, 0.0, -5.0), Tuple4::vector(0.0, 0.0, 1.0));
+
+        let c = world.color_at(&r);
+
+        assert!(colors_equal(&c, &Color::new(0.38066, 0.47583, 0.2855)));
+    }
+
+    fn mirror(y: Float) -> Sphere {
+        let mut s = Sphere::new();
+        s.set_transform(Matrix4x4::translation(0.0, y, 0.0) * Matrix4x4::scaling(10.0, 0.01, 10.0));
+        s.set_material(Material {
+            reflective: 1.0,
+            ..Default::default()
+        });
+        s
+    }
+
+    #[test]
+    fn test_the_reflected_color_for_a_nonreflective_material() {
+        let mut world = default_world();
+        world.objects[1].set_material(Material {
+            ambient: 1.0,
+            ..Default::default()
+        });
+        let r = Ray::new(Tuple4::point(0.0, 0.0, 0.0), Tuple4::vector(0.0, 0.0, 1.0));
+        let i = SphereIntersection::new(1.0, &world.get_objects()[1]);
+
+        let comps = i.prepare_computations(&r);
+
+        assert_eq!(world.reflected_color(&comps, 5), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_shade_hit_adds_the_reflected_color() {
+        let mut world = default_world();
+        let mut floor = mirror(-1.0);
+        floor.set_material(Material {
+            reflective: 0.5,
+            ..Default::default()
+        });
+        world.add_object(floor);
+        let r = Ray::new(
+            Tuple4::point(0.0, 0.0, -3.0),
+            Tuple4::vector(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+        );
+        let xs = world.intersect(&r);
+        let comps = xs.hit().unwrap().prepare_computations(&r);
+
+        let reflected = world.reflected_color(&comps, 1);
+        let shaded = world.shade_hit(&comps, 1);
+
+        assert!(reflected.r > 0.0 && reflected.g > 0.0 && reflected.b > 0.0);
+        assert!(colors_equal(
+            &(shaded - reflected),
+            &world.shade_hit(&comps, 0)
+        ));
+    }
+
+    #[test]
+    fn test_the_reflected_color_at_the_maximum_recursive_depth() {
+        let mut world = default_world();
+        world.add_object(mirror(-1.0));
+        let r = Ray::new(
+            Tuple4::point(0.0, 0.0, -3.0),
+            Tuple4::vector(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+        );
+        let xs = world.intersect(&r);
+        let comps = xs.hit().unwrap().prepare_computations(&r);
+
+        assert_eq!(world.reflected_color(&comps, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_color_at_depth_limits_the_reflections() {
+        let mut world = default_world();
+        world.add_object(mirror(-1.0));
+        let r = Ray::new(
+            Tuple4::point(0.0, 0.0, -3.0),
+            Tuple4::vector(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+        );
+        let xs = world.intersect(&r);
+        let comps = xs.hit().unwrap().prepare_computations(&r);
+
+        assert_eq!(world.color_at_depth(&r, 0), world.shade_hit(&comps, 0));
+        assert_eq!(world.color_at_depth(&r, 2), world.shade_hit(&comps, 2));
+        assert_eq!(
+            world.color_at(&r),
+            world.color_at_depth(&r, world.get_settings().reflection_depth)
+        );
+        assert_ne!(world.color_at_depth(&r, 0), world.color_at_depth(&r, 1));
+    }
+
+    #[test]
+    fn test_the_preview_integrator_matches_whitted_for_hard_lights() {
+        let mut world = default_world();
+        let mut floor = mirror(-1.0);
+        floor.set_material(Material {
+            reflective: 0.5,
+            ..Default::default()
+        });
+        world.add_object(floor);
+        let r = Ray::new(
+            Tuple4::point(0.0, 0.0, -3.0),
+            Tuple4::vector(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+        );
+        let whitted = world.color_at_depth(&r, 1);
+
+        world.set_settings(RenderSettings::preview());
+
+        assert!(colors_equal(&world.color_at(&r), &whitted));
+    }
+
+    #[test]
+    fn test_the_preview_integrator_ignores_light_radius() {
+        let mut world = default_world();
+        let mut floor = mirror(-1.0);
+        floor.set_material(Material::default());
+        world.add_object(floor);
+        // Straight down into the penumbra the light casts once it has a radius.
+        let r = Ray::new(Tuple4::point(2.3, 1.0, 1.0), Tuple4::vector(0.0, -1.0, 0.0));
+        world.set_settings(RenderSettings::preview());
+        let hard = world.color_at(&r);
+
+        if let Light::Point(light) = &mut world.lights[0] {
+            light.set_radius(2.0);
+        }
+        let preview = world.color_at(&r);
+        world.set_settings(RenderSettings::default());
+        let soft = world.color_at(&r);
+
+        assert_eq!(preview, hard);
+        assert!(soft.r > hard.r, "{:?} {:?}", soft, hard);
+    }
+
+    #[test]
+    fn test_color_at_with_mutually_reflective_surfaces() {
+        let mut world = World::new();
+        world.add_light(PointLight::new(
+            Tuple4::point(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        world.add_object(mirror(-1.0));
+        world.add_object(mirror(1.0));
+        let r = Ray::new(Tuple4::point(0.0, 0.0, 0.0), Tuple4::vector(0.0, 1.0, 0.0));
+
+        let c = world.color_at(&r);
+
+        assert!(c.r > 0.0);
+    }
+
+    #[test]
+    fn test_reflections_beyond_the_reflection_distance_see_the_background() {
+        let mut world = default_world();
+        world.add_object(mirror(-1.0));
+        world.set_background(Background::Color(Color::new(0.2, 0.4, 0.6)));
+        world.set_settings(RenderSettings {
+            reflection_distance: 0.5,
+            ..Default::default()
+        });
+        let r = Ray::new(
+            Tuple4::point(0.0, 0.0, -3.0),
+            Tuple4::vector(0.0, -FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+        );
+        let xs = world.intersect(&r);
+        let comps = xs.hit().unwrap().prepare_computations(&r);
+
+        assert_eq!(world.reflected_color(&comps, 1), Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn test_point_lights_cast_hard_shadows() {
+        let world = default_world();
+
+        let lit = world.light_visibility(&world.get_lights()[0], Tuple4::point(0.0, 10.0, 0.0));
+        let dark = world.light_visibility(&world.get_lights()[0], Tuple4::point(10.0, -10.0, 10.0));
+
+        assert_eq!(lit, 1.0);
+        assert_eq!(dark, 0.0);
+    }
+
+    #[test]
+    fn test_area_lights_cast_soft_shadows() {
+        let mut world = World::new();
+        let mut light = PointLight::new(Tuple4::point(0.0, 5.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        light.set_radius(1.0);
+        world.add_light(light);
+        let mut blocker = Sphere::new();
+        blocker.set_transform(
+            Matrix4x4::translation(0.0, 2.5, 0.0) * Matrix4x4::scaling(0.5, 0.5, 0.5),
+        );
+        world.add_object(blocker);
+        let light = world.get_lights()[0];
+
+        let penumbra = world.light_visibility(&light, Tuple4::point(0.9, 0.0, 0.0));
+
+        assert!(penumbra > 0.0 && penumbra < 1.0);
+        assert_eq!(
+            penumbra,
+            world.light_visibility(&light, Tuple4::point(0.9, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn test_the_render_seed_changes_the_noise() {
+        let mut world = World::new();
+        let mut light = PointLight::new(Tuple4::point(0.0, 5.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        light.set_radius(1.0);
+        world.add_light(light);
+        let mut blocker = Sphere::new();
+        blocker.set_transform(
+            Matrix4x4::translation(0.0, 2.5, 0.0) * Matrix4x4::scaling(0.5, 0.5, 0.5),
+        );
+        world.add_object(blocker);
+        let light = world.get_lights()[0];
+        let penumbra = |world: &World| {
+            (0..8)
+                .map(|i| {
+                    world.light_visibility(&light, Tuple4::point(0.8 + i as Float * 0.05, 0.0, 0.0))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = penumbra(&world);
+        world.set_settings(RenderSettings {
+            seed: 7,
+            ..*world.get_settings()
+        });
+
+        assert_ne!(first, penumbra(&world));
+        assert_eq!(penumbra(&world), penumbra(&world));
+    }
+
+    #[test]
+    fn test_samplers_accept_any_random_source() {
+        struct Fixed(u64);
+        impl RandomSource for Fixed {
+            fn next_u64(&mut self) -> u64 {
+                self.0
+            }
+        }
+        let mirror = Ray::new(Tuple4::point(0.0, 0.0, 0.0), Tuple4::vector(0.0, 1.0, 0.0));
+        let normal = Tuple4::vector(0.0, 1.0, 0.0);
+
+        let a = glossy_ray(&mirror, normal, 0.5, &mut Fixed(u64::MAX / 3));
+        let b = glossy_ray(&mirror, normal, 0.5, &mut Fixed(u64::MAX / 3));
+
+        assert_eq!(a.direction, b.direction);
+        assert!(a.direction.dot(&normal) > 0.0);
+    }
+
+    #[test]
+    fn test_a_single_shadow_sample_keeps_shadows_hard() {
+        let mut world = World::new();
+        world.set_settings(RenderSettings::draft());
+        let mut light = PointLight::new(Tuple4::point(0.0, 5.0, 0.0), Color::new(1.0, 1.0, 1.0));
+        light.set_radius(1.0);
+        world.add_light(light);
+        let mut blocker = Sphere::new();
+        blocker.set_transform(
+            Matrix4x4::translation(0.0, 2.5, 0.0) * Matrix4x4::scaling(0.5, 0.5, 0.5),
+        );
+        world.add_object(blocker);
+        let light = world.get_lights()[0];
+
+        let visibility = world.light_visibility(&light, Tuple4::point(0.9, 0.0, 0.0));
+
+        assert!(visibility == 0.0 || visibility == 1.0);
+    }
+
+    #[test]
+    fn test_baking_a_lightmap_for_a_sphere_lit_from_above() {
+        let mut world = World::new();
+        world.add_light(PointLight::new(
+            Tuple4::point(0.0, 10.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let mut sphere = Sphere::new();
+        sphere.set_transform(Matrix4x4::translation(3.0, 0.0, 0.0));
+        world.add_object(sphere.clone());
+
+        let lightmap = world.bake_lightmap(&sphere, 8, 4);
+
+        assert_eq!(lightmap.get_width(), 8);
+        assert_eq!(lightmap.get_height(), 4);
+        let top = lightmap.get_pixel((3, 0));
+        let bottom = lightmap.get_pixel((3, 3));
+        assert!(top.r > 0.5);
+        assert!((bottom.r - 0.1).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_raycasting_without_rendering() {
+        let world = default_world();
+        let origin = Tuple4::point(0.0, 0.0, -5.0);
+
+        let hit = world
+            .raycast(origin, Tuple4::vector(0.0, 0.0, 2.0), 10.0)
+            .unwrap();
+
+        assert!((hit.distance - 4.0).abs() < EPSILON);
+        assert_eq!(hit.point, Tuple4::point(0.0, 0.0, -1.0));
+        assert_eq!(hit.normal, Tuple4::vector(0.0, 0.0, -1.0));
+        assert_eq!(hit.object, &world.get_objects()[0]);
+        assert!(world
+            .raycast(origin, Tuple4::vector(0.0, 0.0, 1.0), 3.0)
+            .is_none());
+        assert!(world
+            .raycast(
+                Tuple4::point(0.0, 0.0, 0.0),
+                Tuple4::vector(0.0, 0.0, 0.0),
+                10.0
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_line_of_sight_without_rendering() {
+        let world = default_world();
+        let origin = Tuple4::point(0.0, 0.0, -5.0);
+
+        assert!(!world.line_of_sight(origin, Tuple4::point(0.0, 0.0, 5.0)));
+        assert!(world.line_of_sight(origin, Tuple4::point(0.0, 5.0, -5.0)));
+        assert!(world.line_of_sight(origin, origin));
+        assert!(world.line_of_sight(Tuple4::point(0.0, 0.0, 0.0), Tuple4::point(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_finding_the_closest_point_without_rendering() {
+        let world = default_world();
+
+        let (closest, object) = world.closest_point(Tuple4::point(0.0, 3.0, 0.0)).unwrap();
+
+        assert!((closest - Tuple4::point(0.0, 1.0, 0.0)).magnitude() < EPSILON);
+        assert_eq!(object, &world.get_objects()[0]);
+        assert!(World::new()
+            .closest_point(Tuple4::point(0.0, 0.0, -5.0))
+            .is_none());
+    }
+}
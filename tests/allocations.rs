@@ -0,0 +1,73 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use ray_tracer_rs::{
+    camera::turntable,
+    presets::PRESETS,
+    settings::{Integrator, RenderSettings},
+    tuple::Tuple4,
+};
+
+/// Counts allocations made by the current thread, so tests running in
+/// parallel do not see each other's.
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn test_shading_frames_does_not_allocate() {
+    let integrators = [
+        Integrator::Whitted,
+        Integrator::Preview,
+        Integrator::PathTracer {
+            samples: 2,
+            max_bounces: 3,
+        },
+    ];
+
+    for (name, preset) in PRESETS {
+        for integrator in integrators {
+            let (mut world, mut camera) = preset(8, 4);
+            world.set_settings(RenderSettings {
+                integrator,
+                ..RenderSettings::default()
+            });
+            let frames = turntable(Tuple4::point(0.0, 1.0, 0.0), 6.0, 0.3, 4);
+            world.color_at(&camera.ray_for_pixel(0, 0));
+
+            let before = allocations();
+            for view in frames {
+                camera.set_transform(view);
+                for y in 0..camera.get_vsize() {
+                    for x in 0..camera.get_hsize() {
+                        world.color_at(&camera.ray_for_pixel(x, y));
+                    }
+                }
+            }
+
+            assert_eq!(allocations() - before, 0, "{} {:?}", name, integrator);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::{env, fs, path::PathBuf};
+
+use ray_tracer_rs::{
+    canvas::Canvas,
+    float::Float,
+    ppm::{PPMDecoder, PPMEncoder},
+    presets::PRESETS,
+};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 32;
+#[cfg(not(feature = "f32"))]
+const MAX_ERROR: Float = 2.0 / 255.0;
+#[cfg(not(feature = "f32"))]
+const MEAN_ERROR: Float = 0.5 / 255.0;
+
+// The references are rendered in f64. In f32 a ray grazing the edge of a
+// wall can hit or miss depending on rounding, so single pixels may flip
+// while the image as a whole still matches.
+#[cfg(feature = "f32")]
+const MAX_ERROR: Float = 1.0;
+#[cfg(feature = "f32")]
+const MEAN_ERROR: Float = 0.5 / 255.0;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.ppm", name))
+}
+
+fn encode(canvas: &Canvas) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    PPMEncoder::new(&mut bytes).write(canvas).unwrap();
+
+    bytes
+}
+
+fn decode(mut bytes: &[u8]) -> Canvas {
+    PPMDecoder::new(&mut bytes).read().unwrap()
+}
+
+/// Renders every preset and compares it against its reference image. Run with
+/// `UPDATE_GOLDEN=1` to rewrite the references after an intended change.
+#[test]
+fn test_presets_match_golden_images() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+
+    for (name, preset) in PRESETS {
+        let (world, camera) = preset(WIDTH, HEIGHT);
+        let rendered = encode(&camera.render(&world));
+        let path = golden_path(name);
+
+        if update {
+            fs::write(&path, &rendered).unwrap();
+            continue;
+        }
+
+        let expected = fs::read(&path).unwrap_or_else(|_| panic!("missing {}", path.display()));
+        match decode(&rendered).diff(&decode(&expected)) {
+            Some(diff) if diff.max <= MAX_ERROR && diff.mean <= MEAN_ERROR => {}
+            diff => failures.push(format!("{}: {:?}", name, diff)),
+        }
+    }
+
+    assert!(failures.is_empty(), "golden mismatches: {:?}", failures);
+}
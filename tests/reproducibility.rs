@@ -0,0 +1,107 @@
+use std::thread;
+
+use ray_tracer_rs::{
+    camera::{Camera, Projection},
+    canvas::Canvas,
+    color::Color,
+    film::{Film, Filter},
+    float::{consts::PI, Float},
+    lights::PointLight,
+    matrix::Matrix4x4,
+    ray::Ray,
+    sphere::Sphere,
+    tuple::Tuple4,
+};
+
+const THREADS: usize = 4;
+
+fn camera() -> Camera {
+    let mut camera = Camera::new(48, 32, Projection::Perspective(PI / 3.0));
+    camera.set_transform(Matrix4x4::view_transform(
+        Tuple4::point(0.0, 1.0, -5.0),
+        Tuple4::point(0.0, 0.0, 0.0),
+        Tuple4::vector(0.0, 1.0, 0.0),
+    ));
+    camera.set_aperture(0.2);
+    camera.set_focal_distance(5.0);
+    camera.set_lens_samples(8);
+    camera.set_filter(Filter::Mitchell {
+        radius: 2.0,
+        b: 1.0 / 3.0,
+        c: 1.0 / 3.0,
+    });
+
+    camera
+}
+
+fn shade(sphere: &Sphere, light: PointLight) -> impl Fn(&Ray) -> Color + Sync + '_ {
+    move |ray| {
+        let xs = sphere.intersect(ray);
+        match xs.hit() {
+            Some(hit) => {
+                let point = ray.position(hit.t);
+                let normal = hit.sphere.normal_at(point);
+                let eye = -1.0 * ray.direction;
+                hit.sphere
+                    .get_material()
+                    .lighting(hit.sphere, light, point, eye, normal, false)
+            }
+            None => Color::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+fn render_threaded<F: Fn(&Ray) -> Color + Sync>(camera: &Camera, shade: &F) -> Canvas {
+    let rows: Vec<Vec<Vec<(Float, Float, Color)>>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                scope.spawn(move || {
+                    (t..camera.get_vsize())
+                        .step_by(THREADS)
+                        .map(|y| {
+                            (0..camera.get_hsize())
+                                .map(|x| camera.pixel_samples(x, y, shade))
+                                .collect::<Vec<_>>()
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut rows = vec![Vec::new(); camera.get_vsize()];
+        for (t, handle) in handles.into_iter().enumerate() {
+            for (i, row) in handle.join().unwrap().into_iter().enumerate() {
+                rows[t + i * THREADS] = row;
+            }
+        }
+        rows
+    });
+
+    let mut film = Film::new(camera.get_hsize(), camera.get_vsize(), camera.get_filter());
+    for (x, y, color) in rows.into_iter().flatten().flatten() {
+        film.add_sample(x, y, color);
+    }
+
+    film.to_canvas()
+}
+
+#[test]
+fn test_single_and_multi_threaded_renders_are_bit_identical() {
+    let camera = camera();
+    let mut sphere = Sphere::new();
+    sphere.set_transform(Matrix4x4::scaling(1.5, 1.5, 1.5));
+    let light = PointLight::new(Tuple4::point(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+    let shade = shade(&sphere, light);
+
+    let serial = camera.render_with(&shade);
+    let threaded = render_threaded(&camera, &shade);
+
+    let serial: Vec<_> = serial.into_iter().collect();
+    let threaded: Vec<_> = threaded.into_iter().collect();
+    assert_eq!(serial.len(), threaded.len());
+    for (a, b) in serial.iter().zip(threaded.iter()) {
+        assert_eq!(a.r.to_bits(), b.r.to_bits());
+        assert_eq!(a.g.to_bits(), b.g.to_bits());
+        assert_eq!(a.b.to_bits(), b.b.to_bits());
+    }
+}